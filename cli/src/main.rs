@@ -0,0 +1,98 @@
+//! Command-line interface for generating crates from OpenAPI specs.
+
+use paperclip_openapi::source::SourceMap;
+use paperclip_openapi::v2::{
+    self, codegen::CrateMeta, loader::DirLoader, DefaultEmitter, DefaultSchema, Emitter,
+    EmitterState,
+};
+use paperclip_openapi::{PaperClipResult, Spec};
+use structopt::StructOpt;
+
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process;
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "paperclip",
+    about = "Generate a Rust crate from an OpenAPI spec (v2 or v3)."
+)]
+struct Opt {
+    /// Path to the spec (JSON or YAML). Use `-` to read it from stdin.
+    #[structopt(parse(from_os_str))]
+    spec: PathBuf,
+    /// Output directory for the generated crate.
+    #[structopt(short = "o", long = "out", parse(from_os_str))]
+    output: PathBuf,
+    /// Separator for namespaces in definition names.
+    #[structopt(long = "ns-sep", default_value = ".")]
+    ns_sep: String,
+    /// Name of the generated crate (defaults to the name of the output directory).
+    #[structopt(long = "name")]
+    name: Option<String>,
+}
+
+/// Reads the spec document from the given path (or stdin, if it's `-`).
+fn read_spec(path: &Path) -> io::Result<Vec<u8>> {
+    if path.to_str() == Some("-") {
+        let mut buf = vec![];
+        io::stdin().read_to_end(&mut buf)?;
+        Ok(buf)
+    } else {
+        fs::read(path)
+    }
+}
+
+/// Loads the spec from the given document (read from the given path). References to
+/// other documents are loaded relative to the spec's parent directory (or the current
+/// directory, if it's read from stdin).
+fn load_spec(path: &Path, buf: &[u8]) -> PaperClipResult<Spec<DefaultSchema>> {
+    let dir = match path.parent() {
+        Some(p) if path.to_str() != Some("-") => p,
+        _ => Path::new(""),
+    };
+
+    paperclip_openapi::from_slice_with_loader(buf, &DirLoader::new(dir))
+}
+
+/// Resolves the spec and generates the crate based on the given options.
+fn generate(spec: Spec<DefaultSchema>, opt: Opt) -> PaperClipResult<()> {
+    let api: v2::Api<DefaultSchema> = spec.resolve()?.into();
+
+    fs::create_dir_all(&opt.output)?;
+
+    let mut state = EmitterState::default();
+    state.working_dir = opt.output;
+    state.ns_sep = opt.ns_sep;
+    state.crate_meta = Some(CrateMeta {
+        name: opt.name,
+        ..Default::default()
+    });
+
+    let emitter = DefaultEmitter::from(state);
+    emitter.generate(&api)
+}
+
+fn main() {
+    env_logger::init();
+    let opt = Opt::from_args();
+    let buf = match read_spec(&opt.spec) {
+        Ok(b) => b,
+        Err(e) => {
+            eprintln!("Failed to read {}: {}", opt.spec.display(), e);
+            process::exit(1);
+        }
+    };
+
+    let result = load_spec(&opt.spec, &buf).and_then(|spec| generate(spec, opt));
+    if let Err(e) = result {
+        // Errors caused by some node in the spec are shown along with its snippet.
+        match SourceMap::new(buf) {
+            Some(s) => eprintln!("{}", s.render(&e)),
+            None => eprintln!("{:#}", e),
+        }
+
+        process::exit(1);
+    }
+}
@@ -1,4 +1,3 @@
-#![feature(proc_macro_diagnostic)]
 #![recursion_limit = "512"]
 
 extern crate proc_macro;
@@ -8,14 +7,18 @@ use quote::quote;
 use syn::{Data, DeriveInput, Fields, FieldsNamed};
 
 fn call_site_error_with_msg(msg: &str) -> TokenStream {
-    Span::call_site().error(msg);
-    (quote! {}).into()
+    syn::Error::new(Span::call_site().into(), msg)
+        .to_compile_error()
+        .into()
 }
 
 /// Converts your struct to support deserializing from an OpenAPI v2
 /// [Schema](https://github.com/OAI/OpenAPI-Specification/blob/master/versions/2.0.md#schemaObject)
 /// object. This adds the necessary fields (in addition to your own fields) and implements the
 /// `Schema` trait for parsing and codegen.
+///
+/// If your struct also derives `Serialize`, then the added fields are serialized
+/// along with yours (empty fields are skipped).
 #[proc_macro_attribute]
 pub fn api_v2_schema(_attr: TokenStream, input: TokenStream) -> TokenStream {
     let mut item_ast: DeriveInput = match syn::parse(input) {
@@ -41,23 +44,46 @@ pub fn api_v2_schema(_attr: TokenStream, input: TokenStream) -> TokenStream {
 
     let default_fields: FieldsNamed = syn::parse2(quote! {
         {
-            #[serde(rename = "$ref")]
+            #[serde(rename = "$ref", skip_serializing_if = "Option::is_none")]
             pub reference: Option<String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
             pub title: Option<String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
             pub description: Option<String>,
-            #[serde(rename = "type")]
+            #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
             pub data_type: Option<paperclip_openapi::v2::models::DataType>,
+            #[serde(skip_serializing_if = "Option::is_none")]
             pub format: Option<paperclip_openapi::v2::models::DataTypeFormat>,
+            #[serde(skip_serializing_if = "Option::is_none")]
             pub properties: Option<std::collections::BTreeMap<String, paperclip_openapi::v2::im::ArcRwLock<#name>>>,
+            #[serde(skip_serializing_if = "Option::is_none")]
             pub items: Option<paperclip_openapi::v2::im::ArcRwLock<#name>>,
-            #[serde(rename = "additionalProperties")]
+            #[serde(rename = "enum", default, skip_serializing_if = "Vec::is_empty")]
+            pub enum_: Vec<paperclip_openapi::v2::models::EnumValue>,
+            #[serde(rename = "allOf", default, skip_serializing_if = "Vec::is_empty")]
+            pub all_of: Vec<paperclip_openapi::v2::im::ArcRwLock<#name>>,
+            #[serde(rename = "oneOf", default, skip_serializing_if = "Vec::is_empty")]
+            pub one_of: Vec<paperclip_openapi::v2::im::ArcRwLock<#name>>,
+            #[serde(rename = "anyOf", default, skip_serializing_if = "Vec::is_empty")]
+            pub any_of: Vec<paperclip_openapi::v2::im::ArcRwLock<#name>>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            pub not: Option<paperclip_openapi::v2::im::ArcRwLock<#name>>,
+            #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+            pub nullable: bool,
+            #[serde(rename = "additionalProperties", skip_serializing_if = "Option::is_none")]
             pub extra_props: Option<paperclip_openapi::v2::im::ArcRwLock<#name>>,
-            #[serde(default)]
-            pub required: std::collections::HashSet<String>,
+            #[serde(default, skip_serializing_if = "std::collections::BTreeSet::is_empty")]
+            pub required: std::collections::BTreeSet<String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            pub discriminator: Option<String>,
             #[serde(skip)]
             name: Option<String>,
             #[serde(skip)]
             cyclic: bool,
+            #[serde(skip)]
+            subtypes: Vec<String>,
+            #[serde(skip)]
+            alias_target: Option<paperclip_openapi::v2::im::ArcRwLock<#name>>,
         }
     })
     .expect("parsing schema field?");
@@ -87,6 +113,31 @@ pub fn api_v2_schema(_attr: TokenStream, input: TokenStream) -> TokenStream {
                 self.cyclic
             }
 
+            #[inline]
+            fn discriminator(&self) -> Option<&str> {
+                self.discriminator.as_ref().map(String::as_str)
+            }
+
+            #[inline]
+            fn subtypes(&self) -> &[String] {
+                &self.subtypes
+            }
+
+            #[inline]
+            fn add_subtype(&mut self, name: &str) {
+                self.subtypes.push(name.into());
+            }
+
+            #[inline]
+            fn alias_target(&self) -> Option<&paperclip_openapi::v2::im::ArcRwLock<Self>> {
+                self.alias_target.as_ref()
+            }
+
+            #[inline]
+            fn set_alias_target(&mut self, target: paperclip_openapi::v2::im::ArcRwLock<Self>) {
+                self.alias_target = Some(target);
+            }
+
             #[inline]
             fn description(&self) -> Option<&str> {
                 self.description.as_ref().map(String::as_str)
@@ -107,6 +158,15 @@ pub fn api_v2_schema(_attr: TokenStream, input: TokenStream) -> TokenStream {
                 self.format.as_ref()
             }
 
+            #[inline]
+            fn enum_variants(&self) -> Option<&[paperclip_openapi::v2::models::EnumValue]> {
+                if self.enum_.is_empty() {
+                    None
+                } else {
+                    Some(&self.enum_)
+                }
+            }
+
             #[inline]
             fn items(&self) -> Option<&paperclip_openapi::v2::im::ArcRwLock<Self>> {
                 self.items.as_ref()
@@ -117,6 +177,52 @@ pub fn api_v2_schema(_attr: TokenStream, input: TokenStream) -> TokenStream {
                 self.items.as_mut()
             }
 
+            #[inline]
+            fn all_of(&self) -> Option<&[paperclip_openapi::v2::im::ArcRwLock<Self>]> {
+                if self.all_of.is_empty() {
+                    None
+                } else {
+                    Some(&self.all_of)
+                }
+            }
+
+            #[inline]
+            fn all_of_mut(&mut self) -> Option<&mut [paperclip_openapi::v2::im::ArcRwLock<Self>]> {
+                if self.all_of.is_empty() {
+                    None
+                } else {
+                    Some(&mut self.all_of)
+                }
+            }
+
+            #[inline]
+            fn one_of(&self) -> Option<&[paperclip_openapi::v2::im::ArcRwLock<Self>]> {
+                if self.one_of.is_empty() {
+                    None
+                } else {
+                    Some(&self.one_of)
+                }
+            }
+
+            #[inline]
+            fn any_of(&self) -> Option<&[paperclip_openapi::v2::im::ArcRwLock<Self>]> {
+                if self.any_of.is_empty() {
+                    None
+                } else {
+                    Some(&self.any_of)
+                }
+            }
+
+            #[inline]
+            fn not(&self) -> Option<&paperclip_openapi::v2::im::ArcRwLock<Self>> {
+                self.not.as_ref()
+            }
+
+            #[inline]
+            fn is_nullable(&self) -> bool {
+                self.nullable
+            }
+
             #[inline]
             fn additional_properties(&self) -> Option<&paperclip_openapi::v2::im::ArcRwLock<Self>> {
                 self.extra_props.as_ref()
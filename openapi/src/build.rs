@@ -0,0 +1,212 @@
+//! Generating code from build scripts.
+//!
+//! `compile` generates the code for a spec into a single file (with inline modules)
+//! in `OUT_DIR`, which can then be included in the crate:
+//!
+//! ```rust,no_run
+//! // In `main` of build.rs
+//! paperclip_openapi::build::compile("specs/pet.yaml").unwrap();
+//! ```
+//!
+//! ```rust,ignore
+//! // lib.rs
+//! #[macro_use]
+//! extern crate serde_derive;
+//!
+//! include!(concat!(env!("OUT_DIR"), "/pet.rs"));
+//! ```
+//!
+//! The generated code needs `serde`, `serde_derive` and `reqwest` (0.9) in the
+//! crate's dependencies. By default, paths to the generated types start from
+//! the crate root (`crate::`), so the file should be included there (set
+//! `EmitterState::mod_prefix` and use `compile_with_state` otherwise).
+//! The same goes for specs whose definitions are generated into several
+//! top-level modules (set `EmitterState::root_module` for helpers).
+
+use crate::error::PaperClipResult;
+use crate::v2::codegen::{DefaultEmitter, Emitter, EmitterState, MemoryTree};
+use crate::v2::loader::{DirLoader, RefLoader};
+use crate::v2::{Api, DefaultSchema};
+use crate::SpecFormat;
+use serde_json::Value;
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Generates code for the spec (v2 or v3) at the given path into `OUT_DIR` and
+/// returns the path to the generated file, which is named after the spec
+/// (say, `pet.rs` for `specs/pet.yaml`).
+///
+/// This also tells cargo to rerun the build script if the spec (or any
+/// document referred by it) changes.
+pub fn compile<P>(spec: P) -> PaperClipResult<PathBuf>
+where
+    P: AsRef<Path>,
+{
+    compile_with_state(spec, EmitterState::default())
+}
+
+/// Same as `compile`, but uses the given emitter state.
+///
+/// **NOTE:** The working directory, sink and crate metadata of the state are
+/// ignored, since the code is generated in memory and flattened into a single file.
+pub fn compile_with_state<P>(spec: P, mut state: EmitterState) -> PaperClipResult<PathBuf>
+where
+    P: AsRef<Path>,
+{
+    let spec_path = spec.as_ref();
+    let out_dir = env::var_os("OUT_DIR").map(PathBuf::from).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "OUT_DIR is not set (are we in a build script?)",
+        )
+    })?;
+
+    println!("cargo:rerun-if-changed={}", spec_path.display());
+    let dir = spec_path.parent().unwrap_or_else(|| Path::new(""));
+    let loader = TrackingLoader {
+        inner: DirLoader::new(dir),
+        loaded: RefCell::new(BTreeSet::new()),
+    };
+
+    let buf = fs::read(spec_path)?;
+    let spec = crate::spec_from_buf(buf, SpecFormat::from_path(spec_path), &loader);
+    // We need these even if the spec is invalid, so that fixing
+    // the referred documents triggers another build.
+    for path in &*loader.loaded.borrow() {
+        let mut full_path = dir.to_path_buf();
+        full_path.extend(path.split('/'));
+        println!("cargo:rerun-if-changed={}", full_path.display());
+    }
+
+    let api: Api<DefaultSchema> = spec?.resolve()?.into();
+    let tree = Arc::new(MemoryTree::default());
+    state.working_dir = PathBuf::new();
+    state.sink = tree.clone();
+    state.crate_meta = None;
+    // The state is shared with the emitter, so we can get the modules it has declared.
+    let modules = state.clone();
+    DefaultEmitter::from(state).generate(&api)?;
+
+    let name = spec_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| String::from("api"));
+    let out_path = out_dir.join(name + ".rs");
+    fs::write(
+        &out_path,
+        flatten(&tree.files(), &modules.module_children())?,
+    )?;
+    Ok(out_path)
+}
+
+/// Loader which keeps track of the documents it has been asked to load.
+struct TrackingLoader {
+    inner: DirLoader,
+    loaded: RefCell<BTreeSet<String>>,
+}
+
+impl RefLoader for TrackingLoader {
+    fn load(&self, path: &str) -> PaperClipResult<Value> {
+        self.loaded.borrow_mut().insert(path.into());
+        self.inner.load(path)
+    }
+}
+
+/// Modules (relative paths) declared by the emitter and the names of their children.
+type ModuleTree = BTreeMap<PathBuf, BTreeSet<String>>;
+
+/// Flattens the given (generated) files into a single module by inlining
+/// the modules (and their children) declared by the emitter. If the working
+/// directory is the root module (i.e., it has `mod.rs`), then its contents
+/// are at the top of the file. Otherwise, the top-level modules are inlined.
+fn flatten(files: &BTreeMap<PathBuf, String>, modules: &ModuleTree) -> PaperClipResult<String> {
+    let mut contents = String::new();
+    let root = Path::new("");
+    match files.get(&root.join("mod.rs")) {
+        Some(module) => write_module_body(files, modules, root, module, &mut contents),
+        None => {
+            let top_mods = modules.keys().filter(|p| p.components().count() == 1);
+            for path in top_mods {
+                inline_module(files, modules, path, &mut contents);
+            }
+        }
+    }
+
+    if contents.trim().is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "No code has been generated for the spec",
+        )
+        .into());
+    }
+
+    Ok(contents)
+}
+
+/// Writes the module at the given path as an inline module, along with its children.
+fn inline_module(
+    files: &BTreeMap<PathBuf, String>,
+    modules: &ModuleTree,
+    path: &Path,
+    out: &mut String,
+) {
+    let module = match files
+        .get(&path.with_extension("rs"))
+        .or_else(|| files.get(&path.join("mod.rs")))
+    {
+        Some(m) => m,
+        None => {
+            warn!("Skipping missing module {:?}", path);
+            return;
+        }
+    };
+
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy())
+        .unwrap_or_default();
+    out.push_str("pub mod ");
+    out.push_str(&name);
+    out.push_str(" {\n");
+    write_module_body(files, modules, path, module, out);
+    out.push_str("}\n");
+}
+
+/// Writes the contents of the module at the given path, along with its children.
+fn write_module_body(
+    files: &BTreeMap<PathBuf, String>,
+    modules: &ModuleTree,
+    path: &Path,
+    module: &str,
+    out: &mut String,
+) {
+    // Declarations of the child modules (`pub mod foo;`) are replaced with the modules.
+    let mut children = modules.get(path).cloned().unwrap_or_default();
+    for line in module.lines() {
+        let child = children
+            .iter()
+            .find(|c| line.trim() == format!("pub mod {};", c))
+            .cloned();
+        match child {
+            Some(c) => {
+                children.remove(&c);
+                inline_module(files, modules, &path.join(c), out);
+            }
+            None => {
+                out.push_str(line);
+                out.push('\n');
+            }
+        }
+    }
+
+    // Children which weren't declared in the module (if any).
+    for child in children {
+        inline_module(files, modules, &path.join(child), out);
+    }
+}
@@ -1,3 +1,8 @@
+#[cfg(feature = "v2")]
+use crate::source::SourceLocation;
+
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
 use std::io;
 use std::path::PathBuf;
 
@@ -15,66 +20,343 @@ macro_rules! impl_err_from {
 pub type PaperClipResult<T> = Result<T, PaperClipError>;
 
 /// Global error which encapsulates all related errors.
-#[derive(Debug, Fail)]
+///
+/// Errors from the resolver and the emitter are usually wrapped in `Located`
+/// (JSON pointer to the offending node) and some context (`InDefinition`,
+/// `InPath` or `InOperation`), in that order. Use `root` (or `into_inner`)
+/// for getting the actual error and `location` for getting its location.
+///
+/// Wrappers (and I/O, JSON and YAML errors) expose the errors they wrap as
+/// sources, so their messages don't include the inner messages. Use the
+/// alternate format (`{:#}`) for a message which includes the whole chain.
+#[derive(Debug)]
 pub enum PaperClipError {
     /// Failed to resolve the schema because an invalid URI was provided for
     /// `$ref` field.
     ///
-    /// Currently, we only support `#/definitions/YourType` in `$ref` field.
-    #[fail(
-        display = "Invalid $ref URI: {}. Only relative URIs for definitions are supported right now.",
-        _0
-    )]
+    /// Currently, we only support JSON pointers in the same document
+    /// or in documents with relative paths (`common.yaml#/definitions/YourType`).
     InvalidRefURI(String),
+    /// The JSON pointer in some `$ref` field doesn't point to anything.
+    UnresolvedReference(String),
+    /// Some `$ref` field (other than definitions) refers to itself.
+    ReferenceCycle(String),
+    /// The document referred by some `$ref` field cannot be loaded.
+    MissingDocument(String),
     /// The given schema object is an array, but the `items` field is missing.
-    #[fail(display = "Mising item schema for array: {:?}", _0)]
     MissingArrayItem(Option<String>),
     /// The name for the given definition is missing or invalid.
-    #[fail(display = "Invalid name for definition")]
     InvalidDefinitionName,
     /// A valid path cannot be obtained for the given defition.
-    #[fail(display = "Invalid path for definition: {:?}", _0)]
     InvalidDefinitionPath(PathBuf),
     /// A definition has been referenced but it's missing.
-    #[fail(display = "Definition missing: {}", _0)]
     MissingDefinition(String),
+    /// A definition (directly or indirectly) refers to itself through
+    /// root-level `$ref` fields (i.e., an alias of itself).
+    CyclicDefinitionAlias(String),
+    /// A definition (directly or indirectly) inherits itself through `allOf`.
+    CyclicAllOf(String),
     /// If a parameter uses a schema, then we expect it to exist in
     /// the definition (for now).
-    #[fail(
-        display = "Parameter {:?} in path {:?} defines a new schema, which is unsupported at this point.",
-        _0, _1
-    )]
     UnsupportedParameterDefinition(String, String),
     /// If a parameter specifies body, then schema must be specified.
-    #[fail(
-        display = "Parameter {:?} in path {:?} is a body but the schema is missing",
-        _0, _1
-    )]
     MissingSchemaForBodyParameter(String, String),
     /// If a parameter doesn't specify a body, then it must have a type.
-    #[fail(display = "Parameter {:?} in path {:?} must have a type", _0, _1)]
     MissingParameterType(String, String),
     /// The type of this parameter is not known.
-    #[fail(
-        display = "Parameter {:?} in path {:?} doesn't have a known type",
-        _0, _1
-    )]
     UnknownParameterType(String, String),
+    /// Some placeholder in a path template doesn't have a corresponding
+    /// path parameter.
+    MissingPathParameter(String, String),
+    /// Some path parameter is not required.
+    OptionalPathParameter(String, String),
+    /// `multi` collection format is used for a parameter which is neither in query nor in form.
+    InvalidCollectionFormat(String, String),
+    /// The key of some response in an operation is neither a valid status code
+    /// (`100` to `599`), nor a range (`1XX` to `5XX`), nor `default`.
+    InvalidStatusCode(String, String, String),
+    /// Some media type in `consumes` or `produces` (of the root object or
+    /// some operation) is invalid.
+    InvalidMediaType(String, String),
+    /// Definitions have been generated into several top-level modules, and
+    /// the root module (for helpers) hasn't been chosen.
+    MultipleRootModules(Vec<String>),
+    /// The given root module is not one of the generated top-level modules.
+    MissingRootModule(String),
+    /// The spec doesn't specify a supported OpenAPI version.
+    UnsupportedSpecVersion(Option<String>),
+    /// Some schema (in a v3 spec) uses a keyword which isn't supported.
+    UnsupportedSchemaKeyword(String),
+    /// Some error caused by the node at the given location in the spec.
+    #[cfg(feature = "v2")]
+    Located(SourceLocation, Box<PaperClipError>),
+    /// Some error occurred while processing the definition with the given name.
+    InDefinition(String, Box<PaperClipError>),
+    /// Some error occurred while processing the given path (but not in
+    /// any of its operations).
+    InPath(String, Box<PaperClipError>),
+    /// Some error occurred while processing the operation with the given
+    /// (uppercase) method in the given path.
+    InOperation(String, String, Box<PaperClipError>),
     /// I/O errors.
-    #[fail(display = "I/O error: {}", _0)]
     Io(io::Error),
     /// JSON coding errors.
-    #[fail(display = "JSON error: {}", _0)]
     Json(serde_json::Error),
     /// YAML coding errors.
-    #[fail(display = "YAML error: {}", _0)]
     Yaml(serde_yaml::Error),
     #[cfg(feature = "codegen-fmt")]
     /// Errors from rustfmt.
-    #[fail(display = "Rustfmt formatting error: {}", _0)]
     RustFmt(rustfmt_nightly::ErrorKind),
 }
 
+impl PaperClipError {
+    /// Returns the actual error (skipping the location and context, if any).
+    pub fn root(&self) -> &PaperClipError {
+        match self {
+            #[cfg(feature = "v2")]
+            PaperClipError::Located(_, e) => e.root(),
+            PaperClipError::InDefinition(_, e)
+            | PaperClipError::InPath(_, e)
+            | PaperClipError::InOperation(_, _, e) => e.root(),
+            e => e,
+        }
+    }
+
+    /// Returns the actual error (without the location and context, if any).
+    pub fn into_inner(self) -> PaperClipError {
+        match self {
+            #[cfg(feature = "v2")]
+            PaperClipError::Located(_, e) => e.into_inner(),
+            PaperClipError::InDefinition(_, e)
+            | PaperClipError::InPath(_, e)
+            | PaperClipError::InOperation(_, _, e) => e.into_inner(),
+            e => e,
+        }
+    }
+
+    /// Adds context to this error using the given function. If this error
+    /// has a location, then the context is added inside it (so that the
+    /// location stays at the top).
+    pub(crate) fn context<F>(self, f: F) -> PaperClipError
+    where
+        F: FnOnce(Box<PaperClipError>) -> PaperClipError,
+    {
+        match self {
+            #[cfg(feature = "v2")]
+            PaperClipError::Located(loc, e) => PaperClipError::Located(loc, Box::new(f(e))),
+            e => f(Box::new(e)),
+        }
+    }
+}
+
+#[cfg(feature = "v2")]
+impl PaperClipError {
+    /// Returns the location of the node which caused this error, if it's known.
+    pub fn location(&self) -> Option<&SourceLocation> {
+        match self {
+            PaperClipError::Located(loc, _) => Some(loc),
+            _ => None,
+        }
+    }
+
+    /// Prefixes the given JSON pointer to the location of this error
+    /// (or sets it, if this error doesn't have a location).
+    pub(crate) fn at(self, pointer: &str) -> PaperClipError {
+        match self {
+            PaperClipError::Located(mut loc, e) => {
+                loc.pointer.insert_str(0, pointer);
+                PaperClipError::Located(loc, e)
+            }
+            e => PaperClipError::Located(
+                SourceLocation {
+                    pointer: pointer.into(),
+                    position: None,
+                },
+                Box::new(e),
+            ),
+        }
+    }
+}
+
+/// Extension for attaching locations and context to errors as they
+/// bubble up from the nested nodes of the spec.
+pub(crate) trait ResultExt<T> {
+    /// Prefixes the JSON pointer returned by the given function to the
+    /// location of the error.
+    #[cfg(feature = "v2")]
+    fn at<F>(self, pointer: F) -> PaperClipResult<T>
+    where
+        F: FnOnce() -> String;
+
+    /// Adds context to the error (see `PaperClipError::context`).
+    fn context<F>(self, f: F) -> PaperClipResult<T>
+    where
+        F: FnOnce(Box<PaperClipError>) -> PaperClipError;
+}
+
+impl<T> ResultExt<T> for PaperClipResult<T> {
+    #[cfg(feature = "v2")]
+    fn at<F>(self, pointer: F) -> PaperClipResult<T>
+    where
+        F: FnOnce() -> String,
+    {
+        self.map_err(|e| e.at(&pointer()))
+    }
+
+    fn context<F>(self, f: F) -> PaperClipResult<T>
+    where
+        F: FnOnce(Box<PaperClipError>) -> PaperClipError,
+    {
+        self.map_err(|e| e.context(f))
+    }
+}
+
+impl Display for PaperClipError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            PaperClipError::InvalidRefURI(uri) => write!(
+                f,
+                "Invalid $ref URI: {}. Only JSON pointers (optionally prefixed with relative paths) are supported right now.",
+                uri
+            ),
+            PaperClipError::UnresolvedReference(uri) => {
+                write!(f, "Cannot resolve reference: {}", uri)
+            }
+            PaperClipError::ReferenceCycle(uri) => write!(f, "Reference cycle detected in {}", uri),
+            PaperClipError::MissingDocument(path) => write!(f, "Missing document: {}", path),
+            PaperClipError::MissingArrayItem(name) => {
+                write!(f, "Mising item schema for array: {:?}", name)
+            }
+            PaperClipError::InvalidDefinitionName => f.write_str("Invalid name for definition"),
+            PaperClipError::InvalidDefinitionPath(path) => {
+                write!(f, "Invalid path for definition: {:?}", path)
+            }
+            PaperClipError::MissingDefinition(name) => write!(f, "Definition missing: {}", name),
+            PaperClipError::CyclicDefinitionAlias(name) => {
+                write!(f, "Definition {} is an alias of itself", name)
+            }
+            PaperClipError::CyclicAllOf(name) => {
+                write!(f, "Definition {} inherits itself through allOf", name)
+            }
+            PaperClipError::UnsupportedParameterDefinition(name, path) => write!(
+                f,
+                "Parameter {:?} in path {:?} defines a new schema, which is unsupported at this point.",
+                name, path
+            ),
+            PaperClipError::MissingSchemaForBodyParameter(name, path) => write!(
+                f,
+                "Parameter {:?} in path {:?} is a body but the schema is missing",
+                name, path
+            ),
+            PaperClipError::MissingParameterType(name, path) => write!(
+                f,
+                "Parameter {:?} in path {:?} must have a type",
+                name, path
+            ),
+            PaperClipError::UnknownParameterType(name, path) => write!(
+                f,
+                "Parameter {:?} in path {:?} doesn't have a known type",
+                name, path
+            ),
+            PaperClipError::MissingPathParameter(name, path) => write!(
+                f,
+                "Path {:?} has a placeholder for parameter {:?}, but the parameter is missing",
+                path, name
+            ),
+            PaperClipError::OptionalPathParameter(name, path) => write!(
+                f,
+                "Parameter {:?} in path {:?} must be required",
+                name, path
+            ),
+            PaperClipError::InvalidCollectionFormat(name, path) => write!(
+                f,
+                "Parameter {:?} in path {:?} can't use 'multi' collection format (only allowed for query and form parameters)",
+                name, path
+            ),
+            PaperClipError::InvalidStatusCode(code, method, path) => write!(
+                f,
+                "Invalid status code {:?} in the responses of {} {:?}",
+                code, method, path
+            ),
+            PaperClipError::InvalidMediaType(media, location) => {
+                write!(f, "Invalid media type {:?} in {}", media, location)
+            }
+            PaperClipError::MultipleRootModules(mods) => write!(
+                f,
+                "Found multiple root modules ({}). Set `EmitterState::root_module` to choose one for helpers.",
+                mods.join(", ")
+            ),
+            PaperClipError::MissingRootModule(name) => {
+                write!(f, "Root module {:?} is not one of the generated modules", name)
+            }
+            PaperClipError::UnsupportedSpecVersion(version) => {
+                write!(f, "Unsupported OpenAPI version: {:?}", version)
+            }
+            PaperClipError::UnsupportedSchemaKeyword(key) => {
+                write!(f, "Unsupported keyword {:?} in schema", key)
+            }
+            #[cfg(feature = "v2")]
+            PaperClipError::Located(loc, e) if f.alternate() => {
+                write!(f, "{:#} (at {})", e, loc)
+            }
+            #[cfg(feature = "v2")]
+            PaperClipError::Located(loc, _) => write!(f, "At {}", loc),
+            PaperClipError::InDefinition(name, e) => {
+                write!(f, "In definition {}", name)?;
+                write_inner(f, &**e)
+            }
+            PaperClipError::InPath(path, e) => {
+                write!(f, "In path {:?}", path)?;
+                write_inner(f, &**e)
+            }
+            PaperClipError::InOperation(method, path, e) => {
+                write!(f, "In operation {} {:?}", method, path)?;
+                write_inner(f, &**e)
+            }
+            PaperClipError::Io(e) => {
+                f.write_str("I/O error")?;
+                write_inner(f, e)
+            }
+            PaperClipError::Json(e) => {
+                f.write_str("JSON error")?;
+                write_inner(f, e)
+            }
+            PaperClipError::Yaml(e) => {
+                f.write_str("YAML error")?;
+                write_inner(f, e)
+            }
+            #[cfg(feature = "codegen-fmt")]
+            PaperClipError::RustFmt(e) => write!(f, "Rustfmt formatting error: {}", e),
+        }
+    }
+}
+
+/// Writes the message of the inner error (and its chain) if the alternate
+/// format has been requested.
+fn write_inner(f: &mut Formatter, e: &dyn Display) -> fmt::Result {
+    if f.alternate() {
+        write!(f, ": {:#}", e)
+    } else {
+        Ok(())
+    }
+}
+
+impl Error for PaperClipError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            #[cfg(feature = "v2")]
+            PaperClipError::Located(_, e) => Some(&**e),
+            PaperClipError::InDefinition(_, e)
+            | PaperClipError::InPath(_, e)
+            | PaperClipError::InOperation(_, _, e) => Some(&**e),
+            PaperClipError::Io(e) => Some(e),
+            PaperClipError::Json(e) => Some(e),
+            PaperClipError::Yaml(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
 impl_err_from!(PaperClipError::io::Error > Io);
 impl_err_from!(PaperClipError::serde_json::Error > Json);
 impl_err_from!(PaperClipError::serde_yaml::Error > Yaml);
@@ -1,5 +1,3 @@
-#[macro_use]
-extern crate failure;
 extern crate lazy_static;
 #[macro_use]
 extern crate log;
@@ -8,8 +6,300 @@ extern crate paperclip_macros;
 #[macro_use]
 extern crate serde_derive;
 
+#[cfg(all(feature = "v2", feature = "codegen"))]
+pub mod build;
 mod error;
 #[cfg(feature = "v2")]
+pub mod source;
+#[cfg(feature = "v2")]
 pub mod v2;
+#[cfg(feature = "v3")]
+pub mod v3;
 
 pub use error::{PaperClipError, PaperClipResult};
+
+#[cfg(feature = "v2")]
+use crate::source::{Position, SourceLocation, SourceMap};
+#[cfg(feature = "v2")]
+use crate::v2::loader::{DirLoader, NoLoader, RefLoader};
+#[cfg(feature = "v2")]
+use serde::de::DeserializeOwned;
+#[cfg(feature = "v2")]
+use serde::Deserialize;
+#[cfg(feature = "v2")]
+use serde_json::Value;
+
+#[cfg(feature = "v2")]
+use std::fs;
+#[cfg(feature = "v2")]
+use std::io::Read;
+#[cfg(feature = "v2")]
+use std::path::Path;
+#[cfg(feature = "v2")]
+use std::sync::Arc;
+
+/// Format of a spec document.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SpecFormat {
+    Json,
+    Yaml,
+}
+
+/// An API spec of some supported OpenAPI version.
+#[cfg(feature = "v2")]
+#[derive(Clone, Debug)]
+#[allow(clippy::large_enum_variant)] // there's usually only one spec around.
+pub enum Spec<S> {
+    /// OpenAPI v2 spec.
+    V2(v2::Api<S>),
+    /// OpenAPI v3 spec.
+    #[cfg(feature = "v3")]
+    V3(v3::Api<S>),
+}
+
+#[cfg(feature = "v2")]
+impl<S: v2::Schema> Spec<S> {
+    /// Resolves the references in this spec (see `v2::Api::resolve` and
+    /// `v3::Api::resolve`).
+    pub fn resolve(self) -> PaperClipResult<Spec<S>> {
+        Ok(match self {
+            Spec::V2(api) => Spec::V2(api.resolve()?),
+            #[cfg(feature = "v3")]
+            Spec::V3(api) => Spec::V3(api.resolve()?),
+        })
+    }
+}
+
+/// Returns the v2 spec (converting the v3 spec, if needed) so that it can be used by the emitter.
+#[cfg(feature = "v2")]
+impl<S: v2::Schema> From<Spec<S>> for v2::Api<S> {
+    fn from(spec: Spec<S>) -> Self {
+        match spec {
+            Spec::V2(api) => api,
+            #[cfg(feature = "v3")]
+            Spec::V3(api) => api.into(),
+        }
+    }
+}
+
+/// Deserialize the spec from the given reader, detecting the OpenAPI version
+/// from the `swagger` (v2) or `openapi` (v3) field. Currently, this only supports
+/// JSON and YAML formats.
+#[cfg(feature = "v2")]
+pub fn from_reader<R, S>(reader: R) -> PaperClipResult<Spec<S>>
+where
+    R: Read,
+    for<'de> S: Deserialize<'de>,
+    S: v2::Schema,
+{
+    spec_from_buf(read_document(reader)?, None, &NoLoader)
+}
+
+/// Deserialize the spec (JSON or YAML) from the given bytes, detecting
+/// the OpenAPI version (see `from_reader`).
+#[cfg(feature = "v2")]
+pub fn from_slice<S>(buf: &[u8]) -> PaperClipResult<Spec<S>>
+where
+    for<'de> S: Deserialize<'de>,
+    S: v2::Schema,
+{
+    spec_from_buf(buf.to_vec(), None, &NoLoader)
+}
+
+/// Same as `from_slice`, but uses the given loader for loading documents referred
+/// in `$ref` fields (see `v2::loader`).
+#[cfg(feature = "v2")]
+pub fn from_slice_with_loader<S, L>(buf: &[u8], loader: &L) -> PaperClipResult<Spec<S>>
+where
+    for<'de> S: Deserialize<'de>,
+    S: v2::Schema,
+    L: RefLoader,
+{
+    spec_from_buf(buf.to_vec(), None, loader)
+}
+
+/// Deserialize the spec from the file at the given path, detecting the OpenAPI
+/// version (see `from_reader`). The format is chosen based on the file's extension
+/// (and detected from its contents if the extension is unknown). References to
+/// other documents are loaded relative to the file's parent directory.
+#[cfg(feature = "v2")]
+pub fn from_path<P, S>(path: P) -> PaperClipResult<Spec<S>>
+where
+    P: AsRef<Path>,
+    for<'de> S: Deserialize<'de>,
+    S: v2::Schema,
+{
+    let path = path.as_ref();
+    let loader = DirLoader::new(path.parent().unwrap_or_else(|| Path::new("")));
+    spec_from_buf(fs::read(path)?, SpecFormat::from_path(path), &loader)
+}
+
+/// Deserialize the spec from the given document, detecting the OpenAPI version.
+/// If the format is not given, then it's detected. The document is kept in
+/// the spec (see `v2::Api::source`) and load errors are located in it.
+#[cfg(feature = "v2")]
+pub(crate) fn spec_from_buf<S, L>(
+    buf: Vec<u8>,
+    format: Option<SpecFormat>,
+    loader: &L,
+) -> PaperClipResult<Spec<S>>
+where
+    for<'de> S: Deserialize<'de>,
+    S: v2::Schema,
+    L: RefLoader,
+{
+    let format = format.unwrap_or_else(|| SpecFormat::detect(&buf));
+    let value = value_from_slice(&buf, Some(format))
+        .map_err(|e| locate_load_error::<Value>(e, &buf, format))?;
+    match (value.get("swagger"), value.get("openapi")) {
+        (Some(_), _) => {
+            let mut api = v2::from_value_with_loader(value, loader)
+                .map_err(|e| locate_load_error::<v2::Api<S>>(e, &buf, format))?;
+            api.source = SourceMap::new(buf).map(Arc::new);
+            Ok(Spec::V2(api))
+        }
+        #[cfg(feature = "v3")]
+        (None, Some(_)) => {
+            let mut api = v3::from_value_with_loader(value, loader)
+                .map_err(|e| locate_load_error::<v3::Api<S>>(e, &buf, format))?;
+            api.source = SourceMap::new(buf).map(Arc::new);
+            Ok(Spec::V3(api))
+        }
+        (_, v) => Err(PaperClipError::UnsupportedSpecVersion(
+            v.and_then(Value::as_str).map(String::from),
+        )),
+    }
+}
+
+/// Locates the given error (from loading a spec of type `T`) in the given document.
+///
+/// Syntax errors already have their positions and errors from the checks (status
+/// codes, for example) have JSON pointers to the offending nodes. Errors from
+/// deserializing the spec from the JSON value don't have either of those, so we
+/// deserialize the spec from the document (which has positions) to find out where
+/// the same error occurs. All this happens only when loading fails.
+#[cfg(feature = "v2")]
+pub(crate) fn locate_load_error<T>(
+    err: PaperClipError,
+    buf: &[u8],
+    format: SpecFormat,
+) -> PaperClipError
+where
+    T: DeserializeOwned,
+{
+    let at = |line, column, err| {
+        PaperClipError::Located(
+            SourceLocation {
+                pointer: String::new(),
+                position: Some(Position { line, column }),
+            },
+            Box::new(err),
+        )
+    };
+
+    match err {
+        PaperClipError::Json(e) if e.line() > 0 => {
+            at(e.line(), e.column(), PaperClipError::Json(e))
+        }
+        PaperClipError::Yaml(e) => match e.location() {
+            Some(l) => at(l.line(), l.column(), PaperClipError::Yaml(e)),
+            None => PaperClipError::Yaml(e),
+        },
+        PaperClipError::Json(e) => {
+            let msg = e.to_string();
+            let buf = strip_bom(buf);
+            let position = match format {
+                SpecFormat::Json => serde_json::from_slice::<T>(buf)
+                    .err()
+                    .filter(|d| d.line() > 0 && d.to_string().starts_with(&msg))
+                    .map(|d| (d.line(), d.column())),
+                SpecFormat::Yaml => serde_yaml::from_slice::<T>(buf)
+                    .err()
+                    .filter(|d| d.to_string().contains(&msg))
+                    .and_then(|d| d.location())
+                    .map(|l| (l.line(), l.column())),
+            };
+
+            match position {
+                Some((line, column)) => at(line, column, PaperClipError::Json(e)),
+                None => PaperClipError::Json(e),
+            }
+        }
+        e @ PaperClipError::Located(..) => match SourceMap::from_slice(buf) {
+            Some(s) => s.locate(e),
+            None => e,
+        },
+        e => e,
+    }
+}
+
+/// UTF-8 byte order mark.
+#[cfg(feature = "v2")]
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+#[cfg(feature = "v2")]
+impl SpecFormat {
+    /// Detects the format of the given document (or its beginning). Leading
+    /// whitespace and byte order mark are ignored. Documents starting with `{`
+    /// are JSON and everything else is assumed to be YAML.
+    pub fn detect(buf: &[u8]) -> SpecFormat {
+        match strip_bom(buf).iter().find(|b| !b.is_ascii_whitespace()) {
+            Some(b'{') => SpecFormat::Json,
+            _ => SpecFormat::Yaml,
+        }
+    }
+
+    /// Returns the format for the extension of the given path
+    /// (`json`, `yaml` or `yml`), if it's a known one.
+    pub fn from_path(path: &Path) -> Option<SpecFormat> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "json" => Some(SpecFormat::Json),
+            "yaml" | "yml" => Some(SpecFormat::Yaml),
+            _ => None,
+        }
+    }
+}
+
+/// Removes the byte order mark (if any) from the given document.
+#[cfg(feature = "v2")]
+pub(crate) fn strip_bom(buf: &[u8]) -> &[u8] {
+    if buf.starts_with(BOM) {
+        &buf[BOM.len()..]
+    } else {
+        buf
+    }
+}
+
+/// Reads the whole document from the given reader.
+#[cfg(feature = "v2")]
+pub(crate) fn read_document<R: Read>(mut reader: R) -> PaperClipResult<Vec<u8>> {
+    let mut buf = vec![];
+    reader.read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+/// Deserialize a JSON value from the file at the given path, using the format
+/// corresponding to its extension (or detecting it, if the extension is unknown).
+#[cfg(feature = "v2")]
+pub(crate) fn value_from_path(path: &Path) -> PaperClipResult<Value> {
+    value_from_slice(&fs::read(path)?, SpecFormat::from_path(path))
+}
+
+/// Deserialize a JSON value from the given document. If the format is not
+/// given, then it's detected.
+#[cfg(feature = "v2")]
+pub(crate) fn value_from_slice(buf: &[u8], format: Option<SpecFormat>) -> PaperClipResult<Value> {
+    let buf = strip_bom(buf);
+    match format.unwrap_or_else(|| SpecFormat::detect(buf)) {
+        SpecFormat::Json => Ok(serde_json::from_slice(buf)?),
+        SpecFormat::Yaml => yaml_to_value(serde_yaml::from_slice(buf)?),
+    }
+}
+
+/// YAML allows non-string keys (status codes, for example), which
+/// are converted to strings when we switch to JSON.
+#[cfg(feature = "v2")]
+fn yaml_to_value(value: serde_yaml::Value) -> PaperClipResult<Value> {
+    Ok(serde_json::to_value(value)?)
+}
@@ -0,0 +1,277 @@
+//! Locating nodes in spec documents.
+//!
+//! When a spec is loaded from JSON or YAML, its document is kept around (see `Api::source`),
+//! so that it can be scanned for the line and column of each node (keyed by JSON pointers,
+//! say, `/definitions/Pet`). The scan only happens when some error needs to be located.
+//! Errors from loading the spec, the resolver and the emitter carry the JSON pointer
+//! to the offending node (see `PaperClipError::location`), which can then be rendered
+//! along with the corresponding snippet from the document.
+//!
+//! ```rust,no_run
+//! use paperclip_openapi::v2::{self, Api, DefaultSchema};
+//!
+//! let api: Api<DefaultSchema> = v2::from_path("my_spec.yaml").unwrap();
+//! let source = api.source.clone();
+//! if let Err(e) = api.resolve() {
+//!     match source {
+//!         Some(s) => eprintln!("{}", s.render(&e)),
+//!         None => eprintln!("{:#}", e),
+//!     }
+//! }
+//! ```
+
+use crate::error::PaperClipError;
+use crate::v2::loader::escape_token;
+use parking_lot::Mutex;
+use yaml_rust::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust::scanner::Marker;
+
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::sync::Arc;
+
+/// Line and column of some node in a document (both starting from 1).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Location of some node in the spec.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SourceLocation {
+    /// JSON pointer to the node (say, `/paths/~1pets/get/parameters/0`). This is
+    /// empty for syntax errors (since there's no node).
+    pub pointer: String,
+    /// Position of the node in the document, if it's known.
+    pub position: Option<Position>,
+}
+
+impl Display for SourceLocation {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.position {
+            Some(p) if self.pointer.is_empty() => write!(f, "line {}, column {}", p.line, p.column),
+            Some(p) => write!(f, "line {}, column {} ({})", p.line, p.column, self.pointer),
+            None => f.write_str(&self.pointer),
+        }
+    }
+}
+
+/// Document from which a spec was loaded, along with the positions of its nodes.
+pub struct SourceMap {
+    /// Contents of the document.
+    text: String,
+    /// JSON pointers to nodes and their positions (scanned on first use).
+    /// For object members, this is the position of the key.
+    positions: Mutex<Option<Arc<HashMap<String, Position>>>>,
+}
+
+impl SourceMap {
+    /// Creates a source map for the given (JSON or YAML) document. Returns `None`
+    /// if the document is not valid UTF-8. The document is scanned for the positions
+    /// of its nodes only when they're needed.
+    ///
+    /// **NOTE:** Since JSON is a subset of YAML, JSON documents are scanned
+    /// by the YAML parser. If the parser fails midway, then we only have
+    /// the positions of the nodes before the failure.
+    pub fn new(mut buf: Vec<u8>) -> Option<SourceMap> {
+        let bom_len = buf.len() - crate::strip_bom(&buf).len();
+        buf.drain(..bom_len);
+        Some(SourceMap {
+            text: String::from_utf8(buf).ok()?,
+            positions: Mutex::new(None),
+        })
+    }
+
+    /// Same as `new`, but copies the given document.
+    pub fn from_slice(buf: &[u8]) -> Option<SourceMap> {
+        SourceMap::new(buf.to_vec())
+    }
+
+    /// Returns the position of the node at the given JSON pointer. If the node
+    /// can't be found, then the position of its closest ancestor is returned.
+    pub fn position(&self, pointer: &str) -> Option<Position> {
+        let positions = self.positions();
+        let mut pointer = pointer;
+        loop {
+            if let Some(&p) = positions.get(pointer) {
+                return Some(p);
+            }
+
+            pointer = &pointer[..pointer.rfind('/')?];
+        }
+    }
+
+    /// Returns the positions of nodes, scanning the document if needed.
+    fn positions(&self) -> Arc<HashMap<String, Position>> {
+        let mut positions = self.positions.lock();
+        positions
+            .get_or_insert_with(|| {
+                let mut collector = Collector::default();
+                if let Err(e) = Parser::new(self.text.chars()).load(&mut collector, false) {
+                    debug!("Failed to locate nodes in document: {}", e);
+                }
+
+                Arc::new(collector.positions)
+            })
+            .clone()
+    }
+
+    /// Fills the position for the location of the given error, if it has
+    /// a location and the position is missing.
+    pub(crate) fn locate(&self, err: PaperClipError) -> PaperClipError {
+        match err {
+            PaperClipError::Located(mut loc, e) => {
+                if loc.position.is_none() {
+                    loc.position = self.position(&loc.pointer);
+                }
+
+                PaperClipError::Located(loc, e)
+            }
+            e => e,
+        }
+    }
+
+    /// Renders the given error along with the snippet from the document
+    /// (if it has a location), like so:
+    ///
+    /// ```text
+    /// Definition missing: Category
+    ///   --> line 12, column 9 (/definitions/Pet/properties/category/$ref)
+    ///    |
+    /// 12 |         $ref: "#/definitions/Category"
+    ///    |         ^
+    /// ```
+    pub fn render(&self, err: &PaperClipError) -> String {
+        let (loc, err) = match err {
+            PaperClipError::Located(loc, e) => (loc, &**e),
+            e => return format!("{:#}", e),
+        };
+
+        let pos = match loc.position.or_else(|| self.position(&loc.pointer)) {
+            Some(p) => p,
+            None => return format!("{:#}\n  --> {}", err, loc.pointer),
+        };
+
+        let loc = SourceLocation {
+            pointer: loc.pointer.clone(),
+            position: Some(pos),
+        };
+
+        let line = self.text.lines().nth(pos.line - 1).unwrap_or_default();
+        let gutter = " ".repeat(pos.line.to_string().len());
+        // Retain tabs so that the caret is aligned with the node.
+        let indent: String = line
+            .chars()
+            .take(pos.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        format!(
+            "{err:#}\n{gutter}--> {loc}\n\
+             {gutter} |\n{line} | {snippet}\n{gutter} | {indent}^",
+            err = err,
+            gutter = gutter,
+            loc = loc,
+            line = pos.line,
+            snippet = line,
+            indent = indent,
+        )
+    }
+}
+
+impl Debug for SourceMap {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("SourceMap")
+            .field("len", &self.text.len())
+            .field("nodes", &self.positions.lock().as_ref().map(|p| p.len()))
+            .finish()
+    }
+}
+
+/// Collection (object or array) being visited by the collector.
+enum Frame {
+    Map {
+        pointer: String,
+        /// Key of the member whose value is expected next.
+        key: Option<String>,
+    },
+    Seq {
+        pointer: String,
+        /// Index of the next item.
+        index: usize,
+    },
+}
+
+/// Collects the positions of nodes from YAML parser events.
+#[derive(Default)]
+struct Collector {
+    stack: Vec<Frame>,
+    positions: HashMap<String, Position>,
+}
+
+impl Collector {
+    /// Records the position of the given node.
+    fn record(&mut self, pointer: String, mark: Marker) {
+        self.positions.insert(
+            pointer,
+            Position {
+                line: mark.line(),
+                column: mark.col() + 1,
+            },
+        );
+    }
+
+    /// Returns the JSON pointer for the value which has just started.
+    fn value(&mut self, mark: Marker) -> String {
+        let pointer = match self.stack.last_mut() {
+            // Members are recorded with their keys.
+            Some(Frame::Map { pointer, key }) => {
+                return format!("{}/{}", pointer, key.take().unwrap_or_default());
+            }
+            Some(Frame::Seq { pointer, index }) => {
+                *index += 1;
+                format!("{}/{}", pointer, *index - 1)
+            }
+            None => String::new(),
+        };
+
+        self.record(pointer.clone(), mark);
+        pointer
+    }
+}
+
+impl MarkedEventReceiver for Collector {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        match event {
+            Event::Scalar(value, ..) => {
+                if let Some(Frame::Map { pointer, key }) = self.stack.last_mut() {
+                    if key.is_none() {
+                        let token = escape_token(&value);
+                        let pointer = format!("{}/{}", pointer, token);
+                        *key = Some(token);
+                        self.record(pointer, mark);
+                        return;
+                    }
+                }
+
+                self.value(mark);
+            }
+            Event::Alias(_) => {
+                self.value(mark);
+            }
+            Event::MappingStart(_) => {
+                let pointer = self.value(mark);
+                self.stack.push(Frame::Map { pointer, key: None });
+            }
+            Event::SequenceStart(_) => {
+                let pointer = self.value(mark);
+                self.stack.push(Frame::Seq { pointer, index: 0 });
+            }
+            Event::MappingEnd | Event::SequenceEnd => {
+                self.stack.pop();
+            }
+            _ => (),
+        }
+    }
+}
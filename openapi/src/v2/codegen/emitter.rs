@@ -1,18 +1,27 @@
-use super::object::{ApiObject, ObjectField, OpRequirement, Parameter};
+use super::object::{
+    ApiEnum, ApiObject, BodyFormat, EnumVariant, ObjectField, OpRequirement, OpResponse, Parameter,
+    ResponseHeader, TypeAlias,
+};
+use super::rust_ident;
 use super::state::EmitterState;
-use super::RUST_KEYWORDS;
-use crate::error::PaperClipError;
+use crate::error::{PaperClipError, PaperClipResult, ResultExt};
 use crate::v2::{
-    models::{self, Api, DataType, DataTypeFormat, OperationMap},
+    im::ArcRwLock,
+    loader::escape_token,
+    models::{
+        self, Api, DataType, DataTypeFormat, EnumValue, MediaType, OperationMap, ParameterIn,
+        StatusCode,
+    },
+    validator::template_params,
     Schema,
 };
-use failure::Error;
 use heck::{CamelCase, SnekCase};
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::fmt::Debug;
-use std::fs;
 use std::ops::Deref;
+#[cfg(feature = "parallel")]
+use std::panic;
 use std::path::PathBuf;
 
 /// Checks if the given type/format matches a known Rust type and returns it.
@@ -35,38 +44,229 @@ fn matching_unit_type(
     }
 }
 
+/// Returns the name of the response variant for the given status code
+/// (based on its reason phrase, if it's a known one) or range.
+fn status_variant_name(code: StatusCode) -> String {
+    let code = match code {
+        StatusCode::Code(c) => c,
+        StatusCode::Range(1) => return "Informational".into(),
+        StatusCode::Range(2) => return "Success".into(),
+        StatusCode::Range(3) => return "Redirection".into(),
+        StatusCode::Range(4) => return "ClientError".into(),
+        StatusCode::Range(5) => return "ServerError".into(),
+        StatusCode::Range(c) => return format!("Status{}XX", c),
+        StatusCode::Default => return "Default".into(),
+    };
+
+    let name = match code {
+        100 => "Continue",
+        101 => "SwitchingProtocols",
+        200 => "Ok",
+        201 => "Created",
+        202 => "Accepted",
+        203 => "NonAuthoritativeInformation",
+        204 => "NoContent",
+        205 => "ResetContent",
+        206 => "PartialContent",
+        300 => "MultipleChoices",
+        301 => "MovedPermanently",
+        302 => "Found",
+        303 => "SeeOther",
+        304 => "NotModified",
+        307 => "TemporaryRedirect",
+        308 => "PermanentRedirect",
+        400 => "BadRequest",
+        401 => "Unauthorized",
+        402 => "PaymentRequired",
+        403 => "Forbidden",
+        404 => "NotFound",
+        405 => "MethodNotAllowed",
+        406 => "NotAcceptable",
+        407 => "ProxyAuthenticationRequired",
+        408 => "RequestTimeout",
+        409 => "Conflict",
+        410 => "Gone",
+        411 => "LengthRequired",
+        412 => "PreconditionFailed",
+        413 => "PayloadTooLarge",
+        414 => "UriTooLong",
+        415 => "UnsupportedMediaType",
+        416 => "RangeNotSatisfiable",
+        417 => "ExpectationFailed",
+        422 => "UnprocessableEntity",
+        423 => "Locked",
+        428 => "PreconditionRequired",
+        429 => "TooManyRequests",
+        431 => "RequestHeaderFieldsTooLarge",
+        500 => "InternalServerError",
+        501 => "NotImplemented",
+        502 => "BadGateway",
+        503 => "ServiceUnavailable",
+        504 => "GatewayTimeout",
+        505 => "HttpVersionNotSupported",
+        _ => return format!("Status{}", code),
+    };
+
+    name.into()
+}
+
+/// Returns the media type for encoding the request of the given operation
+/// (if it has a body or form parameters) and the value for its `Accept`
+/// header (if it produces something).
+///
+/// Since we can only decode JSON responses, JSON media types are preferred
+/// for the latter.
+fn media_types_for_operation<S>(
+    op: &models::Operation<S>,
+    params: &[Parameter],
+    has_body: bool,
+) -> (Option<MediaType>, Option<String>) {
+    let consumes = op.consumes.as_ref().map(Vec::as_slice).unwrap_or_default();
+    let produces = op.produces.as_ref().map(Vec::as_slice).unwrap_or_default();
+    let content_type = if params.iter().any(|p| p.presence == ParameterIn::FormData) {
+        let media = consumes
+            .iter()
+            .find(|m| m.is_form_urlencoded() || m.is_multipart_form());
+        Some(
+            media
+                .cloned()
+                .unwrap_or_else(|| MediaType::new("application", "x-www-form-urlencoded")),
+        )
+    } else if has_body {
+        let media = consumes
+            .iter()
+            .find(|m| m.is_json() || m.is_form_urlencoded());
+        Some(
+            media
+                .cloned()
+                .unwrap_or_else(|| MediaType::new("application", "json")),
+        )
+    } else {
+        None
+    };
+
+    let accept = match produces.iter().find(|m| m.is_json()) {
+        Some(m) => Some(m.to_string()),
+        None if !produces.is_empty() => Some(
+            produces
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", "),
+        ),
+        None => None,
+    };
+
+    (content_type, accept)
+}
+
+/// Returns the format in which the response bodies of the given operation are read.
+/// If the operation doesn't produce JSON, then we can't decode the body, so it's
+/// read as text (for textual media types) or bytes.
+fn body_format_for_operation<S>(op: &models::Operation<S>) -> BodyFormat {
+    let produces = op.produces.as_ref().map(Vec::as_slice).unwrap_or_default();
+    if produces.is_empty() || produces.iter().any(MediaType::is_json) {
+        BodyFormat::Json
+    } else if produces.iter().any(MediaType::is_text) {
+        BodyFormat::Text
+    } else {
+        BodyFormat::Binary
+    }
+}
+
+/// Checks that all the placeholders in the given path template have
+/// corresponding path parameters.
+fn check_path_template(path: &str, params: &[Parameter]) -> PaperClipResult<()> {
+    for name in template_params(path) {
+        if !params
+            .iter()
+            .any(|p| p.presence == ParameterIn::Path && p.name == name)
+        {
+            return Err(PaperClipError::MissingPathParameter(
+                name.into(),
+                path.into(),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that the given schema doesn't use any (OpenAPI v3) keywords
+/// which can't be represented by the generated types.
+fn check_schema_keywords<S: Schema>(def: &S) -> PaperClipResult<()> {
+    let keyword = if def.one_of().is_some() {
+        "oneOf"
+    } else if def.any_of().is_some() {
+        "anyOf"
+    } else if def.not().is_some() {
+        "not"
+    } else {
+        return Ok(());
+    };
+
+    Err(PaperClipError::UnsupportedSchemaKeyword(keyword.into()))
+}
+
+/// Calls the given function for each of the given items and stops at the
+/// first error. The items are split across threads (one chunk per CPU), but
+/// the chunks are joined in order, so that the error is the same as the one
+/// we'd get by going through the items sequentially.
+#[cfg(feature = "parallel")]
+fn try_for_each<T, F>(items: &[T], f: F) -> PaperClipResult<()>
+where
+    T: Sync,
+    F: Fn(&T) -> PaperClipResult<()> + Sync,
+{
+    if items.is_empty() {
+        return Ok(());
+    }
+
+    let threads = num_cpus::get();
+    let chunk_size = (items.len() + threads - 1) / threads;
+    let f = &f;
+    crossbeam_utils::thread::scope(|s| {
+        let handles = items
+            .chunks(chunk_size)
+            .map(|chunk| s.spawn(move |_| chunk.iter().try_for_each(f)))
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .try_for_each(|h| h.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+    })
+    .unwrap_or_else(|e| panic::resume_unwind(e))
+}
+
+/// Calls the given function for each of the given items and stops at the
+/// first error.
+#[cfg(not(feature = "parallel"))]
+fn try_for_each<T, F>(items: &[T], f: F) -> PaperClipResult<()>
+where
+    F: Fn(&T) -> PaperClipResult<()>,
+{
+    items.iter().try_for_each(f)
+}
+
 /// `Emitter` represents the interface for generating the relevant
 /// modules, API object definitions and the associated calls.
-pub trait Emitter: Sized {
+///
+/// Emitters (and their schemas) should be thread-safe, because definitions
+/// and paths are processed in parallel when the `parallel` feature is enabled.
+pub trait Emitter: Sized + Sync {
     /// The associated `Schema` implementor.
-    type Definition: Schema + Debug;
+    type Definition: Schema + Debug + Send + Sync;
 
     /// Returns a reference to the underlying state.
     fn state(&self) -> &EmitterState;
 
     /// Entrypoint for emitter. Given an API spec, generate code
     /// inside Rust modules in the configured working directory.
-    fn generate(&self, api: &Api<Self::Definition>) -> Result<(), Error> {
+    ///
+    /// Errors caused by some node in the spec have its location (see
+    /// `PaperClipError::location`).
+    fn generate(&self, api: &Api<Self::Definition>) -> PaperClipResult<()> {
         let gen = CodegenEmitter(self);
-        // Generate file contents by accumulating definitions.
-        for (name, schema) in &api.definitions {
-            debug!("Creating definition {}", name);
-            let schema = schema.read();
-            gen.generate_def_from_root(&schema)?;
-        }
-
-        let state = gen.state();
-        state.declare_modules()?;
-        state.write_definitions()?;
-
-        for (path, map) in &api.paths {
-            gen.collect_requirements_for_path(path, map)?;
-        }
-
-        state.add_builders()?;
-        state.add_deps()?;
-
-        Ok(())
+        gen.generate_api(api).map_err(|e| api.locate(e))
     }
 
     /// Returns an iterator of path components for the given definition.
@@ -74,22 +274,22 @@ pub trait Emitter: Sized {
     /// **NOTE:** All components are [snake_cased](https://docs.rs/heck/*/heck/trait.SnekCase.html)
     /// (including the definition name).
     fn def_ns_name<'a>(
-        &self,
+        &'a self,
         def: &'a Self::Definition,
-    ) -> Result<Box<Iterator<Item = String> + 'a>, Error> {
+    ) -> PaperClipResult<Box<dyn Iterator<Item = String> + 'a>> {
         let state = self.state();
         def.name()
-            .map(|n| n.split(state.ns_sep).map(SnekCase::to_snek_case))
+            .map(|n| n.split(state.ns_sep.as_str()).map(SnekCase::to_snek_case))
             .ok_or_else(|| {
                 trace!("Invalid name for definition: {:?}", def);
-                PaperClipError::InvalidDefinitionName.into()
+                PaperClipError::InvalidDefinitionName
             })
             .map(|i| Box::new(i) as Box<_>)
     }
 
     /// Returns the [CamelCase](https://docs.rs/heck/*/heck/trait.CamelCase.html)
     /// name for the given definition.
-    fn def_name(&self, def: &Self::Definition) -> Result<String, Error> {
+    fn def_name(&self, def: &Self::Definition) -> PaperClipResult<String> {
         Ok(self
             .def_ns_name(def)?
             .last()
@@ -100,7 +300,7 @@ pub trait Emitter: Sized {
     /// Returns the module path (from working directory) for the given definition.
     ///
     /// **NOTE:** This doesn't (shouldn't) set any extension to the leaf component.
-    fn def_mod_path(&self, def: &Self::Definition) -> Result<PathBuf, Error> {
+    fn def_mod_path(&self, def: &Self::Definition) -> PaperClipResult<PathBuf> {
         let state = self.state();
         let mut path = state.working_dir.clone();
         path.extend(self.def_ns_name(def)?);
@@ -112,8 +312,42 @@ pub trait Emitter: Sized {
     /// planning to define a Rust type or whether we're reusing an existing type.
     ///
     /// **NOTE:** We resolve type aliases to known types.
-    fn build_def(&self, def: &Self::Definition, define: bool) -> Result<EmittedUnit, Error> {
+    fn build_def(&self, def: &Self::Definition, define: bool) -> PaperClipResult<EmittedUnit> {
         trace!("Building definition: {:?}", def);
+        check_schema_keywords(def)?;
+        if let Some(target) = def.alias_target() {
+            if define {
+                return Ok(EmittedUnit::None);
+            }
+
+            return self.build_def(&target.read(), false);
+        }
+
+        // Enums in properties are handled by the objects, so we only care about
+        // (named) definitions here.
+        if def.name().is_some() {
+            let gen = CodegenEmitter(self);
+            let name = self.def_name(def)?;
+            let enum_ = match gen.build_tagged_enum(def, name.clone()) {
+                Some(e) => Some(e),
+                None => gen.build_enum(def, name),
+            };
+
+            if let Some(mut enum_) = enum_ {
+                if define {
+                    if let Some(tag) = enum_.tag.clone() {
+                        let base =
+                            gen.build_struct(def, enum_.name.clone() + "Base", Some(&tag))?;
+                        enum_.base = Some(base);
+                    }
+
+                    return Ok(EmittedUnit::Enum(enum_));
+                }
+
+                return Ok(EmittedUnit::Known(gen.def_type_path(def)?));
+            }
+        }
+
         if let Some(ty) = matching_unit_type(def.format(), def.data_type()) {
             trace!("Matches unit type: {}", ty);
             if define {
@@ -126,6 +360,8 @@ pub trait Emitter: Sized {
         match def.data_type() {
             Some(DataType::Array) => CodegenEmitter(self).emit_array(def, define),
             Some(DataType::Object) => CodegenEmitter(self).emit_object(def, define),
+            // Composed schemas may not specify the type.
+            None if def.all_of().is_some() => CodegenEmitter(self).emit_object(def, define),
             Some(_) => unreachable!("bleh?"), // we've already handled everything else
             None => {
                 if define {
@@ -146,7 +382,7 @@ impl<'a, E> Deref for CodegenEmitter<'a, E> {
     type Target = E;
 
     fn deref(&self) -> &E {
-        &self.0
+        self.0
     }
 }
 
@@ -154,27 +390,110 @@ impl<'a, E> CodegenEmitter<'a, E>
 where
     E: Emitter,
 {
+    /// Generates code for the given API spec (see `Emitter::generate`).
+    fn generate_api(&self, api: &Api<E::Definition>) -> PaperClipResult<()> {
+        // Generate file contents by accumulating definitions.
+        let defs = api.definitions.iter().collect::<Vec<_>>();
+        try_for_each(&defs, |&(name, schema)| {
+            debug!("Creating definition {}", name);
+            let schema = schema.read();
+            self.generate_def_from_root(&schema)
+                .at(|| format!("/definitions/{}", escape_token(name)))
+                .context(|e| PaperClipError::InDefinition(name.clone(), e))
+        })?;
+
+        let state = self.state();
+        state.declare_modules()?;
+        state.write_definitions()?;
+
+        let paths = api.paths.iter().collect::<Vec<_>>();
+        try_for_each(&paths, |&(path, map)| {
+            self.collect_requirements_for_path(path, map)
+                .at(|| format!("/paths/{}", escape_token(path)))
+        })?;
+
+        state.add_builders()?;
+        state.add_deps(api.base_url().as_ref().map(String::as_str))?;
+        state.add_crate_files(api.info.as_ref())?;
+
+        if state.format.is_some() {
+            info!("Formatting modules.");
+            try_for_each(&state.written_modules(), |p| state.format_module(p))?;
+        }
+
+        Ok(())
+    }
+
     /// Given a schema definition, generate the corresponding Rust definition.
     ///
     /// **NOTE:** This doesn't generate any files. It only adds the generated stuff
     /// to `EmitterState`.
-    fn generate_def_from_root(&self, def: &E::Definition) -> Result<(), Error> {
+    fn generate_def_from_root(&self, def: &E::Definition) -> PaperClipResult<()> {
         let state = self.state();
+        // Aliases are resolved anyway, but we still need their names in the crate.
+        if let Some(ty_path) = self.alias_type(def)? {
+            let (mod_path, _) = self.add_def_module(def)?;
+            let alias = TypeAlias {
+                name: self.def_name(def)?,
+                ty_path,
+            };
+
+            state.alias_mods.write().insert(mod_path, alias);
+            return Ok(());
+        }
+
         // Generate the object.
         let mut object = match self.build_def(def, true)? {
             EmittedUnit::Object(o) => o,
-            // We don't care about type aliases because we resolve them anyway.
+            EmittedUnit::Enum(e) => {
+                let (mod_path, _) = self.add_def_module(def)?;
+                state.enum_mods.write().insert(mod_path, e);
+                return Ok(());
+            }
             _ => return Ok(()),
         };
 
+        let (mod_path, rel_path) = self.add_def_module(def)?;
+        // Set the path for future reference
+        object.path = rel_path;
+
+        // Add generated object to state.
+        let mut def_mods = state.def_mods.write();
+        def_mods.insert(mod_path, object);
+
+        Ok(())
+    }
+
+    /// Returns the aliased type, if the given root definition is an alias
+    /// (i.e., it refers some other definition, or it's an array or a map).
+    fn alias_type(&self, def: &E::Definition) -> PaperClipResult<Option<String>> {
+        if def.alias_target().is_some() {
+            return Ok(Some(self.build_def(def, false)?.known_type()));
+        }
+
+        match def.data_type() {
+            Some(DataType::Array) => Ok(Some(self.emit_array(def, false)?.known_type())),
+            Some(DataType::Object) if def.properties().is_none() => {
+                match self.try_emit_map(def, false)? {
+                    EmittedUnit::Known(ty) => Ok(Some(ty)),
+                    _ => Ok(None),
+                }
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Creates the parent directories for the module of the given (root) definition
+    /// and adds its module declarations. Returns the path to the module and
+    /// its path from the root module.
+    fn add_def_module(&self, def: &E::Definition) -> PaperClipResult<(PathBuf, String)> {
+        let state = self.state();
         let mod_path = self.def_mod_path(def)?;
         // Create parent dirs recursively for the leaf module.
         let dir_path = mod_path
             .parent()
             .ok_or(PaperClipError::InvalidDefinitionPath(mod_path.clone()))?;
-        if !dir_path.exists() {
-            fs::create_dir_all(&dir_path)?;
-        }
+        state.sink.create_dir_all(dir_path)?;
 
         // Get the path without the extension.
         let full_path = dir_path.join(
@@ -188,25 +507,19 @@ where
             .map_err(|_| PaperClipError::InvalidDefinitionPath(full_path.clone()))?;
 
         // Gather the immediate parent-children pairs for module declarations.
-        let mut mods = state.mod_children.borrow_mut();
+        let mut mods = state.mod_children.write();
         for path in rel_path.ancestors() {
             match (path.parent(), path.file_name()) {
                 (Some(parent), Some(name)) if parent.parent().is_some() => {
-                    let entry = mods.entry(parent.into()).or_insert_with(HashSet::new);
+                    let entry = mods.entry(parent.into()).or_default();
                     entry.insert(name.to_string_lossy().into_owned());
                 }
                 _ => (),
             }
         }
 
-        // Set the path for future reference
-        object.path = rel_path.to_string_lossy().into_owned().replace('/', "::");
-
-        // Add generated object to state.
-        let mut def_mods = state.def_mods.borrow_mut();
-        def_mods.insert(mod_path, object);
-
-        Ok(())
+        let rel_path = rel_path.to_string_lossy().into_owned().replace('/', "::");
+        Ok((mod_path, rel_path))
     }
 
     /// Given a path and an operation map, collect the stuff required
@@ -216,14 +529,16 @@ where
         &self,
         path: &str,
         map: &OperationMap<E::Definition>,
-    ) -> Result<(), Error> {
+    ) -> PaperClipResult<()> {
         debug!("Collecting builder requirement for {:?}", path);
         let state = self.state();
 
         let mut unused_params = vec![];
         // Collect all the parameters local to some API call.
         if let Some(global_params) = map.parameters.as_ref() {
-            let (params, _) = self.collect_parameters(path, global_params)?;
+            let (params, _) = self
+                .collect_parameters(path, global_params)
+                .context(|e| PaperClipError::InPath(path.into(), e))?;
             // FIXME: What if a body is "required" globally (for all operations)?
             // This means, operations can override the body with some other schema
             // and we may need to map it to the appropriate builders.
@@ -234,9 +549,16 @@ where
         for (&meth, op) in &map.methods {
             let mut op_addressed = false;
             let mut unused_local_params = vec![];
+            let responses = self.responses_for_operation(path, op);
+            let op_pointer = || format!("/{}", meth.as_str());
+            let in_op =
+                |e| PaperClipError::InOperation(meth.as_str().to_uppercase(), path.into(), e);
 
             if let Some(local_params) = op.parameters.as_ref() {
-                let (mut params, schema_path) = self.collect_parameters(path, local_params)?;
+                let (mut params, schema_path) = self
+                    .collect_parameters(path, local_params)
+                    .at(op_pointer)
+                    .context(in_op)?;
                 // If we have unused params which don't exist in the method-specific
                 // params (which take higher precedence), then we can copy those inside.
                 for global_param in &unused_params {
@@ -249,38 +571,46 @@ where
                     }
                 }
 
+                check_path_template(path, &params)
+                    .at(op_pointer)
+                    .context(in_op)?;
+
                 // If there's a matching object, add the params to its operation.
                 if let Some(pat) = schema_path.as_ref() {
                     op_addressed = true;
-                    let mut def_mods = state.def_mods.borrow_mut();
+                    let mut def_mods = state.def_mods.write();
                     let obj = def_mods.get_mut(pat).expect("bleh?");
-                    let ops = obj
-                        .paths
-                        .entry(path.into())
-                        .or_insert_with(Default::default);
+                    let ops = obj.paths.entry(path.into()).or_default();
+                    let (content_type, accept) = media_types_for_operation(op, &params, true);
                     ops.req.insert(
                         meth,
                         OpRequirement {
                             id: op.operation_id.clone(),
                             params,
                             body_required: true,
+                            responses: responses.clone(),
+                            content_type,
+                            accept,
                         },
                     );
                 } else {
                     unused_local_params = params;
                 }
             } else {
+                check_path_template(path, &unused_params)
+                    .at(op_pointer)
+                    .context(in_op)?;
                 unused_local_params = unused_params.clone();
             }
 
             // We haven't attached this operation to any object.
             // Let's try from the response maybe...
             if !op_addressed {
-                let mut def_mods = state.def_mods.borrow_mut();
+                let mut def_mods = state.def_mods.write();
                 for schema in op
                     .responses
                     .iter()
-                    .filter(|(c, _)| c.starts_with('2')) // 2xx response
+                    .filter(|(c, _)| c.is_success())
                     .filter_map(|(_, r)| r.schema.as_ref())
                 {
                     let pat = self.def_mod_path(&*schema.read()).ok();
@@ -295,16 +625,18 @@ where
                         }
                     };
 
-                    let ops = obj
-                        .paths
-                        .entry(path.into())
-                        .or_insert_with(Default::default);
+                    let ops = obj.paths.entry(path.into()).or_default();
+                    let (content_type, accept) =
+                        media_types_for_operation(op, &unused_local_params, false);
                     ops.req.insert(
                         meth,
                         OpRequirement {
                             id: op.operation_id.clone(),
                             params: unused_local_params,
                             body_required: false,
+                            responses,
+                            content_type,
+                            accept,
                         },
                     );
 
@@ -325,43 +657,150 @@ where
         &self,
         path: &str,
         obj_params: &[models::Parameter<E::Definition>],
-    ) -> Result<(Vec<Parameter>, Option<PathBuf>), Error> {
-        let def_mods = self.state().def_mods.borrow();
+    ) -> PaperClipResult<(Vec<Parameter>, Option<PathBuf>)> {
+        let def_mods = self.state().def_mods.read();
         let mut schema_path = None;
         let mut params = vec![];
-        for p in obj_params {
-            p.check(path)?; // validate the parameter
+        for (i, p) in obj_params.iter().enumerate() {
+            let pointer = || format!("/parameters/{}", i);
+            p.check(path).at(pointer)?; // validate the parameter
+            if p.in_ == ParameterIn::Path && !p.required {
+                return Err(
+                    PaperClipError::OptionalPathParameter(p.name.clone(), path.into())
+                        .at(&pointer()),
+                );
+            }
 
             if let Some(def) = p.schema.as_ref() {
                 // If a schema exists, then get its path for later use.
-                let pat = self.def_mod_path(&*def.read())?;
-                def_mods
-                    .get(&pat)
-                    .ok_or(PaperClipError::UnsupportedParameterDefinition(
-                        p.name.clone(),
-                        path.into(),
-                    ))?;
+                let pat = self.def_mod_path(&*def.read()).at(pointer)?;
+                def_mods.get(&pat).ok_or_else(|| {
+                    PaperClipError::UnsupportedParameterDefinition(p.name.clone(), path.into())
+                        .at(&pointer())
+                })?;
                 schema_path = Some(pat);
                 continue;
             }
 
-            // Enforce that the parameter is a known type and collect it.
-            let ty = matching_unit_type(p.format.as_ref(), p.data_type).ok_or(
-                PaperClipError::UnknownParameterType(p.name.clone(), path.into()),
-            )?;
+            // Enforce that the parameter is a known type (or an array of
+            // some known type) and collect it.
+            let unknown_type =
+                || PaperClipError::UnknownParameterType(p.name.clone(), path.into()).at(&pointer());
+            let (ty_path, collection) = match (p.data_type, p.items.as_ref()) {
+                (Some(DataType::Array), Some(items)) => {
+                    let items = items.read();
+                    let ty = matching_unit_type(items.format(), items.data_type())
+                        .ok_or_else(unknown_type)?;
+                    (
+                        format!("Vec<{}>", ty),
+                        Some(p.collection_format.unwrap_or_default()),
+                    )
+                }
+                _ => {
+                    let ty = matching_unit_type(p.format.as_ref(), p.data_type)
+                        .ok_or_else(unknown_type)?;
+                    (ty.into(), None)
+                }
+            };
+
             params.push(Parameter {
                 name: p.name.clone(),
-                ty_path: ty.into(),
+                ty_path,
                 required: p.required,
+                presence: p.in_,
+                collection,
             });
         }
 
         Ok((params, schema_path))
     }
 
+    /// Collects the responses declared for the given operation (along with
+    /// their body types and headers).
+    fn responses_for_operation(
+        &self,
+        path: &str,
+        op: &models::Operation<E::Definition>,
+    ) -> Vec<OpResponse> {
+        let body_format = body_format_for_operation(op);
+        let mut responses = vec![];
+        for (&code, response) in &op.responses {
+            let ty_path = response.schema.as_ref().and_then(|schema| {
+                match body_format {
+                    BodyFormat::Text => return Some(String::from("String")),
+                    BodyFormat::Binary => return Some(String::from("Vec<u8>")),
+                    BodyFormat::Json => (),
+                }
+
+                match self.build_def(&*schema.read(), false) {
+                    Ok(EmittedUnit::Known(ty)) => Some(ty),
+                    _ => {
+                        warn!(
+                            "Skipping unknown response type for path {:?}: {:?}",
+                            path, schema
+                        );
+                        None
+                    }
+                }
+            });
+
+            let mut headers = vec![];
+            for (name, header) in &response.headers {
+                let (ty_path, separator) = match (header.data_type, header.items.as_ref()) {
+                    (Some(DataType::Array), Some(items)) => {
+                        let items = items.read();
+                        let ty = matching_unit_type(items.format(), items.data_type());
+                        let sep = header.collection_format.unwrap_or_default().separator();
+                        (ty.map(|t| format!("Vec<{}>", t)), sep)
+                    }
+                    (t, _) => (
+                        matching_unit_type(header.format.as_ref(), t).map(String::from),
+                        None,
+                    ),
+                };
+
+                let ty_path = match ty_path {
+                    Some(t) => t,
+                    None => {
+                        warn!(
+                            "Skipping header {:?} of unknown type in path {:?}",
+                            name, path
+                        );
+                        continue;
+                    }
+                };
+
+                let mut field = name.to_snek_case();
+                if field == "body" || field == "status" {
+                    field.insert_str(0, "header_");
+                } else {
+                    field = rust_ident(&field);
+                }
+
+                headers.push(ResponseHeader {
+                    name: name.clone(),
+                    field,
+                    ty_path,
+                    separator,
+                });
+            }
+
+            responses.push(OpResponse {
+                code,
+                variant: status_variant_name(code),
+                description: response.description.clone(),
+                ty_path,
+                body_format,
+                headers,
+            });
+        }
+
+        responses
+    }
+
     /// Assumes that the given definition is an array and returns the corresponding
     /// vector type for it.
-    fn emit_array(&self, def: &E::Definition, define: bool) -> Result<EmittedUnit, Error> {
+    fn emit_array(&self, def: &E::Definition, define: bool) -> PaperClipResult<EmittedUnit> {
         if define {
             return Ok(EmittedUnit::None);
         }
@@ -377,37 +816,192 @@ where
 
     /// Assumes that the given definition is an object and returns the corresponding
     /// Rust struct / map.
-    fn emit_object(&self, def: &E::Definition, define: bool) -> Result<EmittedUnit, Error> {
+    fn emit_object(&self, def: &E::Definition, define: bool) -> PaperClipResult<EmittedUnit> {
         match self.try_emit_map(def, define)? {
             EmittedUnit::None => (),
             x => return Ok(x),
         }
 
         if !define {
-            // Use absolute paths to save some pain.
-            // FIXME: This assumes that the working directory is the immediate
-            // child module of a crate. We should support custom prefixes.
-            let mut ty_path = String::from("crate");
-            let mut iter = self.def_ns_name(def)?.peekable();
-            while let Some(mut c) = iter.next() {
+            return Ok(EmittedUnit::Known(self.def_type_path(def)?));
+        }
+
+        self.emit_struct(def)
+    }
+
+    /// Returns the absolute path to the Rust type for the given definition.
+    fn def_type_path(&self, def: &E::Definition) -> PaperClipResult<String> {
+        let name = def.name().ok_or(PaperClipError::InvalidDefinitionName)?;
+        Ok(self.type_path_for_name(name))
+    }
+
+    /// Returns the absolute path to the Rust type for the definition
+    /// with the given name.
+    fn type_path_for_name(&self, name: &str) -> String {
+        // Use absolute paths to save some pain.
+        let mut ty_path = String::from(self.state().mod_prefix());
+        let mut iter = name
+            .split(self.state().ns_sep.as_str())
+            .map(SnekCase::to_snek_case)
+            .peekable();
+        while let Some(mut c) = iter.next() {
+            if iter.peek().is_none() {
+                ty_path.push_str(&c);
                 ty_path.push_str("::");
-                if iter.peek().is_none() {
-                    ty_path.push_str(&c);
-                    ty_path.push_str("::");
-                    c = c.to_camel_case();
+                c = c.to_camel_case();
+            }
+
+            ty_path.push_str(&c);
+            if iter.peek().is_some() {
+                ty_path.push_str("::");
+            }
+        }
+
+        ty_path
+    }
+
+    /// Builds a Rust enum (tagged with the discriminator) with the given name
+    /// for wrapping the given definition and its subtypes, if it has any. The
+    /// definition itself is wrapped as a struct named `{name}Base` (without
+    /// the discriminator), which is added to the enum by the caller.
+    ///
+    /// **NOTE:** Variants are named after the definitions, since the definition
+    /// names are the values of the discriminator in OpenAPI v2. If the names
+    /// (without namespaces) collide, then the namespaces are included.
+    fn build_tagged_enum(&self, def: &E::Definition, name: String) -> Option<ApiEnum> {
+        let tag = def.discriminator()?;
+        if def.subtypes().is_empty() {
+            return None;
+        }
+
+        let base = def.name()?;
+        let ns_sep = self.state().ns_sep.as_str();
+        let types = Some((base, self.type_path_for_name(base) + "Base"))
+            .into_iter()
+            .chain(
+                def.subtypes()
+                    .iter()
+                    .map(|sub| (sub.as_str(), self.type_path_for_name(sub))),
+            )
+            .collect::<Vec<_>>();
+        let short_names = types
+            .iter()
+            .map(|(value, _)| value.rsplit(ns_sep).next().unwrap_or(value).to_camel_case())
+            .collect::<Vec<_>>();
+
+        let mut names = HashSet::new();
+        let mut variants = vec![];
+        for ((value, ty_path), short) in types.into_iter().zip(&short_names) {
+            let mut var_name = if short_names.iter().filter(|&n| n == short).count() > 1 {
+                value.split(ns_sep).map(|c| c.to_camel_case()).collect()
+            } else {
+                short.clone()
+            };
+
+            // Make sure that the names are unique.
+            let base = var_name.clone();
+            let mut idx = 1;
+            while !names.insert(var_name.clone()) {
+                idx += 1;
+                var_name = format!("{}{}", base, idx);
+            }
+
+            variants.push(EnumVariant {
+                name: var_name,
+                value: value.into(),
+                ty_path: Some(ty_path),
+            });
+        }
+
+        Some(ApiEnum {
+            name,
+            is_integer: false,
+            variants,
+            has_other: false,
+            tag: Some(tag.into()),
+            base: None,
+        })
+    }
+
+    /// Builds a Rust enum with the given name, if the given definition has
+    /// enum values (and if they're all strings or integers).
+    fn build_enum(&self, def: &E::Definition, name: String) -> Option<ApiEnum> {
+        let values = def.enum_variants()?;
+        let is_integer = match values[0] {
+            EnumValue::Integer(_) => true,
+            EnumValue::String(_) => false,
+            _ => {
+                warn!(
+                    "Skipping enum {} with unsupported values: {:?}",
+                    name, values
+                );
+                return None;
+            }
+        };
+
+        let has_other = self.state().enum_other_variant;
+        let mut names = HashSet::new();
+        if has_other {
+            names.insert(String::from("Other"));
+        }
+
+        let mut variants = vec![];
+        for value in values {
+            let (mut var_name, value) = match value {
+                EnumValue::Integer(i) if is_integer => {
+                    let v = i.to_string();
+                    match v.chars().next() {
+                        Some('-') => (String::from("VNeg") + &v[1..], v),
+                        _ => (String::from("V") + &v, v),
+                    }
+                }
+                EnumValue::String(s) if !is_integer => (s.to_camel_case(), s.clone()),
+                EnumValue::Other(_) => {
+                    warn!(
+                        "Skipping enum {} with unsupported values: {:?}",
+                        name, values
+                    );
+                    return None;
+                }
+                _ => {
+                    warn!("Skipping enum {} with mixed values: {:?}", name, values);
+                    return None;
                 }
+            };
 
-                ty_path.push_str(&c);
+            if var_name.is_empty() || var_name.starts_with(|c: char| c.is_ascii_digit()) {
+                var_name.insert(0, 'V');
+            } else if var_name == "Self" {
+                var_name.push('_');
             }
 
-            return Ok(EmittedUnit::Known(ty_path));
+            // Make sure that the names are unique.
+            let base = var_name.clone();
+            let mut idx = 1;
+            while !names.insert(var_name.clone()) {
+                idx += 1;
+                var_name = format!("{}{}", base, idx);
+            }
+
+            variants.push(EnumVariant {
+                name: var_name,
+                value,
+                ty_path: None,
+            });
         }
 
-        self.emit_struct(def)
+        Some(ApiEnum {
+            name,
+            is_integer,
+            variants,
+            has_other,
+            tag: None,
+            base: None,
+        })
     }
 
     /// Checks if the given definition is a simple map and returns the corresponding `BTreeMap`.
-    fn try_emit_map(&self, def: &E::Definition, define: bool) -> Result<EmittedUnit, Error> {
+    fn try_emit_map(&self, def: &E::Definition, define: bool) -> PaperClipResult<EmittedUnit> {
         if define {
             return Ok(EmittedUnit::None);
         }
@@ -424,40 +1018,142 @@ where
 
     /// Helper for `emit_object` - This returns the Rust struct definition for the
     /// given schema definition.
-    fn emit_struct(&self, def: &E::Definition) -> Result<EmittedUnit, Error> {
-        let mut obj = ApiObject::with_name(self.def_name(def)?);
+    fn emit_struct(&self, def: &E::Definition) -> PaperClipResult<EmittedUnit> {
+        let obj = self.build_struct(def, self.def_name(def)?, None)?;
+        Ok(EmittedUnit::Object(obj))
+    }
 
-        if let Some(props) = def.properties() {
-            props
-                .iter()
-                .try_for_each(|(name, prop)| -> Result<(), Error> {
-                    let mut new_name = name.to_snek_case();
-                    // Check if the field matches a Rust keyword and add '_' suffix.
-                    if RUST_KEYWORDS.iter().any(|&k| k == new_name) {
-                        new_name.push('_');
+    /// Builds a Rust struct with the given name for the given definition,
+    /// skipping the given property (if any).
+    fn build_struct(
+        &self,
+        def: &E::Definition,
+        name: String,
+        skip: Option<&str>,
+    ) -> PaperClipResult<ApiObject> {
+        let mut obj = ApiObject::with_name(name);
+        // Inline enums live in the same module as the object.
+        let def_path = self.def_type_path(def)?;
+        let mod_prefix = &def_path[..def_path.rfind("::").expect("path has modules?")];
+
+        // Properties from `allOf` schemas are flattened into the struct.
+        let mut props = BTreeMap::new();
+        let mut visiting = def.name().map(String::from).into_iter().collect();
+        self.collect_properties(def, &mut props, &mut visiting)?;
+        if let Some(name) = skip {
+            props.remove(name);
+        }
+
+        props
+            .iter()
+            .try_for_each(|(name, prop)| -> PaperClipResult<()> {
+                // Keywords are escaped only when the field is written (see `rust_ident`).
+                let new_name = name.to_snek_case();
+
+                let schema = prop.read();
+                let inline_enum = match schema.name() {
+                    Some(_) => None,
+                    None => self.build_enum(&schema, obj.name.clone() + &name.to_camel_case()),
+                };
+
+                let ty_path = match inline_enum {
+                    Some(e) => {
+                        let ty_path = format!("{}::{}", mod_prefix, e.name);
+                        obj.enums.push(e);
+                        ty_path
                     }
+                    None => self
+                        .build_def(&schema, false)
+                        .at(|| format!("/properties/{}", escape_token(name)))?
+                        .known_type(),
+                };
 
-                    let schema = prop.read();
-                    let ty = self.build_def(&schema, false)?;
+                obj.fields.push(ObjectField {
+                    // If we've modified the name, mark it for serde renaming.
+                    rename: if rust_ident(&new_name) != name.as_str() {
+                        Some(name.clone())
+                    } else {
+                        None
+                    },
+                    name: new_name,
+                    ty_path,
+                    // Nullable properties can't be required in Rust.
+                    is_required: !schema.is_nullable()
+                        && self.is_required_property(def, name, &mut vec![]),
+                    boxed: schema.is_cyclic(),
+                });
 
-                    obj.fields.push(ObjectField {
-                        // If we've modified the name, mark it for serde renaming.
-                        rename: if new_name != name.as_str() {
-                            Some(name.clone())
-                        } else {
-                            None
-                        },
-                        name: new_name,
-                        ty_path: ty.known_type(),
-                        is_required: def.is_required_property(name),
-                        boxed: schema.is_cyclic(),
-                    });
-
-                    Ok(())
-                })?
+                Ok(())
+            })?;
+
+        Ok(obj)
+    }
+
+    /// Collects the properties of the given definition (along with the
+    /// properties of the schemas in its `allOf` field). `visiting` has the
+    /// names of definitions we're already in (for detecting cycles).
+    fn collect_properties(
+        &self,
+        def: &E::Definition,
+        props: &mut BTreeMap<String, ArcRwLock<E::Definition>>,
+        visiting: &mut Vec<String>,
+    ) -> PaperClipResult<()> {
+        if let Some(members) = def.all_of() {
+            for schema in members {
+                let schema = schema.read();
+                let name = schema.name();
+                if let Some(n) = name {
+                    if visiting.iter().any(|v| v == n) {
+                        return Err(PaperClipError::CyclicAllOf(n.into()));
+                    }
+
+                    visiting.push(n.into());
+                }
+
+                self.collect_properties(&schema, props, visiting)?;
+                if name.is_some() {
+                    visiting.pop();
+                }
+
+                // If we're inheriting a definition with discriminator, then the
+                // discriminator property is owned by the (tagged) enum of that definition.
+                if let (Some(_), Some(d)) = (schema.name(), schema.discriminator()) {
+                    props.remove(d);
+                }
+            }
         }
 
-        Ok(EmittedUnit::Object(obj))
+        // Local properties override the ones from `allOf` schemas.
+        if let Some(p) = def.properties() {
+            props.extend(p.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+
+        Ok(())
+    }
+
+    /// Checks whether the given property is required by the given definition
+    /// (or by any of the schemas in its `allOf` field). Definitions in `visited`
+    /// are skipped.
+    fn is_required_property(
+        &self,
+        def: &E::Definition,
+        property: &str,
+        visited: &mut Vec<String>,
+    ) -> bool {
+        if let Some(name) = def.name() {
+            if visited.iter().any(|v| v == name) {
+                return false;
+            }
+
+            visited.push(name.into());
+        }
+
+        def.is_required_property(property)
+            || def.all_of().map_or(false, |members| {
+                members
+                    .iter()
+                    .any(|s| self.is_required_property(&s.read(), property, visited))
+            })
     }
 }
 
@@ -465,6 +1161,8 @@ where
 pub enum EmittedUnit {
     /// Object represented as a Rust struct.
     Object(ApiObject),
+    /// Enum represented as a Rust enum.
+    Enum(ApiEnum),
     /// Some Rust type.
     Known(String),
     /// Nothing to do.
@@ -0,0 +1,108 @@
+use crate::error::PaperClipResult;
+
+use std::path::PathBuf;
+#[cfg(not(feature = "codegen-fmt"))]
+use std::{
+    io::{self, Write},
+    process::{Command, Stdio},
+    thread,
+};
+
+/// Options for formatting the generated code with rustfmt.
+///
+/// With the `codegen-fmt` feature, the code is formatted using the rustfmt
+/// library. Otherwise, it's piped through an external `rustfmt` binary.
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    /// Rust edition used for parsing the code (`2015` or `2018`). Defaults to `2018`.
+    pub edition: &'static str,
+    /// Path to the rustfmt config file (`rustfmt.toml`), if any.
+    pub config_path: Option<PathBuf>,
+    /// Path to the rustfmt binary. Defaults to `rustfmt` (i.e., it's looked up in `PATH`).
+    /// If it's missing, then the code is left unformatted (with a warning).
+    ///
+    /// **NOTE:** This is ignored when the `codegen-fmt` feature is enabled.
+    pub binary: PathBuf,
+}
+
+impl FormatOptions {
+    /// Formats the given code using the rustfmt library.
+    #[cfg(feature = "codegen-fmt")]
+    pub(super) fn format(&self, code: &str) -> PaperClipResult<String> {
+        use rustfmt_nightly::{Config, Edition, EmitMode, Input, Session, Verbosity};
+
+        let mut config = match self.config_path.as_ref() {
+            Some(p) => Config::from_toml_path(p)?,
+            None => Config::default(),
+        };
+
+        config.set().edition(match self.edition {
+            "2015" => Edition::Edition2015,
+            _ => Edition::Edition2018,
+        });
+        config.set().emit_mode(EmitMode::Stdout);
+        config.set().verbose(Verbosity::Quiet);
+
+        let mut out = vec![];
+        {
+            let mut session = Session::new(config, Some(&mut out));
+            session.format(Input::Text(code.into()))?;
+        }
+
+        Ok(String::from_utf8_lossy(&out).into_owned())
+    }
+
+    /// Formats the given code by piping it through the rustfmt binary. If the
+    /// binary can't be found, then the code is returned as it is.
+    #[cfg(not(feature = "codegen-fmt"))]
+    pub(super) fn format(&self, code: &str) -> PaperClipResult<String> {
+        let mut cmd = Command::new(&self.binary);
+        cmd.args(&["--edition", self.edition]);
+        if let Some(p) = self.config_path.as_ref() {
+            cmd.arg("--config-path").arg(p);
+        }
+
+        let spawned = cmd
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn();
+        let mut child = match spawned {
+            Ok(c) => c,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+                warn!(
+                    "Cannot find rustfmt binary {:?}. Leaving the code unformatted.",
+                    self.binary
+                );
+                return Ok(code.into());
+            }
+            Err(e) => return Err(e.into()),
+        };
+        // Write from another thread, so that we don't block on a full stdout pipe.
+        let mut stdin = child.stdin.take().expect("piped stdin");
+        let code = code.to_owned();
+        let writer = thread::spawn(move || stdin.write_all(code.as_bytes()));
+
+        let output = child.wait_with_output()?;
+        writer.join().expect("rustfmt writer")?;
+        if !output.status.success() {
+            let msg = format!(
+                "rustfmt failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            return Err(io::Error::new(io::ErrorKind::Other, msg).into());
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            edition: "2018",
+            config_path: None,
+            binary: PathBuf::from("rustfmt"),
+        }
+    }
+}
@@ -1,21 +1,46 @@
 //! Code generation for OpenAPI v2.
 
 mod emitter;
+mod fmt;
 pub mod object;
+mod sink;
 mod state;
 
 pub use self::emitter::{EmittedUnit, Emitter};
-pub use self::state::EmitterState;
+pub use self::fmt::FormatOptions;
+pub use self::sink::{FileSystem, MemoryTree, OutputSink};
+pub use self::state::{CrateMeta, EmitterState};
 
 use super::Schema;
 
 use std::fmt::Debug;
 use std::marker::PhantomData;
 
-/// Common conflicting keywords in Rust. An underscore will be added
-/// to fields using these keywords.
-// FIXME: Fill this list!
-const RUST_KEYWORDS: &[&str] = &["type", "continue", "enum", "ref"];
+/// Keywords in Rust (strict and reserved, as of the 2018 edition), which
+/// can't be used as identifiers as they are (see `rust_ident`).
+const RUST_KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in",
+    "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// Keywords which can't be used as raw identifiers.
+const NON_RAW_KEYWORDS: &[&str] = &["crate", "self", "Self", "super"];
+
+/// Returns the given name as a valid identifier. Keywords are escaped as raw
+/// identifiers (`r#type`), or with an underscore suffix (`self_`) if they
+/// can't be raw identifiers.
+fn rust_ident(name: &str) -> String {
+    if NON_RAW_KEYWORDS.contains(&name) {
+        format!("{}_", name)
+    } else if RUST_KEYWORDS.contains(&name) {
+        format!("r#{}", name)
+    } else {
+        name.into()
+    }
+}
 
 /// Default emitter for anything that implements `Schema` trait.
 ///
@@ -35,7 +60,7 @@ impl<S> From<EmitterState> for DefaultEmitter<S> {
     }
 }
 
-impl<S: Schema + Debug> Emitter for DefaultEmitter<S> {
+impl<S: Schema + Debug + Send + Sync> Emitter for DefaultEmitter<S> {
     type Definition = S;
 
     fn state(&self) -> &EmitterState {
@@ -3,7 +3,8 @@
 //! This contains the necessary objects for generating actual
 //! API objects, their builders, impls, etc.
 
-use crate::v2::models::HttpMethod;
+use super::rust_ident;
+use crate::v2::models::{CollectionFormat, HttpMethod, MediaType, ParameterIn, StatusCode};
 use heck::{CamelCase, SnekCase};
 
 use std::collections::{BTreeMap, HashSet};
@@ -21,10 +22,51 @@ pub struct ApiObject {
     pub fields: Vec<ObjectField>,
     /// Paths with operations which address this object.
     pub paths: BTreeMap<String, PathOps>,
+    /// Enums defined inline in this object's properties.
+    pub enums: Vec<ApiEnum>,
 }
 
-/// Operations in a path.
+/// Represents a (simplified) Rust enum.
+#[derive(Debug, Clone)]
+pub struct ApiEnum {
+    /// Name of the enum (camel-cased).
+    pub name: String,
+    /// Whether the values are integers (strings otherwise).
+    pub is_integer: bool,
+    /// Variants of this enum.
+    pub variants: Vec<EnumVariant>,
+    /// Whether this enum has a catch-all `Other` variant for unknown values.
+    pub has_other: bool,
+    /// Name of the tag field, if this enum wraps subtypes (i.e., it's for a
+    /// definition with discriminator).
+    pub tag: Option<String>,
+    /// Struct for the definition itself, if this enum wraps subtypes
+    /// (written along with the enum).
+    pub base: Option<ApiObject>,
+}
+
+/// Variant of some enum.
+#[derive(Debug, Clone)]
+pub struct EnumVariant {
+    /// Name of the variant (camel-cased).
+    pub name: String,
+    /// Value of this variant (as it appears in the spec).
+    pub value: String,
+    /// Type wrapped by this variant (if any).
+    pub ty_path: Option<String>,
+}
+
+/// Represents a type alias for some definition.
 #[derive(Debug, Clone)]
+pub struct TypeAlias {
+    /// Name of the alias (camel-cased).
+    pub name: String,
+    /// Path to the aliased type.
+    pub ty_path: String,
+}
+
+/// Operations in a path.
+#[derive(Debug, Default, Clone)]
 pub struct PathOps {
     /// Operations for this object and their associated requirements.
     pub req: BTreeMap<HttpMethod, OpRequirement>,
@@ -44,23 +86,78 @@ pub struct OpRequirement {
     pub params: Vec<Parameter>,
     /// Whether the object itself is required (in body) for this operation.
     pub body_required: bool,
+    /// Responses declared for this operation.
+    pub responses: Vec<OpResponse>,
+    /// Media type of the request body or form (if the operation has either).
+    /// This decides the encoder (JSON, URL-encoded form or multipart form).
+    pub content_type: Option<MediaType>,
+    /// Value of the `Accept` header (based on the media types produced by
+    /// this operation), if any.
+    pub accept: Option<String>,
+}
+
+/// Response declared for some operation. Each of these becomes a variant
+/// in the response enum for that operation.
+#[derive(Debug, Clone)]
+pub struct OpResponse {
+    /// Status code (or range) of this response.
+    pub code: StatusCode,
+    /// Name of the variant (camel-cased).
+    pub variant: String,
+    /// Description of this response (if any).
+    pub description: Option<String>,
+    /// Type of the response body (if any) as a path.
+    pub ty_path: Option<String>,
+    /// How the response body is read.
+    pub body_format: BodyFormat,
+    /// Headers sent with this response.
+    pub headers: Vec<ResponseHeader>,
+}
+
+/// How the body of some response is read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyFormat {
+    /// Body is decoded from JSON into the response type (for error responses,
+    /// the raw body is kept if it can't be decoded).
+    Json,
+    /// Body is read as text (`String`).
+    Text,
+    /// Body is read as raw bytes (`Vec<u8>`).
+    Binary,
+}
+
+/// Header sent with some response.
+#[derive(Debug, Clone)]
+pub struct ResponseHeader {
+    /// Name of the header (as it appears in the spec).
+    pub name: String,
+    /// Name of the variant field (snake-cased).
+    pub field: String,
+    /// Type of the header value as a path.
+    pub ty_path: String,
+    /// Separator for the values, if this header is an array (`Vec<T>`).
+    pub separator: Option<&'static str>,
 }
 
 /// Represents some parameter somewhere (header, path, query, etc.).
 #[derive(Debug, Clone)]
 pub struct Parameter {
-    /// Name of the parameter (snake-cased).
+    /// Name of the parameter (as it appears in the spec).
     pub name: String,
     /// Type of the parameter as a path.
     pub ty_path: String,
     /// Whether this parameter is required.
     pub required: bool,
+    /// Where this parameter goes in the request.
+    pub presence: ParameterIn,
+    /// Format of the values if this parameter is an array (`Vec<T>`).
+    pub collection: Option<CollectionFormat>,
 }
 
 /// Represents a struct field.
 #[derive(Debug, Clone)]
 pub struct ObjectField {
-    /// Name of the field (snake-cased).
+    /// Name of the field (snake-cased). Keywords are escaped when the field is written.
     pub name: String,
     /// Actual name of the field (should it be serde-renamed).
     pub rename: Option<String>,
@@ -84,12 +181,19 @@ impl ApiObject {
             name: name.into(),
             fields: vec![],
             paths: BTreeMap::new(),
+            enums: vec![],
         }
     }
 
     /// Returns a struct representing the impl for this object.
-    pub fn impl_repr(&self) -> ApiObjectImpl<'_> {
-        ApiObjectImpl(self)
+    ///
+    /// `helper_module` is the path prefix (ending with `::`) of the module
+    /// which contains the generated `prelude` and `client` modules.
+    pub fn impl_repr<'a>(&'a self, helper_module: &'a str) -> ApiObjectImpl<'a> {
+        ApiObjectImpl {
+            inner: self,
+            helper_module,
+        }
     }
 
     /// Returns the builders for this object.
@@ -98,14 +202,22 @@ impl ApiObject {
     /// bound to any operation, then the builder only keeps track of the fields.
     // FIXME: Make operations generic across builders. This will reduce the
     // number of structs generated.
-    pub fn builders<'a>(&'a self) -> Box<Iterator<Item = ApiObjectBuilder<'a>> + 'a> {
+    pub fn builders<'a>(
+        &'a self,
+        helper_module: &'a str,
+    ) -> Box<dyn Iterator<Item = ApiObjectBuilder<'a>> + 'a> {
         if self.paths.is_empty() {
             return Box::new(iter::once(ApiObjectBuilder {
                 idx: 0,
                 object: &self.name,
+                rel_path: None,
                 method: None,
                 op_id: None,
                 body_required: true,
+                responses: &[],
+                content_type: None,
+                accept: None,
+                helper_module,
                 fields: &self.fields,
                 global_params: &[],
                 local_params: &[],
@@ -114,18 +226,23 @@ impl ApiObject {
 
         Box::new(
             self.paths
-                .values()
+                .iter()
                 .enumerate()
-                .flat_map(move |(idx, path_ops)| {
+                .flat_map(move |(idx, (path, path_ops))| {
                     path_ops
                         .req
                         .iter()
                         .map(move |(&method, req)| ApiObjectBuilder {
                             idx,
                             object: &self.name,
+                            rel_path: Some(path),
                             op_id: req.id.as_ref().map(String::as_str),
                             method: Some(method),
                             body_required: req.body_required,
+                            responses: &req.responses,
+                            content_type: req.content_type.as_ref(),
+                            accept: req.accept.as_ref().map(String::as_str),
+                            helper_module,
                             fields: &self.fields,
                             global_params: &path_ops.params,
                             local_params: &req.params,
@@ -136,22 +253,35 @@ impl ApiObject {
 }
 
 /// Represents the API object impl.
-pub struct ApiObjectImpl<'a>(&'a ApiObject);
+pub struct ApiObjectImpl<'a> {
+    inner: &'a ApiObject,
+    helper_module: &'a str,
+}
 
 /// Represents a builder struct for some API object.
 #[derive(Debug, Clone)]
 pub struct ApiObjectBuilder<'a> {
     idx: usize,
+    rel_path: Option<&'a str>,
     op_id: Option<&'a str>,
     method: Option<HttpMethod>,
     object: &'a str,
     body_required: bool,
+    responses: &'a [OpResponse],
+    content_type: Option<&'a MediaType>,
+    accept: Option<&'a str>,
+    helper_module: &'a str,
     fields: &'a [ObjectField],
     global_params: &'a [Parameter],
     local_params: &'a [Parameter],
 }
 
 impl<'a> ApiObjectBuilder<'a> {
+    /// Returns the method and path of the operation bound to this builder, if any.
+    pub(super) fn operation(&self) -> Option<(HttpMethod, &'a str)> {
+        Some((self.method?, self.rel_path?))
+    }
+
     /// Returns an iterator of all fields and parameters required for the Rust builder struct.
     ///
     /// **NOTE:** The names yielded by this iterator are unique for a builder.
@@ -198,20 +328,22 @@ impl<'a> ApiObjectBuilder<'a> {
                     )))
                 }
             })
-            .filter_map(|p| p);
+            .flatten();
 
-        // Check parameter-field collisions.
+        // Check parameter-field collisions. Names are compared in snake case,
+        // because that's how they end up in the builder.
         param_iter
             .chain(field_iter)
             .scan(HashSet::new(), |set, (name, ty, prop)| {
-                if set.contains(name) {
+                let sk = name.to_snek_case();
+                if set.contains(&sk) {
                     Some(None)
                 } else {
-                    set.insert(name);
+                    set.insert(sk);
                     Some(Some((name, ty, prop)))
                 }
             })
-            .filter_map(|p| p)
+            .flatten()
     }
 
     /// Returns whether this builder needs `repr(transparent)`
@@ -232,12 +364,573 @@ impl<'a> ApiObjectBuilder<'a> {
             .any(|(_, _, p)| p.is_parameter() || p.is_required())
     }
 
+    /// Returns the parameter with the given name (if any). Local parameters
+    /// take precedence over global parameters.
+    fn find_parameter(&self, name: &str) -> Option<&'a Parameter> {
+        self.local_params
+            .iter()
+            .chain(self.global_params.iter())
+            .find(|p| p.name == name)
+    }
+
+    /// Returns the object field with the given name (if any).
+    fn find_field(&self, name: &str) -> Option<&'a ObjectField> {
+        self.fields.iter().find(|f| f.name == name)
+    }
+
+    /// Returns the path (from `self`) to the parameter container and the object
+    /// (if it's required) in the builder struct.
+    fn inner_paths(&self) -> (&'static str, &'static str) {
+        if self.has_parameters() {
+            ("self.inner", "self.inner.inner")
+        } else {
+            ("self.inner", "self.inner")
+        }
+    }
+
+    /// Returns the name of the function (in the object's impl) which
+    /// returns this builder.
+    pub(super) fn constructor_name(&self) -> String {
+        let name = match (self.op_id, self.method) {
+            (Some(id), _) => id.to_snek_case(),
+            (None, Some(meth)) => {
+                let mut name = format!("{:?}", meth).to_snek_case();
+                if self.idx > 0 {
+                    name.push('_');
+                    name.push_str(&self.idx.to_string());
+                }
+
+                name
+            }
+            (None, None) => return String::from("builder"),
+        };
+
+        rust_ident(&name)
+    }
+
+    /// Write this builder's name along with its generic parameters into the
+    /// given formatter (see `write_generics_with`).
+    fn write_name_with_generics<F, M>(&self, f: &mut F, marker: M) -> fmt::Result
+    where
+        F: Write,
+        M: FnMut(&str) -> Option<String>,
+    {
+        self.write_name(f)?;
+        self.write_generics_with(f, marker)
+    }
+
+    /// Writes the function (in the object's impl) which creates this builder.
+    fn write_constructor<F>(&self, f: &mut F) -> fmt::Result
+    where
+        F: Write,
+    {
+        f.write_str("\n    #[inline]\n    pub fn ")?;
+        f.write_str(&self.constructor_name())?;
+        f.write_str("() -> ")?;
+        self.write_name_with_generics(f, |name| {
+            Some(format!(
+                "{}prelude::Missing{}",
+                self.helper_module,
+                name.to_camel_case()
+            ))
+        })?;
+
+        f.write_str(" {\n        ")?;
+        self.write_name(f)?;
+        if !self.has_atleast_one_field() && !self.body_required {
+            return f.write_str("\n    }\n");
+        }
+
+        f.write_str(" {")?;
+        if self.body_required || self.has_parameters() {
+            f.write_str("\n            inner: Default::default(),")?;
+        }
+
+        self.struct_fields_iter()
+            .filter(|(_, _, prop)| prop.is_required())
+            .try_for_each(|(name, _, prop)| {
+                f.write_str("\n            ")?;
+                if prop.is_parameter() {
+                    f.write_str("_param")?;
+                }
+
+                f.write_str("_")?;
+                f.write_str(&name.to_snek_case())?;
+                f.write_str(": core::marker::PhantomData,")
+            })?;
+
+        f.write_str("\n        }\n    }\n")
+    }
+
+    /// Writes the impl containing the setters for fields and parameters
+    /// (if there are any).
+    fn write_setters<F>(&self, f: &mut F) -> fmt::Result
+    where
+        F: Write,
+    {
+        let body_required = self.body_required;
+        let mut setters = self
+            .struct_fields_iter()
+            .filter(|(_, _, prop)| prop.is_parameter() || body_required)
+            .peekable();
+        if setters.peek().is_none() {
+            return Ok(());
+        }
+
+        let (params_path, body_path) = self.inner_paths();
+        f.write_str("\nimpl")?;
+        let mut generics = String::new();
+        self.write_generics_if_necessary(&mut generics)?;
+        f.write_str(&generics)?;
+        f.write_str(" ")?;
+        self.write_name(f)?;
+        f.write_str(&generics)?;
+        f.write_str(" {")?;
+
+        let mut is_first = true;
+        setters.try_for_each(|(name, ty, prop)| {
+            let sk = name.to_snek_case();
+            let container_field = if prop.is_parameter() {
+                format!("{}.param_{}", params_path, sk)
+            } else {
+                format!("{}.{}", body_path, rust_ident(name))
+            };
+
+            if !is_first {
+                f.write_str("\n")?;
+            }
+
+            is_first = false;
+            f.write_str("\n    #[inline]\n    pub fn ")?;
+            f.write_str(&rust_ident(&sk))?;
+            f.write_str("(mut self, value: impl Into<")?;
+            f.write_str(ty)?;
+            f.write_str(">) -> ")?;
+            if prop.is_required() {
+                self.write_name_with_generics(f, |n| {
+                    if n == name {
+                        Some(format!(
+                            "{}prelude::{}Exists",
+                            self.helper_module,
+                            name.to_camel_case()
+                        ))
+                    } else {
+                        None
+                    }
+                })?;
+            } else {
+                f.write_str("Self")?;
+            }
+
+            f.write_str(" {\n        ")?;
+            f.write_str(&container_field)?;
+            f.write_str(" = ")?;
+            let (is_required, boxed) = match self.find_field(name) {
+                Some(field) if prop.is_field() => (field.is_required, field.boxed),
+                _ => (false, false),
+            };
+
+            if !is_required {
+                f.write_str("Some(")?;
+            }
+
+            if boxed {
+                f.write_str("Box::new(")?;
+            }
+
+            f.write_str("value.into()")?;
+            if boxed {
+                f.write_str(")")?;
+            }
+
+            if !is_required {
+                f.write_str(")")?;
+            }
+
+            if prop.is_required() {
+                f.write_str(";\n        unsafe { core::mem::transmute(self) }\n    }")
+            } else {
+                f.write_str(";\n        self\n    }")
+            }
+        })?;
+
+        f.write_str("\n}\n")
+    }
+
+    /// Writes the impl containing `send` for sending the request (if this
+    /// builder is bound to an operation). This is only implemented once all
+    /// the required fields and parameters have been set.
+    fn write_send_if_possible<F>(&self, f: &mut F) -> fmt::Result
+    where
+        F: Write,
+    {
+        let (method, rel_path) = match (self.method, self.rel_path) {
+            (Some(m), Some(p)) => (m, p),
+            _ => return Ok(()),
+        };
+
+        let (params_path, body_path) = self.inner_paths();
+        f.write_str("\nimpl ")?;
+        self.write_name_with_generics(f, |name| {
+            Some(format!(
+                "{}prelude::{}Exists",
+                self.helper_module,
+                name.to_camel_case()
+            ))
+        })?;
+
+        f.write_str(" {\n    pub fn send<C: ")?;
+        f.write_str(self.helper_module)?;
+        f.write_str("client::ApiClient>(&self, client: &C) -> Result<")?;
+        self.write_response_name(f)?;
+        f.write_str(", reqwest::Error> {\n        ")?;
+
+        let params = self
+            .struct_fields_iter()
+            .filter(|(_, _, prop)| prop.is_parameter())
+            .filter_map(|(name, _, _)| self.find_parameter(name))
+            .collect::<Vec<_>>();
+        let needs_mut = self.body_required
+            || self.accept.is_some()
+            || params.iter().any(|p| match p.presence {
+                ParameterIn::Query | ParameterIn::Header | ParameterIn::FormData => true,
+                _ => false,
+            });
+
+        f.write_str(if needs_mut { "let mut" } else { "let" })?;
+        write!(
+            f,
+            " request = client.request_builder(reqwest::Method::{}, ",
+            format!("{:?}", method).to_uppercase()
+        )?;
+        self.write_path_template(f, rel_path, params_path)?;
+        f.write_str(");")?;
+        if let Some(accept) = self.accept {
+            write!(
+                f,
+                "\n        request = request.header(reqwest::header::ACCEPT, {:?});",
+                accept
+            )?;
+        }
+
+        let multipart = self
+            .content_type
+            .map_or(false, MediaType::is_multipart_form);
+        let mut has_form = false;
+        for param in &params {
+            let field = format!("{}.param_{}", params_path, param.name.to_snek_case());
+            let mut stmt = match param.presence {
+                ParameterIn::Query => {
+                    format!("request = request.query(&[(\"{}\", v)]);", param.name)
+                }
+                ParameterIn::Header => format!(
+                    "request = request.header(\"{}\", v.to_string());",
+                    param.name
+                ),
+                ParameterIn::FormData => {
+                    if !has_form {
+                        f.write_str(if multipart {
+                            "\n        let mut form = reqwest::multipart::Form::new();"
+                        } else {
+                            "\n        let mut form = vec![];"
+                        })?;
+                        has_form = true;
+                    }
+
+                    if multipart {
+                        format!("form = form.text(\"{}\", v.to_string());", param.name)
+                    } else {
+                        format!("form.push((\"{}\", v.to_string()));", param.name)
+                    }
+                }
+                _ => continue,
+            };
+
+            // Arrays are either joined (with the separator for the collection format)
+            // or added as multiple instances of the parameter.
+            match param.collection.map(CollectionFormat::separator) {
+                Some(Some(sep)) => {
+                    stmt = format!(
+                        "let v = v.iter().map(ToString::to_string).collect::<Vec<_>>().join({:?});\n            {}",
+                        sep, stmt
+                    );
+                }
+                Some(None) => {
+                    stmt = format!("for v in v {{\n                {}\n            }}", stmt);
+                }
+                None => (),
+            }
+
+            write!(
+                f,
+                "\n        if let Some(v) = {}.as_ref() {{\n            {}\n        }}",
+                field, stmt
+            )?;
+        }
+
+        // Encoders set the content type by themselves. We only need to override
+        // it for other media types (say, `application/merge-patch+json`).
+        let default_type = if has_form && multipart {
+            f.write_str("\n        request = request.multipart(form);")?;
+            None
+        } else if has_form {
+            f.write_str("\n        request = request.form(&form);")?;
+            Some("application/x-www-form-urlencoded")
+        } else if self.body_required {
+            let form = self
+                .content_type
+                .map_or(false, MediaType::is_form_urlencoded);
+            write!(
+                f,
+                "\n        request = request.{}(&{});",
+                if form { "form" } else { "json" },
+                body_path
+            )?;
+            Some(if form {
+                "application/x-www-form-urlencoded"
+            } else {
+                "application/json"
+            })
+        } else {
+            None
+        };
+
+        if let (Some(default), Some(media)) = (default_type, self.content_type) {
+            let media = media.to_string();
+            if media != default {
+                write!(
+                    f,
+                    "\n        request = request.header(reqwest::header::CONTENT_TYPE, {:?});",
+                    media
+                )?;
+            }
+        }
+
+        self.write_response_match(f)?;
+        f.write_str("\n    }\n}\n")
+    }
+
+    /// Writes the statements which convert the response into the
+    /// response enum (one arm for each declared response).
+    fn write_response_match<F>(&self, f: &mut F) -> fmt::Result
+    where
+        F: Write,
+    {
+        let has_body = self.responses.iter().any(|r| r.ty_path.is_some());
+        f.write_str(if has_body {
+            "\n        let mut"
+        } else {
+            "\n        let"
+        })?;
+        f.write_str(" response = request.send()?;")?;
+        f.write_str("\n        Ok(match response.status().as_u16() {")?;
+
+        // Responses are ordered from the most specific to the least specific,
+        // so the arms can be written in that order.
+        for resp in self.responses {
+            f.write_str("\n            ")?;
+            match resp.code {
+                StatusCode::Code(c) => write!(f, "{} => ", c)?,
+                StatusCode::Range(c) => write!(f, "status @ {}00..={}99 => ", c, c)?,
+                StatusCode::Default => f.write_str("status => ")?,
+            }
+
+            self.write_response_variant_value(f, resp)?;
+        }
+
+        if !self.responses.iter().any(|r| r.code == StatusCode::Default) {
+            f.write_str("\n            _ => ")?;
+            self.write_response_name(f)?;
+            f.write_str("::Other(response),")?;
+        }
+
+        f.write_str("\n        })")
+    }
+
+    /// Writes the value of the variant for the given response (headers
+    /// are read before the body is decoded).
+    fn write_response_variant_value<F>(&self, f: &mut F, resp: &OpResponse) -> fmt::Result
+    where
+        F: Write,
+    {
+        self.write_response_name(f)?;
+        f.write_str("::")?;
+        f.write_str(&resp.variant)?;
+        if resp.is_unit() {
+            return f.write_str(",");
+        }
+
+        f.write_str(" {")?;
+        if resp.has_status() {
+            f.write_str("\n                status,")?;
+        }
+
+        for header in &resp.headers {
+            write!(
+                f,
+                "\n                {}: response.headers().get({:?}).and_then(|v| v.to_str().ok())",
+                header.field, header.name
+            )?;
+            match header.separator {
+                Some(sep) => write!(
+                    f,
+                    ".map(|v| v.split({:?}).filter_map(|v| v.trim().parse().ok()).collect()),",
+                    sep
+                )?,
+                None => f.write_str(".and_then(|v| v.parse().ok()),")?,
+            }
+        }
+
+        if resp.is_lossy() {
+            write!(
+                f,
+                "\n                body: {}client::decode_json_lossy(&mut response)?,",
+                self.helper_module
+            )?;
+        } else if resp.ty_path.is_some() {
+            f.write_str(match resp.body_format {
+                BodyFormat::Json => "\n                body: response.json()?,",
+                BodyFormat::Text => "\n                body: response.text()?,",
+                BodyFormat::Binary => {
+                    "\n                body: {\n                    let mut body = vec![];\n                    response.copy_to(&mut body)?;\n                    body\n                },"
+                }
+            })?;
+        }
+
+        f.write_str("\n            },")
+    }
+
+    /// Writes the response enum for the operation bound to this builder (if any).
+    fn write_response_enum<F>(&self, f: &mut F) -> fmt::Result
+    where
+        F: Write,
+    {
+        let (method, rel_path) = match self.operation() {
+            Some(op) => op,
+            None => return Ok(()),
+        };
+
+        write!(
+            f,
+            "\n/// Responses for `{} {}`.\n#[derive(Debug)]\npub enum ",
+            format!("{:?}", method).to_uppercase(),
+            rel_path
+        )?;
+        self.write_response_name(f)?;
+        f.write_str(" {")?;
+
+        for resp in self.responses {
+            if let Some(desc) = resp.description.as_ref() {
+                for line in desc.trim().lines() {
+                    f.write_str("\n    ///")?;
+                    if !line.trim().is_empty() {
+                        f.write_str(" ")?;
+                        f.write_str(line.trim_end())?;
+                    }
+                }
+            }
+
+            f.write_str("\n    ")?;
+            f.write_str(&resp.variant)?;
+            if resp.is_unit() {
+                f.write_str(",")?;
+                continue;
+            }
+
+            f.write_str(" {")?;
+            if resp.has_status() {
+                f.write_str("\n        status: u16,")?;
+            }
+
+            for header in &resp.headers {
+                write!(f, "\n        {}: Option<{}>,", header.field, header.ty_path)?;
+            }
+
+            match resp.ty_path.as_ref() {
+                Some(ty) if resp.is_lossy() => write!(
+                    f,
+                    "\n        /// Decoded body (or the raw body, if it couldn't be decoded).\n        body: Result<{}, Vec<u8>>,",
+                    ty
+                )?,
+                Some(ty) => write!(f, "\n        body: {},", ty)?,
+                None => (),
+            }
+
+            f.write_str("\n    },")?;
+        }
+
+        if !self.responses.iter().any(|r| r.code == StatusCode::Default) {
+            f.write_str(
+                "\n    /// Response with an undeclared status code.\n    Other(reqwest::Response),",
+            )?;
+        }
+
+        f.write_str("\n}\n")
+    }
+
+    /// Writes the given path template as a `format!` call (if it has parameters)
+    /// which substitutes the values of path parameters.
+    fn write_path_template<F>(&self, f: &mut F, template: &str, params_path: &str) -> fmt::Result
+    where
+        F: Write,
+    {
+        let mut fmt_string = String::new();
+        let mut args = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            if c != '{' {
+                if c == '}' {
+                    fmt_string.push('}');
+                }
+
+                fmt_string.push(c);
+                continue;
+            }
+
+            let name = chars.by_ref().take_while(|&c| c != '}').collect::<String>();
+            match self.find_parameter(&name) {
+                Some(p) if p.presence == ParameterIn::Path => {
+                    // Values are percent-encoded, so that they stay in their segments.
+                    fmt_string.push_str("{}");
+                    let _ = write!(
+                        args,
+                        ", {}client::encode_path_segment(&{}.param_{}.as_ref().expect(\"missing parameter {}?\")",
+                        self.helper_module,
+                        params_path,
+                        name.to_snek_case(),
+                        name
+                    );
+                    if let Some(sep) = p.collection.and_then(CollectionFormat::separator) {
+                        let _ = write!(
+                            args,
+                            ".iter().map(ToString::to_string).collect::<Vec<_>>().join({:?})",
+                            sep
+                        );
+                    }
+
+                    args.push_str(".to_string())");
+                }
+                _ => {
+                    fmt_string.push_str("{{");
+                    fmt_string.push_str(&name);
+                    fmt_string.push_str("}}");
+                }
+            }
+        }
+
+        if args.is_empty() {
+            write!(f, "{:?}", template)
+        } else {
+            write!(f, "&format!({:?}{})", fmt_string, args)
+        }
+    }
+
     /// Write this builder's name into the given formatter.
     fn write_name<F>(&self, f: &mut F) -> fmt::Result
     where
         F: Write,
     {
-        f.write_str(&self.object)?;
+        f.write_str(self.object)?;
         if let Some(method) = self.method {
             write!(f, "{:?}", method)?;
         }
@@ -250,10 +943,40 @@ impl<'a> ApiObjectBuilder<'a> {
         Ok(())
     }
 
+    /// Write the name of the response enum for this builder into the given formatter.
+    fn write_response_name<F>(&self, f: &mut F) -> fmt::Result
+    where
+        F: Write,
+    {
+        f.write_str(self.object)?;
+        if let Some(method) = self.method {
+            write!(f, "{:?}", method)?;
+        }
+
+        f.write_str("Response")?;
+        if self.idx > 0 {
+            f.write_str(&self.idx.to_string())?;
+        }
+
+        Ok(())
+    }
+
     /// Writes generic stuff to the struct definition, if needed.
     fn write_generics_if_necessary<F>(&self, f: &mut F) -> fmt::Result
     where
         F: Write,
+    {
+        self.write_generics_with(f, |_| None)
+    }
+
+    /// Writes the generic parameters (if any) into the given formatter. For each
+    /// property which is required, the given closure decides the type (a marker
+    /// type from the prelude for example). If it returns `None`, then the generic
+    /// parameter itself is used.
+    fn write_generics_with<F, M>(&self, f: &mut F, mut marker: M) -> fmt::Result
+    where
+        F: Write,
+        M: FnMut(&str) -> Option<String>,
     {
         let mut is_generic = false;
         // Inspect fields and parameters and write generics.
@@ -268,7 +991,10 @@ impl<'a> ApiObjectBuilder<'a> {
                     f.write_str(", ")?;
                 }
 
-                f.write_str(&name.to_camel_case())
+                match marker(name) {
+                    Some(ty) => f.write_str(&ty),
+                    None => f.write_str(&name.to_camel_case()),
+                }
             })?;
 
         if is_generic {
@@ -285,7 +1011,7 @@ impl<'a> ApiObjectBuilder<'a> {
     {
         if self.body_required {
             f.write_str("\n    inner: ")?;
-            f.write_str(&self.object)?;
+            f.write_str(self.object)?;
             f.write_str(",")?;
         }
 
@@ -305,9 +1031,9 @@ impl<'a> ApiObjectBuilder<'a> {
     {
         if prop.is_parameter() {
             f.write_str("\n    param_")?;
-            f.write_str(&name)?;
+            f.write_str(name)?;
             f.write_str(": Option<")?;
-            f.write_str(&ty)?;
+            f.write_str(ty)?;
             f.write_str(">,")?;
         }
 
@@ -350,28 +1076,39 @@ impl Property {
     }
 }
 
+impl OpResponse {
+    /// Whether the variant for this response carries the actual status code
+    /// (i.e., it's a range or the default response).
+    fn has_status(&self) -> bool {
+        match self.code {
+            StatusCode::Code(_) => false,
+            _ => true,
+        }
+    }
+
+    /// Whether the JSON body of this response is decoded on a best-effort basis
+    /// (i.e., it's an error or default response).
+    fn is_lossy(&self) -> bool {
+        self.ty_path.is_some() && self.body_format == BodyFormat::Json && !self.code.is_success()
+    }
+
+    /// Whether the variant for this response doesn't have any fields.
+    fn is_unit(&self) -> bool {
+        !self.has_status() && self.ty_path.is_none() && self.headers.is_empty()
+    }
+}
+
 impl<'a> Display for ApiObjectImpl<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.write_str("impl ")?;
-        f.write_str(&self.0.name)?;
+        f.write_str(&self.inner.name)?;
         f.write_str(" {")?;
 
-        // self.0.builders().try_for_each(|builder| {
-        //     f.write_str("\n    #[inline]\n    pub fn ")?;
-        //     if let Some(_id) = builder.op_id {
-        //         // TODO
-        //     } else if let Some(m) = builder.method {
-        //         // TODO
-        //     } else {
-        //         f.write_str("builder")?;
-        //     }
-
-        //     f.write_str("() -> ")?;
-        //     builder.write_name(f)?;
-        //     f.write_str(" {\n        Default::default()\n    }")
-        // })?;
+        self.inner
+            .builders(self.helper_module)
+            .try_for_each(|builder| builder.write_constructor(f))?;
 
-        f.write_str("\n}\n")
+        f.write_str("}\n")
     }
 }
 
@@ -414,7 +1151,7 @@ impl<'a> Display for ApiObjectBuilder<'a> {
         self.struct_fields_iter().try_for_each(|(name, ty, prop)| {
             let (cc, sk) = (name.to_camel_case(), name.to_snek_case());
             // 'container' is meant for parameters.
-            self.write_parameter_if_required(prop, &sk, &ty, &mut container)?;
+            self.write_parameter_if_required(prop, &sk, ty, &mut container)?;
 
             if prop.is_required() {
                 f.write_str("\n    ")?;
@@ -445,7 +1182,9 @@ impl<'a> Display for ApiObjectBuilder<'a> {
             f.write_str("\n}\n")?;
         }
 
-        Ok(())
+        self.write_setters(f)?;
+        self.write_send_if_possible(f)?;
+        self.write_response_enum(f)
     }
 }
 
@@ -465,7 +1204,7 @@ impl Display for ApiObject {
             }
 
             f.write_str("pub ")?;
-            f.write_str(&field.name)?;
+            f.write_str(&rust_ident(&field.name))?;
             f.write_str(": ")?;
             if !field.is_required {
                 f.write_str("Option<")?;
@@ -493,15 +1232,135 @@ impl Display for ApiObject {
             f.write_str("\n")?;
         }
 
-        f.write_str("}\n")
+        f.write_str("}\n")?;
+        self.enums.iter().try_for_each(|e| write!(f, "\n{}", e))
     }
 }
 
-impl Default for PathOps {
-    fn default() -> Self {
-        PathOps {
-            req: BTreeMap::new(),
-            params: vec![],
+impl ApiEnum {
+    /// Writes the conversions used by serde for (de)serializing integer enums.
+    fn write_integer_conversions(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.has_other {
+            f.write_str("\nimpl From<i64> for ")?;
+            f.write_str(&self.name)?;
+            f.write_str(" {\n    fn from(value: i64) -> Self {\n        match value {")?;
+        } else {
+            f.write_str("\nimpl std::convert::TryFrom<i64> for ")?;
+            f.write_str(&self.name)?;
+            f.write_str(" {\n    type Error = String;\n")?;
+            f.write_str("\n    fn try_from(value: i64) -> Result<Self, Self::Error> {")?;
+            f.write_str("\n        match value {")?;
+        }
+
+        self.variants.iter().try_for_each(|v| {
+            write!(f, "\n            {} => ", v.value)?;
+            if self.has_other {
+                write!(f, "{}::{},", self.name, v.name)
+            } else {
+                write!(f, "Ok({}::{}),", self.name, v.name)
+            }
+        })?;
+
+        if self.has_other {
+            write!(f, "\n            value => {}::Other(value),", self.name)?;
+        } else {
+            write!(
+                f,
+                "\n            _ => Err(format!(\"unknown value for {}: {{}}\", value)),",
+                self.name
+            )?;
         }
+
+        f.write_str("\n        }\n    }\n}\n")?;
+        write!(
+            f,
+            "\nimpl From<{}> for i64 {{\n    fn from(value: {}) -> i64 {{\n        match value {{",
+            self.name, self.name
+        )?;
+        self.variants.iter().try_for_each(|v| {
+            write!(f, "\n            {}::{} => {},", self.name, v.name, v.value)
+        })?;
+        if self.has_other {
+            write!(f, "\n            {}::Other(value) => value,", self.name)?;
+        }
+
+        f.write_str("\n        }\n    }\n}\n")
+    }
+}
+
+impl Display for ApiEnum {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(tag) = self.tag.as_ref() {
+            f.write_str("#[derive(Debug, Clone, Deserialize, Serialize)]")?;
+            write!(f, "\n#[serde(tag = {:?})]", tag)?;
+        } else {
+            f.write_str("#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]")?;
+        }
+
+        if self.is_integer {
+            let from = if self.has_other { "from" } else { "try_from" };
+            write!(f, "\n#[serde({} = \"i64\", into = \"i64\")]", from)?;
+        }
+
+        f.write_str("\npub enum ")?;
+        f.write_str(&self.name)?;
+        f.write_str(" {")?;
+
+        self.variants.iter().try_for_each(|v| {
+            f.write_str("\n    ")?;
+            if !self.is_integer && v.name != v.value {
+                write!(f, "#[serde(rename = {:?})]\n    ", v.value)?;
+            }
+
+            f.write_str(&v.name)?;
+            if let Some(ty) = v.ty_path.as_ref() {
+                write!(f, "({})", ty)?;
+            }
+
+            f.write_str(",")
+        })?;
+
+        if self.has_other {
+            if self.is_integer {
+                f.write_str("\n    Other(i64),")?;
+            } else {
+                f.write_str("\n    #[serde(untagged)]\n    Other(String),")?;
+            }
+        }
+
+        f.write_str("\n}\n")?;
+
+        // Structs derive `Default`, so enums (used as fields) need it too.
+        let default = &self.variants[0];
+        write!(
+            f,
+            "\nimpl Default for {} {{\n    fn default() -> Self {{\n        {}::{}",
+            self.name, self.name, default.name
+        )?;
+        if default.ty_path.is_some() {
+            f.write_str("(Default::default())")?;
+        }
+
+        f.write_str("\n    }\n}\n")?;
+
+        if self.is_integer {
+            self.write_integer_conversions(f)?;
+        }
+
+        if let Some(base) = self.base.as_ref() {
+            write!(f, "\n{}", base)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for TypeAlias {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("pub type ")?;
+        f.write_str(&self.name)?;
+        f.write_str(" = ")?;
+        f.write_str(&self.ty_path)?;
+        f.write_str(";\n")
     }
 }
@@ -0,0 +1,142 @@
+use crate::error::PaperClipResult;
+use parking_lot::RwLock;
+
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Destination for the files generated by the emitter.
+///
+/// The paths given to the sink are the ones computed by the emitter
+/// (i.e., they're inside the working directory of `EmitterState`).
+pub trait OutputSink: Debug + Send + Sync {
+    /// Creates the directory at the given path (along with its parents, if
+    /// they're missing). This is called before writing files to that directory.
+    fn create_dir_all(&self, path: &Path) -> PaperClipResult<()>;
+
+    /// Writes the given contents to a file at the given path (truncating
+    /// the file if it exists).
+    fn write(&self, path: &Path, contents: &str) -> PaperClipResult<()>;
+
+    /// Appends the given contents to the file at the given path (creating
+    /// the file if it doesn't exist).
+    fn append(&self, path: &Path, contents: &str) -> PaperClipResult<()>;
+
+    /// Reads the contents of the file at the given path (which has
+    /// been written before).
+    fn read(&self, path: &Path) -> PaperClipResult<String>;
+}
+
+/// Sink which writes the generated files to disk (default).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FileSystem;
+
+impl OutputSink for FileSystem {
+    fn create_dir_all(&self, path: &Path) -> PaperClipResult<()> {
+        fs::create_dir_all(path)?;
+        Ok(())
+    }
+
+    fn write(&self, path: &Path, contents: &str) -> PaperClipResult<()> {
+        let mut fd = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(path)?;
+        fd.write_all(contents.as_bytes())?;
+        Ok(())
+    }
+
+    fn append(&self, path: &Path, contents: &str) -> PaperClipResult<()> {
+        let mut fd = OpenOptions::new().create(true).append(true).open(path)?;
+        fd.write_all(contents.as_bytes())?;
+        Ok(())
+    }
+
+    fn read(&self, path: &Path) -> PaperClipResult<String> {
+        Ok(fs::read_to_string(path)?)
+    }
+}
+
+/// Sink which holds the generated files in memory (as a map of paths
+/// to their contents), so that they can be inspected or processed
+/// before writing them to disk (if at all).
+///
+/// ```rust,no_run
+/// use paperclip_openapi::v2::{self, Api, DefaultSchema};
+/// use paperclip_openapi::v2::codegen::{DefaultEmitter, Emitter, EmitterState, MemoryTree};
+///
+/// use std::sync::Arc;
+///
+/// let api: Api<DefaultSchema> = v2::from_path("my_spec.yaml").unwrap();
+/// let tree = Arc::new(MemoryTree::default());
+/// let mut state = EmitterState::default();
+/// state.working_dir = "src/gen".into();
+/// state.sink = tree.clone();
+///
+/// let emitter = DefaultEmitter::from(state);
+/// emitter.generate(&api.resolve().unwrap()).unwrap();
+/// for (path, contents) in tree.files() {
+///     println!("{}: {} bytes", path.display(), contents.len());
+/// }
+///
+/// tree.write_to_disk().unwrap();
+/// ```
+#[derive(Debug, Default)]
+pub struct MemoryTree {
+    files: RwLock<BTreeMap<PathBuf, String>>,
+}
+
+impl MemoryTree {
+    /// Returns the generated files (sorted by their paths) and their contents.
+    pub fn files(&self) -> BTreeMap<PathBuf, String> {
+        self.files.read().clone()
+    }
+
+    /// Returns the contents of the file at the given path (if it exists).
+    pub fn file<P: AsRef<Path>>(&self, path: P) -> Option<String> {
+        self.files.read().get(path.as_ref()).cloned()
+    }
+
+    /// Writes the generated files to disk (creating the directories as needed).
+    pub fn write_to_disk(&self) -> PaperClipResult<()> {
+        for (path, contents) in &*self.files.read() {
+            if let Some(dir) = path.parent() {
+                FileSystem.create_dir_all(dir)?;
+            }
+
+            FileSystem.write(path, contents)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl OutputSink for MemoryTree {
+    fn create_dir_all(&self, _path: &Path) -> PaperClipResult<()> {
+        // Directories are implied by the paths of the files.
+        Ok(())
+    }
+
+    fn write(&self, path: &Path, contents: &str) -> PaperClipResult<()> {
+        self.files.write().insert(path.into(), contents.into());
+        Ok(())
+    }
+
+    fn append(&self, path: &Path, contents: &str) -> PaperClipResult<()> {
+        self.files
+            .write()
+            .entry(path.into())
+            .or_default()
+            .push_str(contents);
+        Ok(())
+    }
+
+    fn read(&self, path: &Path) -> PaperClipResult<String> {
+        self.file(path).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("{:?} not found", path)).into()
+        })
+    }
+}
@@ -1,41 +1,200 @@
-use super::object::ApiObject;
-use failure::Error;
+use super::fmt::FormatOptions;
+use super::object::{ApiEnum, ApiObject, TypeAlias};
+use super::sink::{FileSystem, OutputSink};
+use crate::error::{PaperClipError, PaperClipResult};
+use crate::v2::models::{HttpMethod, Info};
 use heck::CamelCase;
+use parking_lot::RwLock;
 
-use std::cell::RefCell;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::Write as _;
-use std::fs::OpenOptions;
-use std::io::Write;
+use std::fs;
 use std::path::{Path, PathBuf};
-use std::rc::Rc;
+use std::sync::Arc;
+
+/// Module (in the root module) containing the client used for sending
+/// requests through the builders.
+const CLIENT_MODULE: &str = "
+pub mod client {
+    /// Common interface for clients which can send API requests.
+    pub trait ApiClient {
+        /// Returns a request builder for the given method and path
+        /// (relative to the API root).
+        fn request_builder(&self, method: reqwest::Method, rel_path: &str) -> reqwest::RequestBuilder;
+    }
+
+    /// Default client, which prepends a base URL to the paths of all requests.
+    #[derive(Debug, Clone)]
+    pub struct Client {
+        inner: reqwest::Client,
+        base_url: String,
+    }
+
+    impl Client {
+        /// Creates a client for the given base URL (scheme, host and base path).
+        pub fn new<U: Into<String>>(base_url: U) -> Self {
+            Client::with_client(reqwest::Client::new(), base_url)
+        }
+
+        /// Creates a client for the given base URL using an existing `reqwest` client.
+        pub fn with_client<U: Into<String>>(inner: reqwest::Client, base_url: U) -> Self {
+            let mut base_url = base_url.into();
+            while base_url.ends_with('/') {
+                base_url.pop();
+            }
+
+            Client { inner, base_url }
+        }
+    }
+
+    impl ApiClient for Client {
+        fn request_builder(&self, method: reqwest::Method, rel_path: &str) -> reqwest::RequestBuilder {
+            self.inner.request(method, &(self.base_url.clone() + rel_path))
+        }
+    }
+
+    /// Percent-encodes the given value for use as a path segment.
+    pub fn encode_path_segment(value: &str) -> String {
+        // Dot segments would be removed from the URL.
+        if value == \".\" || value == \"..\" {
+            return value.replace('.', \"%2E\");
+        }
+
+        let mut encoded = String::with_capacity(value.len());
+        for byte in value.bytes() {
+            match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'!'
+                | b'$' | b'&' | b'\\'' | b'(' | b')' | b'*' | b'+' | b',' | b';' | b'='
+                | b':' | b'@' => encoded.push(byte as char),
+                _ => encoded.push_str(&format!(\"%{:02X}\", byte)),
+            }
+        }
+
+        encoded
+    }
+
+    /// Decodes the JSON body of the given response, falling back to the raw body
+    /// if it can't be decoded (error responses don't always match the spec).
+    pub fn decode_json_lossy<T>(response: &mut reqwest::Response) -> reqwest::Result<Result<T, Vec<u8>>>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let mut body = vec![];
+        response.copy_to(&mut body)?;
+        Ok(serde_json::from_slice(&body).map_err(|_| body))
+    }
+";
+
+/// Default client (added to the client module if the spec has a host).
+const DEFAULT_CLIENT: &str = "
+    /// Base URL of the API (from the scheme, host and base path in the spec).
+    pub const DEFAULT_BASE_URL: &str = {base_url:?};
+
+    impl Default for Client {
+        fn default() -> Self {
+            Client::new(DEFAULT_BASE_URL)
+        }
+    }
+";
+
+/// Manifest of the generated crate.
+const MANIFEST_TEMPLATE: &str = r#"[package]
+name = "{name}"
+version = "{version}"
+authors = [{authors}]
+edition = "2018"
+
+[lib]
+path = "lib.rs"
+
+[dependencies]
+reqwest = "0.9"
+serde = "{serde_version}"
+serde_derive = "{serde_version}"
+serde_json = "1.0"
+"#;
+
+/// Metadata for the generated crate.
+#[derive(Debug, Default, Clone)]
+pub struct CrateMeta {
+    /// Name of the crate. Defaults to the name of the working directory.
+    pub name: Option<String>,
+    /// Version of the crate. Defaults to `0.1.0`.
+    pub version: Option<String>,
+    /// Authors of the crate.
+    pub authors: Vec<String>,
+}
 
 /// Holds the state for your schema emitter.
+///
+/// The state is shared (and can be accessed) across threads, so that
+/// definitions and paths can be processed in parallel.
 #[derive(Debug, Clone)]
 pub struct EmitterState {
     /// Working directory - the path in which the necessary modules are generated.
     pub working_dir: PathBuf,
     /// Namespace separation string.
-    pub ns_sep: &'static str,
+    pub ns_sep: String,
+    /// Path of the working directory (as a module) from the crate root, ending
+    /// with `::` (say, `crate::gen::k8s::`). This is used as the prefix for
+    /// paths to the generated types and modules. Defaults to `crate::`.
+    ///
+    /// **NOTE:** This is ignored when generating a standalone crate (see `crate_meta`).
+    pub mod_prefix: String,
+    /// Whether to add a catch-all `Other` variant to the generated enums
+    /// (for values which are not in the spec).
+    ///
+    /// **NOTE:** For string enums, this requires `serde >= 1.0.181` in the
+    /// generated crate.
+    pub enum_other_variant: bool,
+    /// If this is set, then a standalone crate (with manifest, `lib.rs` and README)
+    /// is generated in the working directory.
+    ///
+    /// **NOTE:** In this case, the `prelude` and `client` modules are added to the
+    /// crate root (instead of the root module generated from the definitions).
+    pub crate_meta: Option<CrateMeta>,
+    /// Name of the top-level module (in the working directory) to which the `prelude`
+    /// and `client` modules are added. If this is not set, then the top-level module
+    /// is used only if there's one (i.e., all definitions share their first namespace),
+    /// and it's an error otherwise. If some definitions don't have namespaces (or if
+    /// there are no definitions), then a `mod.rs` declaring the top-level modules
+    /// is written to the working directory, which is then the default root module.
+    ///
+    /// **NOTE:** This is ignored when generating a standalone crate (see `crate_meta`).
+    pub root_module: Option<String>,
+    /// Destination for the generated files. Defaults to `FileSystem`, which
+    /// writes them to disk (use `MemoryTree` for holding them in memory).
+    pub sink: Arc<dyn OutputSink>,
+    /// If this is set, then the generated modules are formatted with rustfmt
+    /// once they've been written. This is set by default only if the
+    /// `codegen-fmt` feature is enabled.
+    pub format: Option<FormatOptions>,
     /// Maps parent mod to immediate children. Used for declaring modules.
-    pub(super) mod_children: Rc<RefCell<HashMap<PathBuf, HashSet<String>>>>,
+    pub(super) mod_children: Arc<RwLock<BTreeMap<PathBuf, BTreeSet<String>>>>,
     /// Holds generated struct definitions for leaf modules.
-    pub(super) def_mods: Rc<RefCell<HashMap<PathBuf, ApiObject>>>,
+    pub(super) def_mods: Arc<RwLock<BTreeMap<PathBuf, ApiObject>>>,
+    /// Holds type aliases for leaf modules.
+    pub(super) alias_mods: Arc<RwLock<BTreeMap<PathBuf, TypeAlias>>>,
+    /// Holds enums for leaf modules.
+    pub(super) enum_mods: Arc<RwLock<BTreeMap<PathBuf, ApiEnum>>>,
     /// Unit types used by builders.
-    unit_types: Rc<RefCell<HashSet<String>>>,
-    /// Root module emitted by codegen.
-    root_module: Rc<RefCell<Option<String>>>,
+    unit_types: Arc<RwLock<BTreeSet<String>>>,
+    /// Module (chosen from the top-level modules) to which the helpers are added.
+    /// If this is `None`, then they're added to `mod.rs` in the working directory.
+    helper_module: Arc<RwLock<Option<String>>>,
+    /// Modules written so far (for formatting them later).
+    written_mods: Arc<RwLock<BTreeSet<PathBuf>>>,
 }
 
 impl EmitterState {
     /// Once the emitter has generated the struct definitions,
     /// we can call this method to generate the module declarations
     /// from root.
-    pub(crate) fn declare_modules(&self) -> Result<(), Error> {
+    pub(crate) fn declare_modules(&self) -> PaperClipResult<()> {
         info!("Writing module declarations.");
-        let mods = self.mod_children.borrow();
+        let mut mods = self.mod_children.write();
         for (rel_parent, children) in &*mods {
-            let mut mod_path = self.working_dir.join(&rel_parent);
+            let mut mod_path = self.working_dir.join(rel_parent);
             mod_path.push("mod.rs");
 
             let mut contents = String::new();
@@ -48,46 +207,134 @@ impl EmitterState {
             self.write_contents(&contents, &mod_path)?;
         }
 
-        if let Some(p) = mods.keys().next() {
-            let mut some_path = PathBuf::from(p);
-            loop {
-                match some_path.parent() {
-                    Some(p) if p.parent().is_some() => some_path = p.into(),
-                    _ => break,
-                }
+        if self.crate_meta.is_some() {
+            return self.write_lib_module();
+        }
+
+        // Helpers go to the root module, which is either given or the only
+        // top-level module (in the working directory). Definitions without
+        // namespaces are modules in the working directory, in which case
+        // the working directory itself is the root module.
+        let root_defs = self.root_level_modules();
+        let top_mods = mods
+            .keys()
+            .filter(|p| p.components().count() == 1)
+            .map(|p| p.to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        let root = match self.root_module.as_ref() {
+            Some(m) if top_mods.contains(m) => Some(m.clone()),
+            Some(m) => return Err(PaperClipError::MissingRootModule(m.clone())),
+            None if !root_defs.is_empty() => None,
+            None if top_mods.len() > 1 => {
+                return Err(PaperClipError::MultipleRootModules(top_mods));
             }
+            None => top_mods.first().cloned(),
+        };
 
-            self.root_module
-                .borrow_mut()
-                .replace(some_path.to_string_lossy().into_owned());
+        if root.is_none() || !root_defs.is_empty() {
+            let children = root_defs
+                .into_iter()
+                .chain(top_mods)
+                .collect::<BTreeSet<_>>();
+            let mut contents = String::new();
+            for child in &children {
+                contents.push_str("pub mod ");
+                contents.push_str(child);
+                contents.push_str(";\n");
+            }
+
+            self.write_contents(&contents, &self.working_dir.join("mod.rs"))?;
+            mods.insert(PathBuf::new(), children);
         }
 
+        *self.helper_module.write() = root;
         Ok(())
     }
 
+    /// Returns the names of the modules of definitions without namespaces
+    /// (i.e., leaf modules in the working directory).
+    fn root_level_modules(&self) -> BTreeSet<String> {
+        let def_mods = self.def_mods.read();
+        let alias_mods = self.alias_mods.read();
+        let enum_mods = self.enum_mods.read();
+        def_mods
+            .keys()
+            .chain(alias_mods.keys())
+            .chain(enum_mods.keys())
+            .filter(|p| p.parent() == Some(&self.working_dir))
+            .filter_map(|p| p.file_stem())
+            .map(|n| n.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    /// Returns the (relative) paths of the modules declared so far, along with
+    /// the names of their children.
+    pub(crate) fn module_children(&self) -> BTreeMap<PathBuf, BTreeSet<String>> {
+        self.mod_children.read().clone()
+    }
+
+    /// Writes `lib.rs` (with the required `extern` declarations and the
+    /// root modules) for the generated crate.
+    fn write_lib_module(&self) -> PaperClipResult<()> {
+        let def_mods = self.def_mods.read();
+        let alias_mods = self.alias_mods.read();
+        let enum_mods = self.enum_mods.read();
+        let root_mods = def_mods
+            .keys()
+            .chain(alias_mods.keys())
+            .chain(enum_mods.keys())
+            .filter_map(|p| p.strip_prefix(&self.working_dir).ok())
+            .filter_map(|p| p.components().next())
+            .map(|c| {
+                let name = c.as_os_str().to_string_lossy();
+                name.trim_end_matches(".rs").to_owned()
+            })
+            .collect::<BTreeSet<_>>();
+
+        let mut contents = String::from("#[macro_use]\nextern crate serde_derive;\n\n");
+        for module in root_mods {
+            contents.push_str("pub mod ");
+            contents.push_str(&module);
+            contents.push_str(";\n");
+        }
+
+        self.write_contents(&contents, &self.working_dir.join("lib.rs"))
+    }
+
     /// Once the emitter has generated the struct definitions,
     /// we can call this method to write the definitions to leaf modules.
-    pub(crate) fn write_definitions(&self) -> Result<(), Error> {
-        let def_mods = self.def_mods.borrow();
+    pub(crate) fn write_definitions(&self) -> PaperClipResult<()> {
+        let def_mods = self.def_mods.read();
         info!("Writing definitions.");
         for (mod_path, object) in &*def_mods {
             let contents = object.to_string();
             self.write_contents(&contents, mod_path)?;
         }
 
+        let alias_mods = self.alias_mods.read();
+        for (mod_path, alias) in &*alias_mods {
+            self.write_contents(&alias.to_string(), mod_path)?;
+        }
+
+        let enum_mods = self.enum_mods.read();
+        for (mod_path, enum_) in &*enum_mods {
+            self.write_contents(&enum_.to_string(), mod_path)?;
+        }
+
         Ok(())
     }
 
     /// Once the emitter has collected requirements for paths,
     /// we can use this method to add builder structs and their impls.
-    pub(crate) fn add_builders(&self) -> Result<(), Error> {
+    pub(crate) fn add_builders(&self) -> PaperClipResult<()> {
         info!("Adding builders to definitions.");
-        let mut unit_types = self.unit_types.borrow_mut();
-        let def_mods = self.def_mods.borrow();
+        let helper_module = self.helper_module_prefix();
+        let mut unit_types = self.unit_types.write();
+        let def_mods = self.def_mods.read();
         for (mod_path, object) in &*def_mods {
             let mut contents = String::from("\n");
-            let _ = write!(contents, "{}", object.impl_repr());
-            for builder in object.builders() {
+            let _ = write!(contents, "{}", object.impl_repr(&helper_module));
+            for builder in object.builders(&helper_module) {
                 builder
                     .struct_fields_iter()
                     .filter(|(_, _, prop)| prop.is_required())
@@ -106,54 +353,191 @@ impl EmitterState {
     }
 
     /// Once the builders have been added, we can add unit types
-    /// and other dependencies.
-    pub(crate) fn add_deps(&self) -> Result<(), Error> {
-        let module = match &*self.root_module.borrow() {
+    /// and other dependencies. If the base URL of the API is known, then
+    /// it's used by the default client.
+    pub(crate) fn add_deps(&self, base_url: Option<&str>) -> PaperClipResult<()> {
+        let module = match &*self.helper_module.read() {
+            _ if self.crate_meta.is_some() => self.working_dir.join("lib.rs"),
             Some(p) => self.working_dir.join(p).join("mod.rs"),
-            None => {
-                error!("No root module to generate deps.");
-                return Ok(());
-            }
+            None => self.working_dir.join("mod.rs"),
         };
 
-        let types = self.unit_types.borrow();
+        let types = self.unit_types.read();
         let mut content = String::new();
         content.push_str("\npub mod prelude {\n");
 
         for ty in &*types {
+            content.push_str("    #[derive(Debug, Clone)]\n");
             content.push_str("    pub struct Missing");
             content.push_str(ty);
             content.push_str(";\n");
-            content.push_str("    pub struct ");
-            content.push_str(ty);
-            content.push_str("Optional;\n");
+            content.push_str("    #[derive(Debug, Clone)]\n");
             content.push_str("    pub struct ");
             content.push_str(ty);
             content.push_str("Exists;\n");
         }
 
+        content.push_str("}\n");
+        content.push_str(CLIENT_MODULE);
+        if let Some(url) = base_url {
+            content.push_str(&DEFAULT_CLIENT.replace("{base_url:?}", &format!("{:?}", url)));
+        }
+
         content.push_str("}\n");
         self.append_contents(&content, &module)
     }
 
-    /// Writes the given contents to a file at the given path (truncating the file if it exists).
-    fn write_contents(&self, contents: &str, path: &Path) -> Result<(), Error> {
-        let mut fd = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .open(path)?;
-        fd.write_all(contents.as_bytes())?;
-        Ok(())
+    /// Returns the path prefix (ending with `::`) of the working directory
+    /// from crate root.
+    pub(super) fn mod_prefix(&self) -> &str {
+        if self.crate_meta.is_some() {
+            "crate::"
+        } else {
+            &self.mod_prefix
+        }
     }
 
-    /// Appends the given contents to an existing file at the given path.
+    /// Returns the path prefix (ending with `::`) of the root module, which
+    /// holds the `prelude` and `client` modules used by the builders.
     ///
-    /// **NOTE:** This doesn't create a file if it is non-existent.
-    fn append_contents(&self, contents: &str, path: &Path) -> Result<(), Error> {
-        let mut fd = OpenOptions::new().append(true).open(path)?;
-        fd.write_all(contents.as_bytes())?;
-        Ok(())
+    /// **NOTE:** This should be called only after the modules have been declared.
+    fn helper_module_prefix(&self) -> String {
+        let mut prefix = String::from(self.mod_prefix());
+        if self.crate_meta.is_some() {
+            return prefix;
+        }
+
+        if let Some(m) = self.helper_module.read().as_ref() {
+            prefix.push_str(m);
+            prefix.push_str("::");
+        }
+
+        prefix
+    }
+
+    /// Writes the manifest and README for the generated crate (if we're generating one).
+    pub(crate) fn add_crate_files(&self, info: Option<&Info>) -> PaperClipResult<()> {
+        let meta = match self.crate_meta.as_ref() {
+            Some(m) => m,
+            None => return Ok(()),
+        };
+
+        let name = match meta.name.as_ref() {
+            Some(n) => n.clone(),
+            // The working directory may not exist if we're not writing to disk.
+            None => fs::canonicalize(&self.working_dir)
+                .unwrap_or_else(|_| self.working_dir.clone())
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| String::from("api")),
+        };
+
+        info!("Writing manifest and README for {}.", name);
+        let authors = meta
+            .authors
+            .iter()
+            .map(|a| format!("{:?}", a))
+            .collect::<Vec<_>>()
+            .join(", ");
+        // Catch-all variants in string enums require untagged variants.
+        let serde_version = if self.enum_other_variant {
+            "1.0.181"
+        } else {
+            "1.0"
+        };
+        let manifest = MANIFEST_TEMPLATE
+            .replace("{name}", &name)
+            .replace(
+                "{version}",
+                meta.version.as_ref().map_or("0.1.0", String::as_str),
+            )
+            .replace("{authors}", &authors)
+            .replace("{serde_version}", serde_version);
+        self.write_contents(&manifest, &self.working_dir.join("Cargo.toml"))?;
+
+        let mut readme = format!(
+            "# {}\n\nAPI client generated by [paperclip](https://github.com/wafflespeanut/paperclip).\n",
+            name
+        );
+        if let Some(info) = info {
+            let _ = write!(readme, "\n## {} ({})\n", info.title, info.version);
+            if let Some(desc) = info.description.as_ref() {
+                let _ = write!(readme, "\n{}\n", desc.trim());
+            }
+        }
+
+        let ops = self.operations();
+        if !ops.is_empty() {
+            readme.push_str("\n## Operations\n\n| Method | Path | Builder |\n|---|---|---|\n");
+            for (path, method, builder) in ops {
+                let _ = writeln!(
+                    readme,
+                    "| `{}` | `{}` | `{}()` |",
+                    format!("{:?}", method).to_uppercase(),
+                    path,
+                    builder
+                );
+            }
+        }
+
+        self.write_contents(&readme, &self.working_dir.join("README.md"))
+    }
+
+    /// Returns the (sorted) paths, methods and builder constructors
+    /// (with their paths from crate root) of all operations.
+    fn operations(&self) -> BTreeSet<(String, HttpMethod, String)> {
+        let def_mods = self.def_mods.read();
+        def_mods
+            .values()
+            .flat_map(|object| {
+                object.builders("").filter_map(move |builder| {
+                    let (method, path) = builder.operation()?;
+                    let constructor = format!(
+                        "{}::{}::{}",
+                        object.path,
+                        object.name,
+                        builder.constructor_name()
+                    );
+                    Some((path.to_owned(), method, constructor))
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the (sorted) paths of all modules written so far.
+    pub(super) fn written_modules(&self) -> Vec<PathBuf> {
+        self.written_mods.read().iter().cloned().collect()
+    }
+
+    /// Formats the module at the given path (if formatting has been enabled).
+    pub(super) fn format_module(&self, path: &Path) -> PaperClipResult<()> {
+        let options = match self.format.as_ref() {
+            Some(o) => o,
+            None => return Ok(()),
+        };
+
+        debug!("Formatting {:?}", path);
+        let contents = options.format(&self.sink.read(path)?)?;
+        self.sink.write(path, &contents)
+    }
+
+    /// Writes the given contents to a file at the given path (truncating the file if it exists).
+    fn write_contents(&self, contents: &str, path: &Path) -> PaperClipResult<()> {
+        self.track_module(path);
+        self.sink.write(path, contents)
+    }
+
+    /// Appends the given contents to the file at the given path.
+    fn append_contents(&self, contents: &str, path: &Path) -> PaperClipResult<()> {
+        self.track_module(path);
+        self.sink.append(path, contents)
+    }
+
+    /// Keeps track of the given path if it's a module.
+    fn track_module(&self, path: &Path) {
+        if path.extension().map_or(false, |e| e == "rs") {
+            self.written_mods.write().insert(path.into());
+        }
     }
 }
 
@@ -161,11 +545,24 @@ impl Default for EmitterState {
     fn default() -> EmitterState {
         EmitterState {
             working_dir: PathBuf::from("."),
-            ns_sep: ".",
-            def_mods: Rc::new(RefCell::new(HashMap::new())),
-            mod_children: Rc::new(RefCell::new(HashMap::new())),
-            unit_types: Rc::new(RefCell::new(HashSet::new())),
-            root_module: Rc::new(RefCell::new(None)),
+            ns_sep: ".".into(),
+            mod_prefix: "crate::".into(),
+            enum_other_variant: false,
+            crate_meta: None,
+            root_module: None,
+            sink: Arc::new(FileSystem),
+            format: if cfg!(feature = "codegen-fmt") {
+                Some(FormatOptions::default())
+            } else {
+                None
+            },
+            def_mods: Arc::new(RwLock::new(BTreeMap::new())),
+            alias_mods: Arc::new(RwLock::new(BTreeMap::new())),
+            enum_mods: Arc::new(RwLock::new(BTreeMap::new())),
+            mod_children: Arc::new(RwLock::new(BTreeMap::new())),
+            unit_types: Arc::new(RwLock::new(BTreeSet::new())),
+            helper_module: Arc::new(RwLock::new(None)),
+            written_mods: Arc::new(RwLock::new(BTreeSet::new())),
         }
     }
 }
@@ -1,7 +1,9 @@
 //! Interior mutability stuff.
 
+use super::Schema;
 use parking_lot::RwLock;
-use serde::{Deserialize, Deserializer};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use std::cell::RefCell;
 use std::ops::{Deref, DerefMut};
@@ -81,3 +83,32 @@ where
         Ok(ArcRwLock(Arc::new(RwLock::new(value))))
     }
 }
+
+/// Schemas which have been resolved to some definition are serialized as
+/// references (`$ref`) to that definition, whereas others are serialized
+/// in their entirety.
+///
+/// **NOTE:** References always point to `#/definitions/`, because the original
+/// `$ref` is gone after resolution. This means that schemas from OpenAPI v3
+/// specs (whose references point to `#/components/schemas/`) should be
+/// converted into v2 (along with the spec) before serializing them.
+impl<S> Serialize for ArcRwLock<S>
+where
+    S: Schema + Serialize,
+{
+    fn serialize<T>(&self, serializer: T) -> Result<T::Ok, T::Error>
+    where
+        T: Serializer,
+    {
+        let schema = self.read();
+        match schema.name() {
+            Some(name) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                let reference = String::from("#/definitions/") + &super::loader::escape_token(name);
+                map.serialize_entry("$ref", &reference)?;
+                map.end()
+            }
+            None => schema.serialize(serializer),
+        }
+    }
+}
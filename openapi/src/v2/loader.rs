@@ -0,0 +1,339 @@
+//! Loading documents and resolving references between them.
+//!
+//! Before a spec is deserialized, all `$ref` URIs in its document are
+//! visited (as raw values):
+//!
+//! - References to definitions in other documents (say, `common.yaml#/definitions/Error`)
+//!   are resolved by copying those definitions into the root `definitions` and pointing the
+//!   references to the copies (`#/definitions/Error`).
+//! - Other references (say, `#/parameters/Foo`, `#/responses/Bar` or nested pointers like
+//!   `#/definitions/Foo/properties/bar`) are replaced with the values they point to.
+//!
+//! References to definitions in the root document are left as they are, since the
+//! resolver takes care of them.
+//!
+//! The same applies to v3 specs, except that the definitions live in `components/schemas`.
+
+use crate::error::{PaperClipError, PaperClipResult};
+use serde_json::Value;
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+
+/// Path to the definitions in v2 specs.
+pub(crate) const V2_DEFINITIONS: &[&str] = &["definitions"];
+/// Path to the definitions (schemas) in v3 specs.
+#[cfg(feature = "v3")]
+pub(crate) const V3_DEFINITIONS: &[&str] = &["components", "schemas"];
+
+/// Loads documents referred by `$ref` URIs.
+pub trait RefLoader {
+    /// Loads the document at the given path. The path is relative to the
+    /// root document, uses `/` as the separator and doesn't have `.` or `..`
+    /// components.
+    fn load(&self, path: &str) -> PaperClipResult<Value>;
+}
+
+/// Loader which doesn't load anything. This is used when we're not expecting
+/// references to other documents.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoLoader;
+
+impl RefLoader for NoLoader {
+    fn load(&self, path: &str) -> PaperClipResult<Value> {
+        Err(PaperClipError::MissingDocument(path.into()))
+    }
+}
+
+/// Loader which loads JSON or YAML documents from a local directory
+/// (usually the parent directory of the root document). The format of a
+/// document is chosen based on its extension (or detected from its contents
+/// if the extension is unknown).
+#[derive(Debug, Clone)]
+pub struct DirLoader {
+    root: PathBuf,
+}
+
+impl DirLoader {
+    /// Creates a loader for the given directory.
+    pub fn new<P>(root: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        DirLoader { root: root.into() }
+    }
+}
+
+impl RefLoader for DirLoader {
+    fn load(&self, path: &str) -> PaperClipResult<Value> {
+        let mut full_path = self.root.clone();
+        full_path.extend(path.split('/'));
+        if !full_path.is_file() {
+            return Err(PaperClipError::MissingDocument(path.into()));
+        }
+
+        crate::value_from_path(&full_path)
+    }
+}
+
+/// In-memory documents (mapped from their paths).
+impl RefLoader for BTreeMap<String, Value> {
+    fn load(&self, path: &str) -> PaperClipResult<Value> {
+        self.get(path)
+            .cloned()
+            .ok_or_else(|| PaperClipError::MissingDocument(path.into()))
+    }
+}
+
+/// Visits the given root document and resolves references to other documents
+/// (using the given loader) and non-definition references (see module-level docs).
+/// Definitions are expected at the given path (say, `definitions` in v2).
+pub(crate) fn resolve_references<L>(
+    root: &mut Value,
+    loader: &L,
+    defs_path: &'static [&'static str],
+) -> PaperClipResult<()>
+where
+    L: RefLoader,
+{
+    let mut walker = RefWalker {
+        loader,
+        defs_path,
+        docs: HashMap::new(),
+        root_defs: defs_path
+            .iter()
+            .try_fold(&*root, |v, key| v.get(key))
+            .and_then(Value::as_object)
+            .map(|m| m.keys().cloned().collect())
+            .unwrap_or_default(),
+        ext_defs: HashMap::new(),
+        new_defs: BTreeMap::new(),
+        inlining: vec![],
+    };
+
+    // We need the root document for resolving pointers, but we also
+    // need to modify it, so we keep a copy.
+    walker.docs.insert(String::new(), root.clone());
+    walker.walk(root, "")?;
+
+    if walker.new_defs.is_empty() {
+        return Ok(());
+    }
+
+    let mut defs = root;
+    for key in defs_path {
+        defs = defs
+            .as_object_mut()
+            .ok_or_else(|| PaperClipError::InvalidRefURI("#".into()))?
+            .entry(*key)
+            .or_insert_with(|| Value::Object(Default::default()));
+    }
+
+    if let Some(defs) = defs.as_object_mut() {
+        defs.extend(walker.new_defs);
+    }
+
+    Ok(())
+}
+
+/// Walks through documents and resolves the references.
+struct RefWalker<'a, L> {
+    loader: &'a L,
+    /// Path to the definitions in documents.
+    defs_path: &'static [&'static str],
+    /// Documents loaded so far (root document has an empty path).
+    docs: HashMap<String, Value>,
+    /// Names of definitions in the root document (and the ones we've added).
+    root_defs: HashSet<String>,
+    /// Definitions from other documents (path and name) and the names
+    /// they've been assigned in the root document.
+    ext_defs: HashMap<(String, String), String>,
+    /// Definitions from other documents which should be added to the root document.
+    new_defs: BTreeMap<String, Value>,
+    /// Stack of references being inlined (to detect cycles).
+    inlining: Vec<String>,
+}
+
+impl<'a, L> RefWalker<'a, L>
+where
+    L: RefLoader,
+{
+    /// Walks through the given value (which exists in the document at the given path)
+    /// and resolves the references.
+    fn walk(&mut self, value: &mut Value, doc: &str) -> PaperClipResult<()> {
+        let reference = match value {
+            Value::Array(values) => {
+                return values.iter_mut().try_for_each(|v| self.walk(v, doc));
+            }
+            Value::Object(map) => match map.get("$ref") {
+                Some(Value::String(r)) => r.clone(),
+                _ => return map.values_mut().try_for_each(|v| self.walk(v, doc)),
+            },
+            _ => return Ok(()),
+        };
+
+        let (target_doc, pointer) = split_reference(&reference, doc)?;
+        let tokens = pointer_tokens(&reference, &pointer)?;
+        let num_keys = self.defs_path.len();
+        if tokens.len() == num_keys + 1 && tokens[..num_keys] == *self.defs_path {
+            if target_doc.is_empty() {
+                return Ok(());
+            }
+
+            let name = self.assign_definition(&reference, &target_doc, &tokens[num_keys])?;
+            if let Value::Object(map) = value {
+                let mut new_ref = String::from("#");
+                for key in self.defs_path {
+                    new_ref.push('/');
+                    new_ref.push_str(key);
+                }
+
+                new_ref.push('/');
+                new_ref.push_str(&escape_token(&name));
+                map.insert("$ref".into(), Value::String(new_ref));
+            }
+
+            return Ok(());
+        }
+
+        // Not a definition - inline it.
+        let key = format!("{}#{}", target_doc, pointer);
+        if self.inlining.contains(&key) {
+            return Err(PaperClipError::ReferenceCycle(reference));
+        }
+
+        let mut target = self.lookup(&reference, &target_doc, &tokens)?;
+        self.inlining.push(key);
+        self.walk(&mut target, &target_doc)?;
+        self.inlining.pop();
+        *value = target;
+        Ok(())
+    }
+
+    /// Assigns a name (in the root document) for the given definition from
+    /// another document and walks through it (if it's new).
+    fn assign_definition(
+        &mut self,
+        reference: &str,
+        doc: &str,
+        name: &str,
+    ) -> PaperClipResult<String> {
+        let key = (doc.to_owned(), name.to_owned());
+        if let Some(n) = self.ext_defs.get(&key) {
+            return Ok(n.clone());
+        }
+
+        // Prefer the actual name. If it's taken, then prefix its last component
+        // with the document's file stem (and suffix a number if that's taken too),
+        // so that it stays in the same namespace (say, `pet.models_Error`).
+        let stem = doc
+            .rsplit('/')
+            .next()
+            .and_then(|f| f.split('.').next())
+            .unwrap_or(doc);
+        let split = name
+            .rfind(|c: char| c.is_ascii_punctuation() && c != '_' && c != '-')
+            .map_or(0, |i| i + 1);
+        let mut new_name = name.to_owned();
+        let mut idx = 0;
+        while self.root_defs.contains(&new_name) {
+            idx += 1;
+            new_name = format!("{}{}_{}", &name[..split], stem, &name[split..]);
+            if idx > 1 {
+                new_name.push_str(&idx.to_string());
+            }
+        }
+
+        self.root_defs.insert(new_name.clone());
+        self.ext_defs.insert(key, new_name.clone());
+
+        let mut tokens = self
+            .defs_path
+            .iter()
+            .map(|&k| String::from(k))
+            .collect::<Vec<_>>();
+        tokens.push(name.to_owned());
+        let mut def = self.lookup(reference, doc, &tokens)?;
+        self.walk(&mut def, doc)?;
+        self.new_defs.insert(new_name.clone(), def);
+        Ok(new_name)
+    }
+
+    /// Returns (a copy of) the value in the given document at the given pointer.
+    fn lookup(&mut self, reference: &str, doc: &str, tokens: &[String]) -> PaperClipResult<Value> {
+        if !self.docs.contains_key(doc) {
+            debug!("Loading document: {}", doc);
+            let value = self.loader.load(doc)?;
+            self.docs.insert(doc.to_owned(), value);
+        }
+
+        let mut value = &self.docs[doc];
+        for token in tokens {
+            value = match value {
+                Value::Object(m) => m.get(token),
+                Value::Array(v) => token.parse::<usize>().ok().and_then(|i| v.get(i)),
+                _ => None,
+            }
+            .ok_or_else(|| PaperClipError::UnresolvedReference(reference.into()))?;
+        }
+
+        Ok(value.clone())
+    }
+}
+
+/// Splits the given reference into the path of the document (relative to the
+/// root document) and the JSON pointer.
+fn split_reference(reference: &str, current_doc: &str) -> PaperClipResult<(String, String)> {
+    let (path, pointer) = match reference.find('#') {
+        Some(i) => (&reference[..i], &reference[i + 1..]),
+        None => (reference, ""),
+    };
+
+    if path.is_empty() {
+        return Ok((current_doc.into(), pointer.into()));
+    }
+
+    // We only support relative paths.
+    if path.contains("://") || path.starts_with('/') {
+        return Err(PaperClipError::InvalidRefURI(reference.into()));
+    }
+
+    let mut components = current_doc.split('/').collect::<Vec<_>>();
+    components.pop(); // current document's file name
+    for c in path.split('/') {
+        match c {
+            "" | "." => (),
+            ".." => {
+                components
+                    .pop()
+                    .ok_or_else(|| PaperClipError::InvalidRefURI(reference.into()))?;
+            }
+            c => components.push(c),
+        }
+    }
+
+    Ok((components.join("/"), pointer.into()))
+}
+
+/// Returns the (unescaped) tokens in the given JSON pointer.
+fn pointer_tokens(reference: &str, pointer: &str) -> PaperClipResult<Vec<String>> {
+    if pointer.is_empty() {
+        return Ok(vec![]);
+    }
+
+    if !pointer.starts_with('/') {
+        return Err(PaperClipError::InvalidRefURI(reference.into()));
+    }
+
+    Ok(pointer[1..].split('/').map(unescape_token).collect())
+}
+
+/// Unescapes the given JSON pointer token (`~1` is `/` and `~0` is `~`).
+pub(crate) fn unescape_token(token: &str) -> String {
+    token.replace("~1", "/").replace("~0", "~")
+}
+
+/// Escapes the given string for use as a JSON pointer token.
+pub(crate) fn escape_token(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
@@ -16,6 +16,32 @@
 //! assert_eq!(api.swagger, Version::V2);
 //! ```
 //!
+//! There are also `from_str`, `from_slice`, `from_json_reader` and `from_yaml_reader`
+//! for specs which are already in memory or in some known format. Readers needn't
+//! be seekable, since the whole document is buffered for detecting its format.
+//!
+//! ```rust,no_run
+//! use paperclip_openapi::v2::{self, Api, DefaultSchema};
+//!
+//! let stdin = std::io::stdin();
+//! let api: Api<DefaultSchema> = v2::from_reader(stdin.lock()).unwrap();
+//! ```
+//!
+//! If your spec refers to other documents in `$ref` fields (say,
+//! `common.yaml#/definitions/Error`), then use `from_path` (which loads them
+//! relative to the spec's directory) or a loader (see the [`loader`](loader/index.html)
+//! module).
+//!
+//! ```rust,no_run
+//! use paperclip_openapi::v2::{self, Api, DefaultSchema, loader::DirLoader};
+//!
+//! use std::fs::File;
+//!
+//! let mut fd = File::open("specs/my_spec.yaml").unwrap();
+//! let loader = DirLoader::new("specs");
+//! let api: Api<DefaultSchema> = v2::from_reader_with_loader(&mut fd, &loader).unwrap();
+//! ```
+//!
 //! On the other hand, if your schema does have custom properties which you'd
 //! like to parse, then use the `#[api_v2_schema]` proc macro.
 //!
@@ -56,6 +82,18 @@
 //! let api: Api<K8sSchema> = v2::from_reader(&mut fd).unwrap();
 //! ```
 //!
+//! Problems in the spec (duplicate operation IDs, undeclared path parameters,
+//! unreferenced definitions, etc.) can be listed with `validate` (see the
+//! [`validator`](validator/index.html) module).
+//!
+//! ```rust,no_run
+//! # use paperclip_openapi::v2::{self, Api, DefaultSchema};
+//! # let api: Api<DefaultSchema> = v2::from_reader(&mut std::io::Cursor::new(vec![])).unwrap();
+//! for diagnostic in v2::validate(&api) {
+//!     eprintln!("{}", diagnostic);
+//! }
+//! ```
+//!
 //! Next stop is to resolve this raw schema i.e., walk through the nodes,
 //! find `$ref` fields and assign references to the corresponding definitions.
 //!
@@ -66,6 +104,18 @@
 //! let resolved = api.resolve().unwrap();
 //! ```
 //!
+//! The (raw or resolved) spec can also be written back as JSON or YAML, provided
+//! that the schema also derives `Serialize`.
+//!
+//! ```rust,no_run
+//! # use paperclip_openapi::v2::{self, Api, DefaultSchema};
+//! # let api: Api<DefaultSchema> = v2::from_reader(&mut std::io::Cursor::new(vec![])).unwrap();
+//! use paperclip_openapi::SpecFormat;
+//!
+//! let mut fd = std::fs::File::create("normalized.yaml").unwrap();
+//! v2::to_writer(&mut fd, &api, SpecFormat::Yaml).unwrap();
+//! ```
+//!
 //! Now, if `codegen` feature is enabled (it is by default), we can use the
 //! emitter to emit the API into some path.
 //!
@@ -79,45 +129,273 @@
 //! let emitter = DefaultEmitter::from(state);
 //! emitter.generate(&api).unwrap(); // generate code!
 //! ```
+//!
+//! If you'd like to generate a standalone crate (with manifest, `lib.rs` and README),
+//! then set the crate metadata in the state.
+//!
+//! ```rust,no_run
+//! # use paperclip_openapi::v2::{self, Api, DefaultSchema};
+//! # let api: Api<DefaultSchema> = v2::from_reader(&mut std::io::Cursor::new(vec![])).unwrap();
+//! use paperclip_openapi::v2::{codegen::CrateMeta, DefaultEmitter, EmitterState, Emitter};
+//!
+//! let mut state = EmitterState::default();
+//! state.working_dir = "/path/to/my/crate".into();
+//! state.crate_meta = Some(CrateMeta {
+//!     name: Some("my-api".into()),
+//!     ..Default::default()
+//! });
+//! let emitter = DefaultEmitter::from(state);
+//! emitter.generate(&api).unwrap();
+//! ```
 
 #[cfg(feature = "codegen")]
 pub mod codegen;
 pub mod im;
+pub mod loader;
 pub mod models;
-mod resolver;
+pub(crate) mod resolver;
+pub mod validator;
 
 use self::im::ArcRwLock;
-use self::models::{DataType, DataTypeFormat};
+use self::loader::{escape_token, DirLoader, NoLoader, RefLoader};
+use self::models::{DataType, DataTypeFormat, EnumValue, OperationProtocol};
 use self::resolver::Resolver;
-use crate::error::PaperClipError;
-use failure::Error;
-use serde::Deserialize;
+use crate::error::{PaperClipError, PaperClipResult, ResultExt};
+use crate::source::SourceMap;
+use crate::SpecFormat;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 use std::collections::BTreeMap;
-use std::io::{Read, Seek, SeekFrom};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::sync::Arc;
 
 #[cfg(feature = "codegen")]
 pub use self::codegen::{DefaultEmitter, Emitter, EmitterState};
 pub use self::models::{Api, DefaultSchema};
+pub use self::validator::validate;
 
 /// Deserialize the schema from the given reader. Currently, this only supports
-/// JSON and YAML formats.
-pub fn from_reader<R, S>(mut reader: R) -> Result<Api<S>, PaperClipError>
+/// JSON and YAML formats (detected by peeking into the document).
+///
+/// **NOTE:** This doesn't support references to other documents. Use
+/// `from_reader_with_loader` (or `from_path`) for that.
+pub fn from_reader<R, S>(reader: R) -> PaperClipResult<Api<S>>
+where
+    R: Read,
+    for<'de> S: Deserialize<'de>,
+    S: Schema,
+{
+    from_reader_with_loader(reader, &NoLoader)
+}
+
+/// Deserialize the schema from the given reader, using the given loader
+/// for loading documents referred in `$ref` fields (see the [`loader`](loader/index.html)
+/// module for details). Currently, this only supports JSON and YAML formats.
+pub fn from_reader_with_loader<R, S, L>(reader: R, loader: &L) -> PaperClipResult<Api<S>>
+where
+    R: Read,
+    for<'de> S: Deserialize<'de>,
+    S: Schema,
+    L: RefLoader,
+{
+    from_buf_with_loader(crate::read_document(reader)?, None, loader)
+}
+
+/// Deserialize the schema from the given JSON reader.
+pub fn from_json_reader<R, S>(reader: R) -> PaperClipResult<Api<S>>
+where
+    R: Read,
+    for<'de> S: Deserialize<'de>,
+    S: Schema,
+{
+    let buf = crate::read_document(reader)?;
+    from_buf_with_loader(buf, Some(SpecFormat::Json), &NoLoader)
+}
+
+/// Deserialize the schema from the given YAML reader.
+pub fn from_yaml_reader<R, S>(reader: R) -> PaperClipResult<Api<S>>
+where
+    R: Read,
+    for<'de> S: Deserialize<'de>,
+    S: Schema,
+{
+    let buf = crate::read_document(reader)?;
+    from_buf_with_loader(buf, Some(SpecFormat::Yaml), &NoLoader)
+}
+
+/// Deserialize the schema from the given JSON or YAML string.
+pub fn from_str<S>(spec: &str) -> PaperClipResult<Api<S>>
+where
+    for<'de> S: Deserialize<'de>,
+    S: Schema,
+{
+    from_slice(spec.as_bytes())
+}
+
+/// Deserialize the schema from the given JSON or YAML bytes.
+pub fn from_slice<S>(buf: &[u8]) -> PaperClipResult<Api<S>>
 where
-    R: Read + Seek,
     for<'de> S: Deserialize<'de>,
     S: Schema,
 {
-    let mut buf = [0; 1];
-    reader.read_exact(&mut buf)?;
-    reader.seek(SeekFrom::Start(0))?;
+    from_buf_with_loader(buf.to_vec(), None, &NoLoader)
+}
 
-    if buf[0] == b'{' {
-        // FIXME: Support whitespaces
-        return Ok(serde_json::from_reader(reader)?);
+/// Deserialize the schema from the file at the given path. The format is chosen
+/// based on the file's extension (`json`, `yaml` or `yml`) and detected from its
+/// contents for other extensions. Documents referred in `$ref` fields are loaded
+/// relative to the file's parent directory (see [`DirLoader`](loader/struct.DirLoader.html)).
+pub fn from_path<P, S>(path: P) -> PaperClipResult<Api<S>>
+where
+    P: AsRef<Path>,
+    for<'de> S: Deserialize<'de>,
+    S: Schema,
+{
+    let path = path.as_ref();
+    let loader = DirLoader::new(path.parent().unwrap_or_else(|| Path::new("")));
+    from_buf_with_loader(fs::read(path)?, SpecFormat::from_path(path), &loader)
+}
+
+/// Deserialize the schema from the given document (detecting the format, if it's
+/// not given) and keep the document around (see `Api::source`). Load errors are
+/// located in the document.
+fn from_buf_with_loader<S, L>(
+    buf: Vec<u8>,
+    format: Option<SpecFormat>,
+    loader: &L,
+) -> PaperClipResult<Api<S>>
+where
+    for<'de> S: Deserialize<'de>,
+    S: Schema,
+    L: RefLoader,
+{
+    let format = format.unwrap_or_else(|| SpecFormat::detect(&buf));
+    let mut api = crate::value_from_slice(&buf, Some(format))
+        .and_then(|v| from_value_with_loader(v, loader))
+        .map_err(|e| crate::locate_load_error::<Api<S>>(e, &buf, format))?;
+    api.source = SourceMap::new(buf).map(Arc::new);
+    Ok(api)
+}
+
+/// Resolves the references to other documents in the given value (using the
+/// given loader) and deserializes the schema from it.
+pub(crate) fn from_value_with_loader<S, L>(mut value: Value, loader: &L) -> PaperClipResult<Api<S>>
+where
+    for<'de> S: Deserialize<'de>,
+    S: Schema,
+    L: RefLoader,
+{
+    loader::resolve_references(&mut value, loader, loader::V2_DEFINITIONS)?;
+    check_status_codes(&value)?;
+    check_media_types(&value)?;
+    Ok(serde_json::from_value(value)?)
+}
+
+/// Checks the keys of responses in all operations of the given (v2 or v3) spec,
+/// so that invalid status codes are reported along with their path, method
+/// and location (instead of some generic deserialization error).
+pub(crate) fn check_status_codes(value: &Value) -> PaperClipResult<()> {
+    for (path, method, op) in operations(value) {
+        let responses = match op.get("responses").and_then(Value::as_object) {
+            Some(r) => r,
+            None => continue,
+        };
+
+        if let Some(key) = responses
+            .keys()
+            .find(|k| models::StatusCode::parse(k).is_none())
+        {
+            let err =
+                PaperClipError::InvalidStatusCode(key.clone(), method.to_uppercase(), path.into());
+            return Err(err.at(&format!(
+                "{}/responses/{}",
+                operation_pointer(path, method),
+                escape_token(key)
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks the media types in `consumes` and `produces` fields (of the root
+/// object and all operations) of the given v2 spec, for the same reason.
+fn check_media_types(value: &Value) -> PaperClipResult<()> {
+    let check = |object: &Value, pointer: &str, location: &dyn Fn() -> String| {
+        for &field in &["consumes", "produces"] {
+            let media_types = match object.get(field).and_then(Value::as_array) {
+                Some(m) => m,
+                None => continue,
+            };
+
+            for (i, media) in media_types.iter().enumerate() {
+                if media.as_str().and_then(models::MediaType::parse).is_none() {
+                    let media = match media.as_str() {
+                        Some(m) => m.into(),
+                        None => media.to_string(),
+                    };
+
+                    let err = PaperClipError::InvalidMediaType(media, location());
+                    return Err(err.at(&format!("{}/{}/{}", pointer, field, i)));
+                }
+            }
+        }
+
+        Ok(())
+    };
+
+    check(value, "", &|| "the root object".into())?;
+    for (path, method, op) in operations(value) {
+        check(op, &operation_pointer(path, method), &|| {
+            format!("{} {:?}", method.to_uppercase(), path)
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Returns the JSON pointer to the operation with the given path and method.
+fn operation_pointer(path: &str, method: &str) -> String {
+    format!("/paths/{}/{}", escape_token(path), method)
+}
+
+/// Returns an iterator over the path, method and the operation object
+/// for all operations in the given (v2 or v3) spec.
+fn operations(value: &Value) -> impl Iterator<Item = (&str, &'static str, &Value)> {
+    const METHODS: &[&str] = &[
+        "get", "put", "post", "delete", "options", "head", "patch", "trace",
+    ];
+
+    value
+        .get("paths")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flat_map(|paths| paths.iter())
+        .flat_map(|(path, item)| {
+            METHODS
+                .iter()
+                .filter_map(move |&m| item.get(m).map(|op| (path.as_str(), m, op)))
+        })
+}
+
+/// Serialize the spec into the given writer in the given format.
+///
+/// Definitions are written out in full, whereas references to them from other
+/// places (even if they've been resolved) are written as `$ref` fields.
+pub fn to_writer<W, S>(writer: W, api: &Api<S>, format: SpecFormat) -> PaperClipResult<()>
+where
+    W: Write,
+    S: Schema + Serialize,
+{
+    match format {
+        SpecFormat::Json => serde_json::to_writer_pretty(writer, api)?,
+        SpecFormat::Yaml => serde_yaml::to_writer(writer, api)?,
     }
 
-    Ok(serde_yaml::from_reader(reader)?)
+    Ok(())
 }
 
 /// Interface for the [`Schema`](https://github.com/OAI/OpenAPI-Specification/blob/master/versions/2.0.md#schemaObject) object.
@@ -137,12 +415,33 @@ pub trait Schema: Sized {
     /// Data type format used by this schema, if any (`format` field).
     fn format(&self) -> Option<&DataTypeFormat>;
 
+    /// Possible values for this schema, if any (`enum` field).
+    fn enum_variants(&self) -> Option<&[EnumValue]>;
+
     /// Schema for array definitions, if any (`items` field).
     fn items(&self) -> Option<&ArcRwLock<Self>>;
 
     /// Mutable access to the `items` field, if it exists.
     fn items_mut(&mut self) -> Option<&mut ArcRwLock<Self>>;
 
+    /// Schemas composing this schema, if any (`allOf` field).
+    fn all_of(&self) -> Option<&[ArcRwLock<Self>]>;
+
+    /// Mutable access to the `allOf` field, if it exists.
+    fn all_of_mut(&mut self) -> Option<&mut [ArcRwLock<Self>]>;
+
+    /// Schemas of which exactly one should match, if any (`oneOf` field in OpenAPI v3).
+    fn one_of(&self) -> Option<&[ArcRwLock<Self>]>;
+
+    /// Schemas of which at least one should match, if any (`anyOf` field in OpenAPI v3).
+    fn any_of(&self) -> Option<&[ArcRwLock<Self>]>;
+
+    /// Schema which shouldn't match, if any (`not` field in OpenAPI v3).
+    fn not(&self) -> Option<&ArcRwLock<Self>>;
+
+    /// Whether this schema also allows `null` values (`nullable` field in OpenAPI v3).
+    fn is_nullable(&self) -> bool;
+
     /// Value schema for maps (`additional_properties` field).
     fn additional_properties(&self) -> Option<&ArcRwLock<Self>>;
 
@@ -161,6 +460,19 @@ pub trait Schema: Sized {
     /// If this is of type "object", checks whether the given property is required.
     fn is_required_property(&self, property: &str) -> bool;
 
+    /// Name of the property used for polymorphism, if any (`discriminator` field).
+    fn discriminator(&self) -> Option<&str>;
+
+    /// Names of the definitions which inherit this definition (through `allOf`),
+    /// if it has a discriminator.
+    ///
+    /// **NOTE:** This is not part of the schema object, but it's
+    /// set by the resolver using `add_subtype` for codegen.
+    fn subtypes(&self) -> &[String];
+
+    /// Adds a subtype for this definition. This is done by the resolver.
+    fn add_subtype(&mut self, name: &str);
+
     /// Set whether this definition is cyclic. This is done by the resolver.
     fn set_cyclic(&mut self, cyclic: bool);
 
@@ -178,6 +490,16 @@ pub trait Schema: Sized {
 
     /// Sets the name for this schema. This is done by the resolver.
     fn set_name(&mut self, name: &str);
+
+    /// Definition referred by this (root) definition, if it's an alias
+    /// (i.e., its schema only has a `$ref` field).
+    ///
+    /// **NOTE:** This is not part of the schema object, but it's
+    /// set by the resolver using `set_alias_target` for codegen.
+    fn alias_target(&self) -> Option<&ArcRwLock<Self>>;
+
+    /// Sets the definition referred by this alias. This is done by the resolver.
+    fn set_alias_target(&mut self, target: ArcRwLock<Self>);
 }
 
 impl<S: Schema> Api<S> {
@@ -187,13 +509,89 @@ impl<S: Schema> Api<S> {
     /// This walks recursively, collects the referenced schema objects,
     /// substitutes the referenced IDs with the pointer to schema objects
     /// and returns the resolved object or an error if it encountered one.
-    pub fn resolve(self) -> Result<Api<S>, Error> {
+    ///
+    /// Top-level `consumes`, `produces` and `schemes` are also copied to the
+    /// operations which don't specify their own.
+    ///
+    /// Errors caused by some node in the spec have its location (see
+    /// `PaperClipError::location`).
+    pub fn resolve(self) -> PaperClipResult<Api<S>> {
+        let source = self.source.clone();
+        self.resolve_all().map_err(|e| match source {
+            Some(s) => s.locate(e),
+            None => e,
+        })
+    }
+
+    /// Fills the position of the node (from the source document) which
+    /// caused the given error, if it's known.
+    #[cfg(feature = "codegen")]
+    pub(crate) fn locate(&self, err: PaperClipError) -> PaperClipError {
+        match self.source.as_ref() {
+            Some(s) => s.locate(err),
+            None => err,
+        }
+    }
+
+    /// Resolves all the references in this spec.
+    fn resolve_all(mut self) -> PaperClipResult<Api<S>> {
         let mut resolver = Resolver::from((self.definitions, self.paths));
         resolver.resolve()?;
-        Ok(Api {
-            swagger: self.swagger,
-            definitions: resolver.defs,
-            paths: resolver.paths,
-        })
+        for (name, param) in &mut self.parameters {
+            resolver
+                .resolve_parameter(param)
+                .at(|| format!("/parameters/{}", loader::escape_token(name)))?;
+        }
+
+        for (name, response) in &mut self.responses {
+            resolver
+                .resolve_response(response)
+                .at(|| format!("/responses/{}", loader::escape_token(name)))?;
+        }
+
+        self.definitions = resolver.defs;
+        self.paths = resolver.paths;
+        self.inherit_defaults();
+        Ok(self)
+    }
+
+    /// Returns the base URL of the API (scheme, host and base path), if the host
+    /// is known. HTTPS is preferred if it's supported (or if schemes are not specified).
+    pub fn base_url(&self) -> Option<String> {
+        let host = self.host.as_ref()?;
+        let scheme = match self.schemes.first() {
+            Some(_) if self.schemes.contains(&OperationProtocol::Https) => "https",
+            Some(s) => s.as_str(),
+            None => "https",
+        };
+
+        let mut url = format!("{}://{}", scheme, host.trim_end_matches('/'));
+        if let Some(path) = self.base_path.as_ref() {
+            if !path.starts_with('/') {
+                url.push('/');
+            }
+
+            url.push_str(path.trim_end_matches('/'));
+        }
+
+        Some(url)
+    }
+
+    /// Copies the top-level defaults to the operations which don't specify their own.
+    fn inherit_defaults(&mut self) {
+        let (consumes, produces, schemes) = (&self.consumes, &self.produces, &self.schemes);
+        for op in self.paths.values_mut().flat_map(|m| m.methods.values_mut()) {
+            if op.consumes.is_none() {
+                op.consumes = Some(consumes.clone());
+            }
+
+            if op.produces.is_none() {
+                op.produces = Some(produces.clone());
+            }
+
+            if op.schemes.is_empty() {
+                op.schemes = schemes.clone();
+            }
+        }
     }
 }
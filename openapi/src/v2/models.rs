@@ -1,21 +1,27 @@
 //! Models used by OpenAPI v2.
 
 use super::im::ArcRwLock;
+use super::Schema;
 use crate as paperclip_openapi;
-use crate::error::PaperClipError; // hack for proc macro
-use failure::Error;
+use crate::error::{PaperClipError, PaperClipResult}; // hack for proc macro
+use crate::source::SourceMap;
+use serde::de;
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::sync::Arc;
 
 /// OpenAPI version.
-#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, Eq, PartialEq)]
 pub enum Version {
     #[serde(rename = "2.0")]
     V2,
 }
 
 /// Supported data types.
-#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, Eq, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum DataType {
     Integer,
@@ -28,8 +34,7 @@ pub enum DataType {
 }
 
 /// Supported data type formats.
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
-#[serde(untagged, rename_all = "lowercase")]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub enum DataTypeFormat {
     Int32,
     Int64,
@@ -38,57 +43,305 @@ pub enum DataTypeFormat {
     Byte,
     Binary,
     Date,
-    #[serde(rename = "date-time")]
     DateTime,
     Password,
     Other(String),
 }
 
+/// Possible value of some schema (`enum` field).
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(untagged)]
+pub enum EnumValue {
+    Integer(i64),
+    Number(f64),
+    Boolean(bool),
+    String(String),
+    /// Some other value (`null`, arrays or objects).
+    Other(serde_json::Value),
+}
+
 /// OpenAPI v2 spec.
-#[derive(Clone, Debug, Deserialize)]
+///
+/// https://github.com/OAI/OpenAPI-Specification/blob/master/versions/2.0.md#swaggerObject
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(bound(serialize = "S: Schema + Serialize"))]
 pub struct Api<S> {
     pub swagger: Version,
+    /// **NOTE:** This is required by the spec, but it's optional here
+    /// so that we can work with incomplete specs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub info: Option<Info>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    #[serde(rename = "basePath", skip_serializing_if = "Option::is_none")]
+    pub base_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub schemes: Vec<OperationProtocol>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub consumes: Vec<MediaType>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub produces: Vec<MediaType>,
+    #[serde(default = "BTreeMap::new", serialize_with = "serialize_definitions")]
     pub definitions: BTreeMap<String, ArcRwLock<S>>,
     pub paths: BTreeMap<String, OperationMap<S>>,
+    #[serde(default = "BTreeMap::new", skip_serializing_if = "BTreeMap::is_empty")]
+    pub parameters: BTreeMap<String, Parameter<S>>,
+    #[serde(default = "BTreeMap::new", skip_serializing_if = "BTreeMap::is_empty")]
+    pub responses: BTreeMap<String, Response<S>>,
+    #[serde(
+        rename = "securityDefinitions",
+        default,
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub security_definitions: BTreeMap<String, SecurityScheme>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub security: Vec<SecurityRequirement>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<Tag>,
+    #[serde(rename = "externalDocs", skip_serializing_if = "Option::is_none")]
+    pub external_docs: Option<ExternalDocs>,
+    /// Document from which this spec was loaded (if it was loaded from JSON
+    /// or YAML), used for locating the nodes which cause errors.
+    ///
+    /// **NOTE:** This is not part of the spec.
+    #[serde(skip)]
+    pub source: Option<Arc<SourceMap>>,
+}
+
+/// Metadata about the API.
+///
+/// https://github.com/OAI/OpenAPI-Specification/blob/master/versions/2.0.md#infoObject
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Info {
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(rename = "termsOfService", skip_serializing_if = "Option::is_none")]
+    pub terms_of_service: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contact: Option<Contact>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license: Option<License>,
+    pub version: String,
+}
+
+/// Contact information for the API.
+///
+/// https://github.com/OAI/OpenAPI-Specification/blob/master/versions/2.0.md#contactObject
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Contact {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+}
+
+/// License information for the API.
+///
+/// https://github.com/OAI/OpenAPI-Specification/blob/master/versions/2.0.md#licenseObject
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct License {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+/// Security scheme used by the API.
+///
+/// https://github.com/OAI/OpenAPI-Specification/blob/master/versions/2.0.md#securitySchemeObject
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SecurityScheme {
+    #[serde(rename = "type")]
+    pub type_: SecuritySchemeType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Name of the header or query parameter (for API keys).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Location of the API key.
+    #[serde(rename = "in", skip_serializing_if = "Option::is_none")]
+    pub in_: Option<ApiKeyIn>,
+    /// OAuth2 flow.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flow: Option<OAuthFlow>,
+    #[serde(rename = "authorizationUrl", skip_serializing_if = "Option::is_none")]
+    pub authorization_url: Option<String>,
+    #[serde(rename = "tokenUrl", skip_serializing_if = "Option::is_none")]
+    pub token_url: Option<String>,
+    /// OAuth2 scopes and their descriptions.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub scopes: BTreeMap<String, String>,
+}
+
+/// Type of a security scheme.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub enum SecuritySchemeType {
+    #[serde(rename = "basic")]
+    Basic,
+    #[serde(rename = "apiKey")]
+    ApiKey,
+    #[serde(rename = "oauth2")]
+    OAuth2,
+}
+
+/// Location of an API key.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiKeyIn {
+    Query,
+    Header,
+}
+
+/// Flow used by an OAuth2 security scheme.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum OAuthFlow {
+    Implicit,
+    Password,
+    Application,
+    AccessCode,
+}
+
+/// Security schemes (and the scopes, for OAuth2) required for an operation.
+///
+/// https://github.com/OAI/OpenAPI-Specification/blob/master/versions/2.0.md#securityRequirementObject
+pub type SecurityRequirement = BTreeMap<String, Vec<String>>;
+
+/// Tag used by operations.
+///
+/// https://github.com/OAI/OpenAPI-Specification/blob/master/versions/2.0.md#tagObject
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Tag {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(rename = "externalDocs", skip_serializing_if = "Option::is_none")]
+    pub external_docs: Option<ExternalDocs>,
+}
+
+/// Reference to external documentation.
+///
+/// https://github.com/OAI/OpenAPI-Specification/blob/master/versions/2.0.md#externalDocumentationObject
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ExternalDocs {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub url: String,
 }
 
 /// Default schema if your schema doesn't have any custom fields.
 ///
 /// https://github.com/OAI/OpenAPI-Specification/blob/master/versions/2.0.md#schemaObject
 #[api_v2_schema]
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct DefaultSchema {}
 
 /// Path item.
 ///
 /// https://github.com/OAI/OpenAPI-Specification/blob/master/versions/2.0.md#pathItemObject
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(bound(serialize = "S: Schema + Serialize"))]
 pub struct OperationMap<S> {
     #[serde(flatten)]
     pub methods: BTreeMap<HttpMethod, Operation<S>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub parameters: Option<Vec<Parameter<S>>>,
 }
 
 /// Request parameter.
 ///
 /// https://github.com/OAI/OpenAPI-Specification/blob/master/versions/2.0.md#parameterObject
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(bound(serialize = "S: Schema + Serialize"))]
 pub struct Parameter<S> {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     #[serde(rename = "in")]
     pub in_: ParameterIn,
     pub name: String,
     #[serde(default)]
     pub required: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub schema: Option<ArcRwLock<S>>,
-    #[serde(rename = "type")]
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
     pub data_type: Option<DataType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub format: Option<DataTypeFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub items: Option<ArcRwLock<S>>,
+    /// Format of array values (`csv` by default).
+    #[serde(rename = "collectionFormat", skip_serializing_if = "Option::is_none")]
+    pub collection_format: Option<CollectionFormat>,
+    #[serde(rename = "allowEmptyValue", default, skip_serializing_if = "is_false")]
+    pub allow_empty_value: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default: Option<serde_json::Value>,
+    #[serde(rename = "enum", default, skip_serializing_if = "Vec::is_empty")]
+    pub enum_: Vec<EnumValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maximum: Option<f64>,
+    #[serde(rename = "exclusiveMaximum", default, skip_serializing_if = "is_false")]
+    pub exclusive_maximum: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minimum: Option<f64>,
+    #[serde(rename = "exclusiveMinimum", default, skip_serializing_if = "is_false")]
+    pub exclusive_minimum: bool,
+    #[serde(rename = "maxLength", skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<u64>,
+    #[serde(rename = "minLength", skip_serializing_if = "Option::is_none")]
+    pub min_length: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    #[serde(rename = "maxItems", skip_serializing_if = "Option::is_none")]
+    pub max_items: Option<u64>,
+    #[serde(rename = "minItems", skip_serializing_if = "Option::is_none")]
+    pub min_items: Option<u64>,
+    #[serde(rename = "uniqueItems", default, skip_serializing_if = "is_false")]
+    pub unique_items: bool,
+    #[serde(rename = "multipleOf", skip_serializing_if = "Option::is_none")]
+    pub multiple_of: Option<f64>,
+}
+
+/// Format of array values in parameters.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CollectionFormat {
+    /// Comma separated values (`foo,bar`).
+    Csv,
+    /// Space separated values (`foo bar`).
+    Ssv,
+    /// Tab separated values (`foo\tbar`).
+    Tsv,
+    /// Pipe separated values (`foo|bar`).
+    Pipes,
+    /// Multiple parameter instances (`foo=bar&foo=baz`). This is valid only
+    /// for query and form parameters.
+    Multi,
+}
+
+impl Default for CollectionFormat {
+    fn default() -> Self {
+        CollectionFormat::Csv
+    }
+}
+
+impl CollectionFormat {
+    /// Returns the separator for delimited values (`None` for `multi`).
+    pub fn separator(self) -> Option<&'static str> {
+        match self {
+            CollectionFormat::Csv => Some(","),
+            CollectionFormat::Ssv => Some(" "),
+            CollectionFormat::Tsv => Some("\t"),
+            CollectionFormat::Pipes => Some("|"),
+            CollectionFormat::Multi => None,
+        }
+    }
 }
 
 /// The location of the parameter.
-#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, Eq, PartialEq, Ord, PartialOrd)]
 #[serde(rename_all = "camelCase")]
 pub enum ParameterIn {
     Query,
@@ -101,32 +354,98 @@ pub enum ParameterIn {
 /// An operation.
 ///
 /// https://github.com/OAI/OpenAPI-Specification/blob/master/versions/2.0.md#operationObject
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(bound(serialize = "S: Schema + Serialize"))]
 pub struct Operation<S> {
+    #[serde(rename = "operationId", skip_serializing_if = "Option::is_none")]
     pub operation_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
-    // FIXME: Switch to `mime::MediaType` (which adds serde support) once 0.4 is released.
-    #[serde(default)]
-    pub consumes: Vec<String>,
-    #[serde(default)]
-    pub produces: Vec<String>,
+    /// Media types consumed by this operation. If this is `None`, then the
+    /// global media types apply (whereas an empty list clears them).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub consumes: Option<Vec<MediaType>>,
+    /// Media types produced by this operation (overrides the global
+    /// media types, just like `consumes`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub produces: Option<Vec<MediaType>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub schemes: Vec<OperationProtocol>,
-    // FIXME: Validate using `http::status::StatusCode::from_u16`
-    pub responses: BTreeMap<String, Response<S>>,
+    pub responses: BTreeMap<StatusCode, Response<S>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub parameters: Option<Vec<Parameter<S>>>,
 }
 
 /// HTTP response.
 ///
 /// https://github.com/OAI/OpenAPI-Specification/blob/master/versions/2.0.md#responseObject
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(bound(serialize = "S: Schema + Serialize"))]
 pub struct Response<S> {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub schema: Option<ArcRwLock<S>>,
+    #[serde(default = "BTreeMap::new", skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, Header<S>>,
+    /// Example responses (mapped from their media types).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub examples: BTreeMap<String, serde_json::Value>,
+}
+
+/// Media type used in `consumes` and `produces` fields (say, `application/json`).
+///
+/// https://github.com/OAI/OpenAPI-Specification/blob/master/versions/2.0.md#mime-types
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct MediaType {
+    /// Top-level type (lowercased).
+    pub type_: String,
+    /// Subtype along with its suffix, if any (lowercased).
+    pub subtype: String,
+    /// Parameters (with lowercased names), if any.
+    pub params: Vec<(String, String)>,
+}
+
+/// Key of a response in an operation.
+///
+/// Responses are ordered from the most specific to the least specific
+/// (i.e., status codes, ranges and then the default response).
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub enum StatusCode {
+    /// Some status code (from `100` to `599`).
+    Code(u16),
+    /// Range of status codes (`1XX` to `5XX`) specified by its class
+    /// (i.e., the first digit).
+    Range(u16),
+    /// Response for the status codes which haven't been declared (`default`).
+    Default,
+}
+
+/// Header sent with a response.
+///
+/// https://github.com/OAI/OpenAPI-Specification/blob/master/versions/2.0.md#headerObject
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(bound(serialize = "S: Schema + Serialize"))]
+pub struct Header<S> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub data_type: Option<DataType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<DataTypeFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub items: Option<ArcRwLock<S>>,
+    /// Format of array values (`csv` by default).
+    #[serde(rename = "collectionFormat", skip_serializing_if = "Option::is_none")]
+    pub collection_format: Option<CollectionFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default: Option<serde_json::Value>,
+    #[serde(rename = "enum", default, skip_serializing_if = "Vec::is_empty")]
+    pub enum_: Vec<EnumValue>,
 }
 
 /// The HTTP method used for an operation.
-#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, Eq, PartialEq, Hash, Ord, PartialOrd)]
 #[serde(rename_all = "lowercase")]
 pub enum HttpMethod {
     Get,
@@ -139,7 +458,7 @@ pub enum HttpMethod {
 }
 
 /// The protocol used for an operation.
-#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, Eq, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum OperationProtocol {
     Http,
@@ -148,9 +467,326 @@ pub enum OperationProtocol {
     Wss,
 }
 
+impl HttpMethod {
+    /// Returns the (lowercase) name of this method, as used in the spec.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HttpMethod::Get => "get",
+            HttpMethod::Put => "put",
+            HttpMethod::Post => "post",
+            HttpMethod::Delete => "delete",
+            HttpMethod::Options => "options",
+            HttpMethod::Head => "head",
+            HttpMethod::Patch => "patch",
+        }
+    }
+}
+
+impl OperationProtocol {
+    /// Returns the URL scheme for this protocol.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OperationProtocol::Http => "http",
+            OperationProtocol::Https => "https",
+            OperationProtocol::Ws => "ws",
+            OperationProtocol::Wss => "wss",
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for DataTypeFormat {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let format = String::deserialize(deserializer)?;
+        Ok(match format.as_str() {
+            "int32" => DataTypeFormat::Int32,
+            "int64" => DataTypeFormat::Int64,
+            "float" => DataTypeFormat::Float,
+            "double" => DataTypeFormat::Double,
+            "byte" => DataTypeFormat::Byte,
+            "binary" => DataTypeFormat::Binary,
+            "date" => DataTypeFormat::Date,
+            "date-time" => DataTypeFormat::DateTime,
+            "password" => DataTypeFormat::Password,
+            _ => DataTypeFormat::Other(format),
+        })
+    }
+}
+
+impl Serialize for DataTypeFormat {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(match self {
+            DataTypeFormat::Int32 => "int32",
+            DataTypeFormat::Int64 => "int64",
+            DataTypeFormat::Float => "float",
+            DataTypeFormat::Double => "double",
+            DataTypeFormat::Byte => "byte",
+            DataTypeFormat::Binary => "binary",
+            DataTypeFormat::Date => "date",
+            DataTypeFormat::DateTime => "date-time",
+            DataTypeFormat::Password => "password",
+            DataTypeFormat::Other(s) => s,
+        })
+    }
+}
+
+impl MediaType {
+    /// Creates a media type with the given type and subtype (and no parameters).
+    pub fn new<T, U>(type_: T, subtype: U) -> Self
+    where
+        T: Into<String>,
+        U: Into<String>,
+    {
+        MediaType {
+            type_: type_.into().to_ascii_lowercase(),
+            subtype: subtype.into().to_ascii_lowercase(),
+            params: vec![],
+        }
+    }
+
+    /// Parses the given media type (say, `application/json; charset=utf-8`).
+    pub fn parse(media: &str) -> Option<MediaType> {
+        let mut parts = media.split(';');
+        let essence = parts.next()?.trim();
+        let mut names = essence.splitn(2, '/');
+        let (type_, subtype) = (names.next()?, names.next()?);
+        if !is_token(type_) || !is_token(subtype) {
+            return None;
+        }
+
+        let mut media = MediaType::new(type_, subtype);
+        for param in parts.map(str::trim).filter(|p| !p.is_empty()) {
+            let mut kv = param.splitn(2, '=');
+            let (name, value) = (kv.next()?.trim(), kv.next()?.trim());
+            let value = if value.len() > 1 && value.starts_with('"') && value.ends_with('"') {
+                &value[1..value.len() - 1]
+            } else if is_token(value) {
+                value
+            } else {
+                return None;
+            };
+
+            if !is_token(name) {
+                return None;
+            }
+
+            media.params.push((name.to_ascii_lowercase(), value.into()));
+        }
+
+        Some(media)
+    }
+
+    /// Returns the suffix of the subtype, if any (say, `json` in `merge-patch+json`).
+    pub fn suffix(&self) -> Option<&str> {
+        self.subtype.rfind('+').map(|i| &self.subtype[i + 1..])
+    }
+
+    /// Checks whether this is some JSON media type (`application/json` or
+    /// something with `+json` suffix).
+    pub fn is_json(&self) -> bool {
+        (self.type_ == "application" && self.subtype == "json") || self.suffix() == Some("json")
+    }
+
+    /// Checks whether this is some textual media type (`text/*` or XML).
+    pub fn is_text(&self) -> bool {
+        self.type_ == "text" || self.subtype == "xml" || self.suffix() == Some("xml")
+    }
+
+    /// Checks whether this is `application/x-www-form-urlencoded`.
+    pub fn is_form_urlencoded(&self) -> bool {
+        self.type_ == "application" && self.subtype == "x-www-form-urlencoded"
+    }
+
+    /// Checks whether this is `multipart/form-data`.
+    pub fn is_multipart_form(&self) -> bool {
+        self.type_ == "multipart" && self.subtype == "form-data"
+    }
+}
+
+impl StatusCode {
+    /// Parses the given response key (status code, range or `default`).
+    /// Ranges are also accepted in lowercase (say, `2xx`).
+    pub fn parse(key: &str) -> Option<StatusCode> {
+        if key == "default" {
+            return Some(StatusCode::Default);
+        }
+
+        let bytes = key.as_bytes();
+        if bytes.len() != 3 || !bytes[0].is_ascii_digit() {
+            return None;
+        }
+
+        let class = u16::from(bytes[0] - b'0');
+        if !(1..=5).contains(&class) {
+            return None;
+        }
+
+        if bytes[1..].eq_ignore_ascii_case(b"XX") {
+            Some(StatusCode::Range(class))
+        } else if bytes[1..].iter().all(u8::is_ascii_digit) {
+            key.parse().ok().map(StatusCode::Code)
+        } else {
+            None
+        }
+    }
+
+    /// Checks whether the given status code is covered by this key.
+    pub fn matches(self, code: u16) -> bool {
+        match self {
+            StatusCode::Code(c) => c == code,
+            StatusCode::Range(c) => code / 100 == c,
+            StatusCode::Default => true,
+        }
+    }
+
+    /// Checks whether this is a successful (2xx) status code or range.
+    pub fn is_success(self) -> bool {
+        match self {
+            StatusCode::Code(c) => c / 100 == 2,
+            StatusCode::Range(c) => c == 2,
+            StatusCode::Default => false,
+        }
+    }
+}
+
+impl Display for StatusCode {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            StatusCode::Code(c) => write!(f, "{}", c),
+            StatusCode::Range(c) => write!(f, "{}XX", c),
+            StatusCode::Default => f.write_str("default"),
+        }
+    }
+}
+
+impl Display for MediaType {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.type_, self.subtype)?;
+        for (name, value) in &self.params {
+            if is_token(value) {
+                write!(f, "; {}={}", name, value)?;
+            } else {
+                write!(f, "; {}={:?}", name, value)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<'de> Deserialize<'de> for MediaType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let media = String::deserialize(deserializer)?;
+        MediaType::parse(&media)
+            .ok_or_else(|| de::Error::custom(format!("invalid media type: {:?}", media)))
+    }
+}
+
+impl Serialize for MediaType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for StatusCode {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let key = String::deserialize(deserializer)?;
+        StatusCode::parse(&key)
+            .ok_or_else(|| de::Error::custom(format!("invalid status code: {:?}", key)))
+    }
+}
+
+impl Serialize for StatusCode {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+/// Checks whether the given string is a token (as per RFC 7230), which is
+/// what media types and their parameters are made of.
+fn is_token(s: &str) -> bool {
+    !s.is_empty()
+        && s.bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+/// Used for skipping boolean fields which are `false` (default) during serialization.
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_false(val: &bool) -> bool {
+    !*val
+}
+
+/// Serializes the definitions in their entirety. References to these
+/// definitions from elsewhere are serialized as `$ref` (see `ArcRwLock`).
+fn serialize_definitions<S, T>(
+    defs: &BTreeMap<String, ArcRwLock<S>>,
+    serializer: T,
+) -> Result<T::Ok, T::Error>
+where
+    S: Serialize,
+    T: Serializer,
+{
+    let mut map = serializer.serialize_map(Some(defs.len()))?;
+    for (name, schema) in defs {
+        map.serialize_entry(name, &*schema.read())?;
+    }
+
+    map.end()
+}
+
 impl<S> Parameter<S> {
+    /// Creates a parameter with the given name and location (with
+    /// everything else unset).
+    pub fn new<N>(name: N, in_: ParameterIn) -> Self
+    where
+        N: Into<String>,
+    {
+        Parameter {
+            description: None,
+            in_,
+            name: name.into(),
+            required: false,
+            schema: None,
+            data_type: None,
+            format: None,
+            items: None,
+            collection_format: None,
+            allow_empty_value: false,
+            default: None,
+            enum_: vec![],
+            maximum: None,
+            exclusive_maximum: false,
+            minimum: None,
+            exclusive_minimum: false,
+            max_length: None,
+            min_length: None,
+            pattern: None,
+            max_items: None,
+            min_items: None,
+            unique_items: false,
+            multiple_of: None,
+        }
+    }
+
     /// Checks if this parameter is valid.
-    pub fn check(&self, path: &str) -> Result<(), Error> {
+    pub fn check(&self, path: &str) -> PaperClipResult<()> {
         if self.in_ == ParameterIn::Body {
             if self.schema.is_none() {
                 Err(PaperClipError::MissingSchemaForBodyParameter(
@@ -163,6 +799,18 @@ impl<S> Parameter<S> {
                 self.name.clone(),
                 path.into(),
             ))?
+        } else if self.data_type == Some(DataType::Array) && self.items.is_none() {
+            Err(PaperClipError::MissingArrayItem(Some(self.name.clone())))?
+        }
+
+        match (self.collection_format, self.in_) {
+            (Some(CollectionFormat::Multi), ParameterIn::Query)
+            | (Some(CollectionFormat::Multi), ParameterIn::FormData) => (),
+            (Some(CollectionFormat::Multi), _) => Err(PaperClipError::InvalidCollectionFormat(
+                self.name.clone(),
+                path.into(),
+            ))?,
+            _ => (),
         }
 
         Ok(())
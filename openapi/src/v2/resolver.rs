@@ -1,13 +1,13 @@
 use super::im::ArcRwLock;
+use super::loader::{escape_token, unescape_token};
 use super::{
-    models::{OperationMap, Parameter},
+    models::{Operation, OperationMap, Parameter, Response},
     Schema,
 };
-use crate::error::PaperClipError;
-use failure::Error;
+use crate::error::{PaperClipError, PaperClipResult, ResultExt};
 
 use std::cell::{Cell, RefCell};
-use std::collections::{BTreeMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::mem;
 
 // FIXME: The resolver is not in its best. It "just" works atm.
@@ -24,6 +24,11 @@ pub(crate) struct Resolver<S> {
     cur_def_cyclic: Cell<bool>,
     /// Set containing cyclic definition names.
     cyclic_defs: HashSet<String>,
+    /// Names of aliases (definitions which directly refer to some other
+    /// definition) and the names of the actual definitions.
+    aliases: HashMap<String, String>,
+    /// Prefix of `$ref` URIs which point to definitions.
+    ref_prefix: &'static str,
     /// Actual definitions.
     pub defs: BTreeMap<String, ArcRwLock<S>>,
     /// Paths and the corresponding operations.
@@ -46,20 +51,32 @@ impl<S>
             cur_def: RefCell::new(None),
             cur_def_cyclic: Cell::new(false),
             cyclic_defs: HashSet::new(),
+            aliases: HashMap::new(),
+            ref_prefix: DEF_REF_PREFIX,
             defs,
             paths,
         }
     }
 }
 
+#[cfg(feature = "v3")]
+impl<S> Resolver<S> {
+    /// Sets the prefix of `$ref` URIs which point to definitions
+    /// (`#/definitions/` by default).
+    pub fn with_ref_prefix(mut self, prefix: &'static str) -> Self {
+        self.ref_prefix = prefix;
+        self
+    }
+}
+
 impl<S> Resolver<S>
 where
     S: Schema,
 {
     /// Visit definitions and resolve them!
-    pub fn resolve(&mut self) -> Result<(), Error> {
-        // FIXME: We don't support definitions that refer another definition
-        // directly from the root. Should we?
+    pub fn resolve(&mut self) -> PaperClipResult<()> {
+        self.resolve_aliases()?;
+
         for (name, schema) in &self.defs {
             trace!("Entering: {}", name);
             {
@@ -70,17 +87,20 @@ where
                 self.cur_def_cyclic.set(false);
             }
 
-            self.resolve_definitions_no_root_ref(schema)?;
+            self.in_definition(name, self.resolve_definitions_no_root_ref(schema))?;
             if self.cur_def_cyclic.get() {
                 self.cyclic_defs.insert(name.clone());
             }
         }
 
+        self.resolve_subtypes()?;
+
         let mut paths = mem::replace(&mut self.paths, BTreeMap::new());
         paths.iter_mut().try_for_each(|(path, map)| {
             trace!("Checking path: {}", path);
 
-            self.resolve_operations(map)
+            self.resolve_operations(path, map)
+                .at(|| format!("/paths/{}", escape_token(path)))
         })?;
         self.paths = paths;
 
@@ -96,20 +116,98 @@ where
         Ok(())
     }
 
+    /// Finds definitions which directly refer some other definition (type aliases)
+    /// and points them to those definitions. References to aliases are later
+    /// resolved to the actual definitions.
+    fn resolve_aliases(&mut self) -> PaperClipResult<()> {
+        let mut targets = BTreeMap::new();
+        for (name, schema) in &self.defs {
+            if let Some(r) = schema.read().reference() {
+                let target = self.def_name_from_reference(r).at(|| "/$ref".into());
+                targets.insert(name.clone(), self.in_definition(name, target)?);
+            }
+        }
+
+        for (name, target) in &targets {
+            let actual = self.in_definition(name, self.alias_target(name, target, &targets))?;
+            trace!("Definition {} is an alias of {}", name, actual);
+            self.defs[name]
+                .write()
+                .set_alias_target(self.defs[target].clone());
+            self.aliases.insert(name.clone(), actual);
+        }
+
+        Ok(())
+    }
+
+    /// Follows the aliases (starting from the given alias and its target)
+    /// until we reach the actual definition and returns its name.
+    fn alias_target(
+        &self,
+        name: &String,
+        target: &String,
+        targets: &BTreeMap<String, String>,
+    ) -> PaperClipResult<String> {
+        let mut seen = HashSet::new();
+        seen.insert(name);
+        let mut actual = target;
+        while let Some(next) = targets.get(actual) {
+            if !seen.insert(actual) {
+                return Err(PaperClipError::CyclicDefinitionAlias(name.clone()).at(""));
+            }
+
+            actual = next;
+        }
+
+        for def in &[target, actual] {
+            if !self.defs.contains_key(*def) {
+                return Err(PaperClipError::MissingDefinition((*def).clone()).at("/$ref"));
+            }
+        }
+
+        Ok(actual.clone())
+    }
+
+    /// Finds the definitions which inherit (through `allOf`) definitions with
+    /// discriminators and adds them as subtypes of those definitions.
+    fn resolve_subtypes(&self) -> PaperClipResult<()> {
+        let mut subtypes = vec![];
+        for (name, schema) in &self.defs {
+            let mut bases = vec![];
+            let result = collect_bases(&*schema.read(), &mut bases, &mut vec![name.clone()]);
+            self.in_definition(name, result)?;
+            subtypes.extend(bases.into_iter().map(|b| (b, name)));
+        }
+
+        for (base, name) in subtypes {
+            trace!("Definition {} is a subtype of {}", name, base);
+            if let Some(schema) = self.defs.get(&base) {
+                schema.write().add_subtype(name);
+            }
+        }
+
+        Ok(())
+    }
+
     /// We've passed some definition. Resolve it assuming that it doesn't
     /// contain any reference.
-    // FIXME: This means we currently don't support definitions which
-    // directly refer some other definition (basically a type alias). Should we?
-    fn resolve_definitions_no_root_ref(&self, schema: &ArcRwLock<S>) -> Result<(), Error> {
+    fn resolve_definitions_no_root_ref(&self, schema: &ArcRwLock<S>) -> PaperClipResult<()> {
         let mut schema = schema.write();
-        if let Some(mut inner) = schema.items_mut().take() {
-            return self.resolve_definitions(&mut inner);
+        if let Some(members) = schema.all_of_mut() {
+            for (i, s) in members.iter_mut().enumerate() {
+                self.resolve_definitions(s).at(|| format!("/allOf/{}", i))?;
+            }
         }
 
-        if let Some(props) = schema.properties_mut().take() {
-            props
-                .values_mut()
-                .try_for_each(|s| self.resolve_definitions(s))?;
+        if let Some(inner) = schema.items_mut() {
+            return self.resolve_definitions(inner).at(|| "/items".into());
+        }
+
+        if let Some(props) = schema.properties_mut() {
+            for (name, s) in props.iter_mut() {
+                self.resolve_definitions(s)
+                    .at(|| format!("/properties/{}", escape_token(name)))?;
+            }
         }
 
         Ok(())
@@ -117,11 +215,14 @@ where
 
     /// Resolve the given definition. If it contains a reference, find and assign it,
     /// otherwise traverse further.
-    fn resolve_definitions(&self, schema: &mut ArcRwLock<S>) -> Result<(), Error> {
+    pub fn resolve_definitions(&self, schema: &mut ArcRwLock<S>) -> PaperClipResult<()> {
         let ref_def = {
             if let Some(ref_name) = schema.read().reference() {
                 trace!("Resolving {}", ref_name);
-                Some(self.resolve_definition_reference(ref_name)?)
+                Some(
+                    self.resolve_definition_reference(ref_name)
+                        .at(|| "/$ref".into())?,
+                )
             } else {
                 None
             }
@@ -136,50 +237,134 @@ where
         Ok(())
     }
 
-    /// Resolve a given operation.
-    fn resolve_operations(&self, map: &mut OperationMap<S>) -> Result<(), Error> {
-        for op in map.methods.values_mut() {
-            self.resolve_parameters(&mut op.parameters)?;
-            for response in op.responses.values_mut() {
-                if let Some(schema) = response.schema.as_mut() {
-                    self.resolve_definitions(schema)?;
-                }
-            }
+    /// Resolve the operations (and parameters) in the given path.
+    fn resolve_operations(&self, path: &str, map: &mut OperationMap<S>) -> PaperClipResult<()> {
+        for (&method, op) in map.methods.iter_mut() {
+            self.resolve_operation(op)
+                .at(|| format!("/{}", method.as_str()))
+                .context(|e| {
+                    PaperClipError::InOperation(method.as_str().to_uppercase(), path.into(), e)
+                })?;
         }
 
         self.resolve_parameters(&mut map.parameters)
+            .context(|e| PaperClipError::InPath(path.into(), e))
+    }
+
+    /// Resolve a given operation.
+    fn resolve_operation(&self, op: &mut Operation<S>) -> PaperClipResult<()> {
+        self.resolve_parameters(&mut op.parameters)?;
+        for (code, response) in op.responses.iter_mut() {
+            self.resolve_response(response)
+                .at(|| format!("/responses/{}", code))?;
+        }
+
+        Ok(())
     }
 
     /// Resolve the given bunch of parameters.
-    fn resolve_parameters(&self, params: &mut Option<Vec<Parameter<S>>>) -> Result<(), Error> {
+    fn resolve_parameters(&self, params: &mut Option<Vec<Parameter<S>>>) -> PaperClipResult<()> {
         if let Some(params) = params.as_mut() {
-            for param in params.iter_mut() {
-                if let Some(schema) = param.schema.as_mut() {
-                    self.resolve_definitions(schema)?;
-                }
+            for (i, p) in params.iter_mut().enumerate() {
+                self.resolve_parameter(p)
+                    .at(|| format!("/parameters/{}", i))?;
             }
         }
 
         Ok(())
     }
 
+    /// Resolve the schema of the given parameter (if any).
+    pub fn resolve_parameter(&self, param: &mut Parameter<S>) -> PaperClipResult<()> {
+        if let Some(schema) = param.schema.as_mut() {
+            self.resolve_definitions(schema).at(|| "/schema".into())?;
+        }
+
+        Ok(())
+    }
+
+    /// Resolve the schema of the given response (if any).
+    pub fn resolve_response(&self, response: &mut Response<S>) -> PaperClipResult<()> {
+        if let Some(schema) = response.schema.as_mut() {
+            self.resolve_definitions(schema).at(|| "/schema".into())?;
+        }
+
+        Ok(())
+    }
+
     /// Given a name (from `$ref` field), get a reference to the definition.
-    fn resolve_definition_reference(&self, name: &str) -> Result<ArcRwLock<S>, Error> {
-        if !name.starts_with(DEF_REF_PREFIX) {
-            // FIXME: Bad
-            return Err(PaperClipError::InvalidRefURI(name.into()))?;
+    fn resolve_definition_reference(&self, name: &str) -> PaperClipResult<ArcRwLock<S>> {
+        let mut name = self.def_name_from_reference(name)?;
+        if let Some(actual) = self.aliases.get(&name) {
+            name = actual.clone();
         }
 
-        let name = &name[DEF_REF_PREFIX.len()..];
         match self.cur_def.borrow().as_ref() {
-            Some(n) if n == name => self.cur_def_cyclic.set(true),
+            Some(n) if *n == name => self.cur_def_cyclic.set(true),
             _ => (),
         }
 
         let schema = self
             .defs
-            .get(name)
-            .ok_or(PaperClipError::MissingDefinition(name.into()))?;
+            .get(&name)
+            .ok_or_else(|| PaperClipError::MissingDefinition(name.clone()))?;
         Ok(schema.clone())
     }
+
+    /// Adds the location and the name of the given definition to the error
+    /// in the given result (if any).
+    fn in_definition<T>(&self, name: &str, result: PaperClipResult<T>) -> PaperClipResult<T> {
+        result
+            .at(|| self.def_pointer(name))
+            .context(|e| PaperClipError::InDefinition(name.into(), e))
+    }
+
+    /// Returns the JSON pointer to the definition with the given name.
+    fn def_pointer(&self, name: &str) -> String {
+        // `$ref` prefix without the leading `#`
+        format!("{}{}", &self.ref_prefix[1..], escape_token(name))
+    }
+
+    /// Returns the name of the definition in the given `$ref` URI.
+    fn def_name_from_reference(&self, uri: &str) -> PaperClipResult<String> {
+        if !uri.starts_with(self.ref_prefix) {
+            // FIXME: Bad
+            Err(PaperClipError::InvalidRefURI(uri.into()))?;
+        }
+
+        Ok(unescape_token(&uri[self.ref_prefix.len()..]))
+    }
+}
+
+/// Collects the names of definitions with discriminators from the `allOf` schemas
+/// of the given schema (recursively). `visiting` has the names of definitions
+/// we're already in, so that we can bail out if some definition inherits itself.
+fn collect_bases<S: Schema>(
+    schema: &S,
+    bases: &mut Vec<String>,
+    visiting: &mut Vec<String>,
+) -> PaperClipResult<()> {
+    for member in schema.all_of().into_iter().flatten() {
+        let member = member.read();
+        let name = member.name();
+        if let Some(n) = name {
+            if visiting.iter().any(|v| v == n) {
+                return Err(PaperClipError::CyclicAllOf(n.into()));
+            }
+
+            visiting.push(n.into());
+        }
+
+        match (name, member.discriminator()) {
+            (Some(name), Some(_)) if !bases.iter().any(|b| b == name) => bases.push(name.into()),
+            _ => (),
+        }
+
+        collect_bases(&*member, bases, visiting)?;
+        if name.is_some() {
+            visiting.pop();
+        }
+    }
+
+    Ok(())
 }
@@ -0,0 +1,350 @@
+//! Validating specs.
+//!
+//! Deserialization and resolution bail out on the first error they encounter.
+//! On the other hand, [`validate`](fn.validate.html) walks through the whole spec
+//! and collects all the problems it finds (along with JSON pointers to their
+//! locations), which is useful for linting specs.
+//!
+//! ```rust,no_run
+//! use paperclip_openapi::v2::{self, Api, DefaultSchema};
+//!
+//! let api: Api<DefaultSchema> = v2::from_path("my_spec.yaml").unwrap();
+//! for diagnostic in v2::validate(&api) {
+//!     println!("{:?} {}", diagnostic.kind.severity(), diagnostic);
+//! }
+//! ```
+
+use super::im::ArcRwLock;
+use super::loader::{escape_token, unescape_token};
+use super::models::{Api, DataTypeFormat, Parameter, ParameterIn, Response};
+use super::Schema;
+
+use std::collections::BTreeSet;
+use std::fmt::{self, Display, Formatter};
+
+const DEF_REF_PREFIX: &str = "#/definitions/";
+
+/// Some problem in the spec.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostic {
+    /// JSON pointer to the offending node (say, `/paths/~1pets/get/parameters/0`).
+    pub location: String,
+    /// The actual problem.
+    pub kind: DiagnosticKind,
+}
+
+/// Kinds of problems found by the validator.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DiagnosticKind {
+    /// Some `operationId` has already been used by another operation.
+    DuplicateOperationId(String),
+    /// Parameter in the path template is not declared (as `in: path`)
+    /// for this operation.
+    UndeclaredPathParameter(String),
+    /// Path parameter is not marked as required.
+    OptionalPathParameter(String),
+    /// Operation has more than one body parameter.
+    MultipleBodyParameters,
+    /// Operation has both body and form parameters.
+    BodyWithFormData,
+    /// Parameter is invalid (see `Parameter::check`).
+    InvalidParameter(String),
+    /// Definition is not referenced anywhere in the spec.
+    UnreferencedDefinition(String),
+    /// Format is not one of those defined by the spec.
+    UnknownFormat(String),
+}
+
+/// Severity of some problem.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Severity {
+    /// The spec is still usable, but it's probably not what was intended.
+    Warning,
+    /// The spec is invalid.
+    Error,
+}
+
+impl DiagnosticKind {
+    /// Returns the severity of this problem.
+    pub fn severity(&self) -> Severity {
+        match self {
+            DiagnosticKind::UnreferencedDefinition(_) | DiagnosticKind::UnknownFormat(_) => {
+                Severity::Warning
+            }
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl Display for DiagnosticKind {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            DiagnosticKind::DuplicateOperationId(id) => {
+                write!(f, "Operation ID {:?} has already been used", id)
+            }
+            DiagnosticKind::UndeclaredPathParameter(name) => {
+                write!(f, "Path parameter {:?} has not been declared", name)
+            }
+            DiagnosticKind::OptionalPathParameter(name) => {
+                write!(f, "Path parameter {:?} must be required", name)
+            }
+            DiagnosticKind::MultipleBodyParameters => {
+                f.write_str("Operation has more than one body parameter")
+            }
+            DiagnosticKind::BodyWithFormData => {
+                f.write_str("Operation has both body and form parameters")
+            }
+            DiagnosticKind::InvalidParameter(msg) => f.write_str(msg),
+            DiagnosticKind::UnreferencedDefinition(name) => {
+                write!(f, "Definition {:?} is not referenced anywhere", name)
+            }
+            DiagnosticKind::UnknownFormat(format) => write!(f, "Unknown format {:?}", format),
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.kind)
+    }
+}
+
+/// Validates the given spec and returns all the problems found in it.
+///
+/// This is meant for raw specs (i.e., as they've been deserialized). Resolved
+/// specs can also be validated, but since the resolver replaces references to
+/// aliases with the actual definitions, aliases will be reported as unreferenced.
+pub fn validate<S: Schema>(api: &Api<S>) -> Vec<Diagnostic> {
+    let mut validator = Validator::default();
+    validator.visit_api(api);
+    validator.diagnostics
+}
+
+/// Collects problems while walking through a spec.
+#[derive(Default)]
+struct Validator {
+    /// Problems found so far.
+    diagnostics: Vec<Diagnostic>,
+    /// Names of definitions referenced so far.
+    references: BTreeSet<String>,
+}
+
+impl Validator {
+    /// Adds a problem at the given location.
+    fn report(&mut self, location: String, kind: DiagnosticKind) {
+        self.diagnostics.push(Diagnostic { location, kind });
+    }
+
+    fn visit_api<S: Schema>(&mut self, api: &Api<S>) {
+        for (name, schema) in &api.definitions {
+            let schema = schema.read();
+            if let Some(r) = schema.reference() {
+                self.add_reference(r);
+            }
+
+            let location = format!("/definitions/{}", escape_token(name));
+            self.visit_schema_fields(&*schema, &location);
+        }
+
+        for (name, param) in &api.parameters {
+            let location = format!("/parameters/{}", escape_token(name));
+            self.visit_parameter(param, &location, &location);
+        }
+
+        for (name, response) in &api.responses {
+            self.visit_response(response, &format!("/responses/{}", escape_token(name)));
+        }
+
+        let mut op_ids = BTreeSet::new();
+        for (path, map) in &api.paths {
+            let path_location = format!("/paths/{}", escape_token(path));
+            let path_params = map.parameters.as_ref().map(|p| &p[..]).unwrap_or(&[]);
+            let path_params = path_params
+                .iter()
+                .enumerate()
+                .map(|(i, p)| (p, format!("{}/parameters/{}", path_location, i)))
+                .collect::<Vec<_>>();
+            for (param, location) in &path_params {
+                self.visit_parameter(param, path, location);
+            }
+
+            for (&method, op) in &map.methods {
+                let op_location = format!("{}/{}", path_location, method.as_str());
+
+                if let Some(id) = op.operation_id.as_ref() {
+                    if !op_ids.insert(id) {
+                        self.report(
+                            format!("{}/operationId", op_location),
+                            DiagnosticKind::DuplicateOperationId(id.clone()),
+                        );
+                    }
+                }
+
+                let local_params = op.parameters.as_ref().map(|p| &p[..]).unwrap_or(&[]);
+                let mut params = local_params
+                    .iter()
+                    .enumerate()
+                    .map(|(i, p)| (p, format!("{}/parameters/{}", op_location, i)))
+                    .collect::<Vec<_>>();
+                for (param, location) in &params {
+                    self.visit_parameter(param, path, location);
+                }
+
+                // Path-level parameters apply unless they're overridden by the operation.
+                for (param, location) in &path_params {
+                    if !local_params
+                        .iter()
+                        .any(|p| p.name == param.name && p.in_ == param.in_)
+                    {
+                        params.push((param, location.clone()));
+                    }
+                }
+
+                for name in template_params(path) {
+                    if !params
+                        .iter()
+                        .any(|(p, _)| p.in_ == ParameterIn::Path && p.name == name)
+                    {
+                        self.report(
+                            op_location.clone(),
+                            DiagnosticKind::UndeclaredPathParameter(name.into()),
+                        );
+                    }
+                }
+
+                let mut bodies = params.iter().filter(|(p, _)| p.in_ == ParameterIn::Body);
+                let has_body = bodies.next().is_some();
+                for (_, location) in bodies {
+                    self.report(location.clone(), DiagnosticKind::MultipleBodyParameters);
+                }
+
+                if has_body && params.iter().any(|(p, _)| p.in_ == ParameterIn::FormData) {
+                    self.report(op_location.clone(), DiagnosticKind::BodyWithFormData);
+                }
+
+                for (code, response) in &op.responses {
+                    let location = format!("{}/responses/{}", op_location, code);
+                    self.visit_response(response, &location);
+                }
+            }
+        }
+
+        for name in api.definitions.keys() {
+            if !self.references.contains(name) {
+                self.report(
+                    format!("/definitions/{}", escape_token(name)),
+                    DiagnosticKind::UnreferencedDefinition(name.clone()),
+                );
+            }
+        }
+    }
+
+    /// Visits the given parameter, which belongs to the given path (used in
+    /// error messages).
+    fn visit_parameter<S: Schema>(&mut self, param: &Parameter<S>, path: &str, location: &str) {
+        if let Err(e) = param.check(path) {
+            self.report(
+                location.into(),
+                DiagnosticKind::InvalidParameter(e.to_string()),
+            );
+        }
+
+        if param.in_ == ParameterIn::Path && !param.required {
+            self.report(
+                location.into(),
+                DiagnosticKind::OptionalPathParameter(param.name.clone()),
+            );
+        }
+
+        self.check_format(param.format.as_ref(), location);
+        if let Some(items) = param.items.as_ref() {
+            self.visit_schema(items, &format!("{}/items", location));
+        }
+
+        if let Some(schema) = param.schema.as_ref() {
+            self.visit_schema(schema, &format!("{}/schema", location));
+        }
+    }
+
+    fn visit_response<S: Schema>(&mut self, response: &Response<S>, location: &str) {
+        if let Some(schema) = response.schema.as_ref() {
+            self.visit_schema(schema, &format!("{}/schema", location));
+        }
+
+        for (name, header) in &response.headers {
+            let location = format!("{}/headers/{}", location, escape_token(name));
+            self.check_format(header.format.as_ref(), &location);
+            if let Some(items) = header.items.as_ref() {
+                self.visit_schema(items, &format!("{}/items", location));
+            }
+        }
+    }
+
+    /// Visits some (non-root) schema. If it refers to a definition, then it's
+    /// not visited any further (definitions are visited separately).
+    fn visit_schema<S: Schema>(&mut self, schema: &ArcRwLock<S>, location: &str) {
+        let schema = schema.read();
+        if let Some(r) = schema.reference() {
+            self.add_reference(r);
+        } else if let Some(name) = schema.name() {
+            // Resolved reference.
+            self.references.insert(name.into());
+        } else {
+            self.visit_schema_fields(&*schema, location);
+        }
+    }
+
+    /// Visits the fields of the given schema.
+    fn visit_schema_fields<S: Schema>(&mut self, schema: &S, location: &str) {
+        self.check_format(schema.format(), location);
+        if let Some(target) = schema.alias_target() {
+            if let Some(name) = target.read().name() {
+                self.references.insert(name.into());
+            }
+        }
+
+        if let Some(items) = schema.items() {
+            self.visit_schema(items, &format!("{}/items", location));
+        }
+
+        if let Some(props) = schema.properties() {
+            for (name, prop) in props {
+                let location = format!("{}/properties/{}", location, escape_token(name));
+                self.visit_schema(prop, &location);
+            }
+        }
+
+        if let Some(schema) = schema.additional_properties() {
+            self.visit_schema(schema, &format!("{}/additionalProperties", location));
+        }
+
+        for (i, member) in schema.all_of().into_iter().flatten().enumerate() {
+            self.visit_schema(member, &format!("{}/allOf/{}", location, i));
+        }
+    }
+
+    /// Reports the given format (at the given parent location) if it's unknown.
+    fn check_format(&mut self, format: Option<&DataTypeFormat>, location: &str) {
+        if let Some(DataTypeFormat::Other(format)) = format {
+            self.report(
+                format!("{}/format", location),
+                DiagnosticKind::UnknownFormat(format.clone()),
+            );
+        }
+    }
+
+    /// Adds the definition in the given `$ref` URI (if any) to the references.
+    fn add_reference(&mut self, uri: &str) {
+        if uri.starts_with(DEF_REF_PREFIX) {
+            self.references
+                .insert(unescape_token(&uri[DEF_REF_PREFIX.len()..]));
+        }
+    }
+}
+
+/// Returns the names of parameters in the given path template (say, `id` in `/pets/{id}`).
+pub(crate) fn template_params(path: &str) -> impl Iterator<Item = &str> {
+    path.split('{')
+        .skip(1)
+        .filter_map(|s| s.find('}').map(|i| &s[..i]))
+}
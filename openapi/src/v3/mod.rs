@@ -0,0 +1,420 @@
+//! Types and traits related to the [OpenAPI v3 spec](https://github.com/OAI/OpenAPI-Specification/blob/master/versions/3.0.2.md).
+//!
+//! Schema objects in v3 share the structural keywords (`type`, `properties`,
+//! `items`, `$ref`, etc.) with v2, so the same `Schema` implementors
+//! (including `DefaultSchema` and structs using `#[api_v2_schema]`) can be used here.
+//! Keywords which don't exist in v2 (`oneOf`, `anyOf`, `not` and `nullable`) are
+//! parsed into the schema, and they're handled by the emitter during codegen:
+//!
+//! - Properties with `nullable: true` are treated as optional (i.e., they become
+//!   `Option` fields even if they're required). `nullable` is ignored elsewhere.
+//! - Schemas using `oneOf`, `anyOf` or `not` are rejected.
+//!
+//! ```rust,no_run
+//! use paperclip_openapi::v2::{self, DefaultEmitter, EmitterState, Emitter};
+//! use paperclip_openapi::v3::{self, Api, DefaultSchema};
+//!
+//! use std::fs::File;
+//!
+//! let mut fd = File::open("my_spec.yaml").unwrap(); // yaml or json
+//! let api: Api<DefaultSchema> = v3::from_reader(&mut fd).unwrap();
+//! let resolved = api.resolve().unwrap();
+//!
+//! // Convert the resolved spec to feed it to the emitter.
+//! let api: v2::Api<_> = resolved.into();
+//! let emitter = DefaultEmitter::from(EmitterState::default());
+//! emitter.generate(&api).unwrap();
+//! ```
+//!
+//! If the version of the spec is not known beforehand, then
+//! [`paperclip_openapi::from_reader`](../fn.from_reader.html) can be used instead.
+
+pub mod models;
+mod resolver;
+
+use self::models::{MediaType, ParameterIn, ParameterStyle, ReferenceOr};
+use self::resolver::Resolver;
+use crate::error::PaperClipResult;
+use crate::source::SourceMap;
+use crate::v2::loader::{self, NoLoader, RefLoader};
+use crate::v2::models::{CollectionFormat, DataType};
+use crate::v2::{self, Schema};
+use crate::SpecFormat;
+use serde::Deserialize;
+use serde_json::Value;
+
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::sync::Arc;
+
+pub use self::models::Api;
+pub use crate::v2::DefaultSchema;
+
+/// Deserialize the schema from the given reader. Currently, this only supports
+/// JSON and YAML formats.
+pub fn from_reader<R, S>(reader: R) -> PaperClipResult<Api<S>>
+where
+    R: Read,
+    for<'de> S: Deserialize<'de>,
+    S: Schema,
+{
+    let buf = crate::read_document(reader)?;
+    let format = SpecFormat::detect(&buf);
+    let mut api = crate::value_from_slice(&buf, Some(format))
+        .and_then(|v| from_value_with_loader(v, &NoLoader))
+        .map_err(|e| crate::locate_load_error::<Api<S>>(e, &buf, format))?;
+    api.source = SourceMap::new(buf).map(Arc::new);
+    Ok(api)
+}
+
+/// Resolves the references to other documents in the given value (using the
+/// given loader) and deserializes the spec from it.
+pub(crate) fn from_value_with_loader<S, L>(mut value: Value, loader: &L) -> PaperClipResult<Api<S>>
+where
+    for<'de> S: Deserialize<'de>,
+    S: Schema,
+    L: RefLoader,
+{
+    loader::resolve_references(&mut value, loader, loader::V3_DEFINITIONS)?;
+    v2::check_status_codes(&value)?;
+    Ok(serde_json::from_value(value)?)
+}
+
+impl<S: Schema> Api<S> {
+    /// Consumes this API schema, resolves the references and returns
+    /// the resolved schema.
+    ///
+    /// Schemas are resolved just like v2 definitions, whereas references
+    /// to other components (parameters, request bodies and responses)
+    /// are replaced with the corresponding components.
+    pub fn resolve(self) -> PaperClipResult<Api<S>> {
+        let mut resolver = Resolver::from((self.components, self.paths));
+        resolver.resolve()?;
+        Ok(Api {
+            openapi: self.openapi,
+            info: self.info,
+            servers: self.servers,
+            paths: resolver.paths,
+            components: resolver.components,
+            source: self.source,
+        })
+    }
+}
+
+/// Converts a (resolved) v3 spec into the v2 spec, so that it can be used
+/// by the emitter.
+///
+/// - Scheme, host and base path are taken from the first server.
+/// - Component schemas become definitions.
+/// - Request bodies become body parameters (preferring JSON content).
+/// - Response schemas are taken from the preferred content (and header
+///   types are taken from their schemas).
+/// - Cookie parameters, `trace` operations and unresolved references are skipped.
+impl<S: Schema> From<Api<S>> for v2::Api<S> {
+    fn from(api: Api<S>) -> Self {
+        let paths = api
+            .paths
+            .into_iter()
+            .map(|(path, item)| {
+                let methods = item
+                    .methods
+                    .into_iter()
+                    .filter_map(|(meth, op)| {
+                        let meth = convert_method(meth)?;
+                        Some((meth, convert_operation(&path, op)))
+                    })
+                    .collect();
+
+                let parameters = item.parameters.map(|p| convert_parameters(&path, p));
+                (
+                    path,
+                    v2::models::OperationMap {
+                        methods,
+                        parameters,
+                    },
+                )
+            })
+            .collect();
+
+        let (scheme, host, base_path) = api
+            .servers
+            .first()
+            .map(|s| split_server_url(&s.url))
+            .unwrap_or_default();
+
+        v2::Api {
+            swagger: v2::models::Version::V2,
+            info: api.info,
+            host,
+            base_path,
+            schemes: scheme.into_iter().collect(),
+            consumes: vec![],
+            produces: vec![],
+            definitions: api.components.schemas,
+            paths,
+            parameters: BTreeMap::new(),
+            responses: BTreeMap::new(),
+            security_definitions: BTreeMap::new(),
+            security: vec![],
+            tags: vec![],
+            external_docs: None,
+            source: api.source,
+        }
+    }
+}
+
+/// Splits the given server URL into the scheme, host and base path (if they exist).
+fn split_server_url(
+    url: &str,
+) -> (
+    Option<v2::models::OperationProtocol>,
+    Option<String>,
+    Option<String>,
+) {
+    use crate::v2::models::OperationProtocol;
+
+    let (scheme, rest) = match url.find("://") {
+        Some(i) => (Some(&url[..i]), &url[i + 3..]),
+        None => (None, url),
+    };
+
+    let has_host = scheme.is_some();
+    let scheme = scheme.and_then(|s| match s.to_ascii_lowercase().as_str() {
+        "http" => Some(OperationProtocol::Http),
+        "https" => Some(OperationProtocol::Https),
+        "ws" => Some(OperationProtocol::Ws),
+        "wss" => Some(OperationProtocol::Wss),
+        _ => None,
+    });
+
+    let (host, path) = match (has_host, rest.find('/')) {
+        (true, Some(i)) => (Some(&rest[..i]), &rest[i..]),
+        (true, None) => (Some(rest), ""),
+        (false, _) => (None, rest),
+    };
+
+    let path = path.trim_end_matches('/');
+    (
+        scheme,
+        host.filter(|h| !h.is_empty()).map(String::from),
+        Some(path).filter(|p| !p.is_empty()).map(String::from),
+    )
+}
+
+/// Returns the v2 equivalent of the given method (if any).
+fn convert_method(meth: models::HttpMethod) -> Option<v2::models::HttpMethod> {
+    use self::models::HttpMethod as V3;
+    use crate::v2::models::HttpMethod as V2;
+
+    Some(match meth {
+        V3::Get => V2::Get,
+        V3::Put => V2::Put,
+        V3::Post => V2::Post,
+        V3::Delete => V2::Delete,
+        V3::Options => V2::Options,
+        V3::Head => V2::Head,
+        V3::Patch => V2::Patch,
+        V3::Trace => return None,
+    })
+}
+
+/// Converts a v3 operation into a v2 operation.
+fn convert_operation<S: Schema>(path: &str, op: models::Operation<S>) -> v2::models::Operation<S> {
+    let mut parameters = op
+        .parameters
+        .map(|p| convert_parameters(path, p))
+        .unwrap_or_default();
+    let mut consumes = vec![];
+    match op.request_body {
+        Some(ReferenceOr::Item(body)) => {
+            consumes.extend(
+                body.content
+                    .keys()
+                    .filter_map(|m| parse_media_type(path, m)),
+            );
+            if let Some((_, media)) = MediaType::preferred(&body.content) {
+                let mut param = v2::models::Parameter::new("body", v2::models::ParameterIn::Body);
+                param.description = body.description.clone();
+                param.required = body.required;
+                param.schema = media.schema.clone();
+                parameters.push(param);
+            }
+        }
+        Some(ReferenceOr::Reference { reference }) => {
+            warn!(
+                "Skipping unresolved request body in {:?}: {}",
+                path, reference
+            )
+        }
+        None => (),
+    }
+
+    let mut produces = vec![];
+    let mut responses = BTreeMap::new();
+    for (code, response) in op.responses {
+        let response = match response {
+            ReferenceOr::Item(r) => r,
+            ReferenceOr::Reference { reference } => {
+                warn!("Skipping unresolved response in {:?}: {}", path, reference);
+                continue;
+            }
+        };
+
+        for media in response
+            .content
+            .keys()
+            .filter_map(|m| parse_media_type(path, m))
+        {
+            if !produces.contains(&media) {
+                produces.push(media);
+            }
+        }
+
+        let schema = MediaType::preferred(&response.content).and_then(|(_, m)| m.schema.clone());
+        responses.insert(
+            code,
+            v2::models::Response {
+                description: response.description,
+                schema,
+                headers: convert_headers(path, response.headers),
+                examples: BTreeMap::new(),
+            },
+        );
+    }
+
+    v2::models::Operation {
+        operation_id: op.operation_id,
+        description: op.description.or(op.summary),
+        consumes: Some(consumes).filter(|m| !m.is_empty()),
+        produces: Some(produces).filter(|m| !m.is_empty()),
+        schemes: vec![],
+        responses,
+        parameters: if parameters.is_empty() {
+            None
+        } else {
+            Some(parameters)
+        },
+    }
+}
+
+/// Parses the given media type (from the content of some request body
+/// or response), skipping it if it's invalid.
+fn parse_media_type(path: &str, media: &str) -> Option<v2::models::MediaType> {
+    let parsed = v2::models::MediaType::parse(media);
+    if parsed.is_none() {
+        warn!("Skipping invalid media type in {:?}: {:?}", path, media);
+    }
+
+    parsed
+}
+
+/// Converts v3 parameters into v2 parameters.
+fn convert_parameters<S: Schema>(
+    path: &str,
+    params: Vec<ReferenceOr<models::Parameter<S>>>,
+) -> Vec<v2::models::Parameter<S>> {
+    params
+        .into_iter()
+        .filter_map(|param| {
+            let param = match param {
+                ReferenceOr::Item(p) => p,
+                ReferenceOr::Reference { reference } => {
+                    warn!("Skipping unresolved parameter in {:?}: {}", path, reference);
+                    return None;
+                }
+            };
+
+            let in_ = match param.in_ {
+                ParameterIn::Query => v2::models::ParameterIn::Query,
+                ParameterIn::Header => v2::models::ParameterIn::Header,
+                ParameterIn::Path => v2::models::ParameterIn::Path,
+                ParameterIn::Cookie => {
+                    warn!("Skipping cookie parameter {:?} in {:?}", param.name, path);
+                    return None;
+                }
+            };
+
+            let mut v2_param = v2::models::Parameter::new(param.name, in_);
+            v2_param.description = param.description;
+            v2_param.required = param.required;
+            if let Some(s) = param.schema.as_ref() {
+                let s = s.read();
+                v2_param.data_type = s.data_type();
+                v2_param.format = s.format().cloned();
+                v2_param.items = s.items().cloned();
+                v2_param.enum_ = s.enum_variants().map(<[_]>::to_vec).unwrap_or_default();
+            }
+
+            if v2_param.data_type == Some(DataType::Array) {
+                v2_param.collection_format =
+                    collection_format(param.in_, param.style, param.explode);
+            }
+
+            Some(v2_param)
+        })
+        .collect()
+}
+
+/// Converts v3 response headers into v2 headers.
+fn convert_headers<S: Schema>(
+    path: &str,
+    headers: BTreeMap<String, ReferenceOr<models::Header<S>>>,
+) -> BTreeMap<String, v2::models::Header<S>> {
+    headers
+        .into_iter()
+        .filter_map(|(name, header)| {
+            let header = match header {
+                ReferenceOr::Item(h) => h,
+                ReferenceOr::Reference { reference } => {
+                    warn!("Skipping unresolved header in {:?}: {}", path, reference);
+                    return None;
+                }
+            };
+
+            let mut v2_header = v2::models::Header {
+                description: header.description,
+                data_type: None,
+                format: None,
+                items: None,
+                collection_format: None,
+                default: None,
+                enum_: vec![],
+            };
+
+            if let Some(s) = header.schema.as_ref() {
+                let s = s.read();
+                v2_header.data_type = s.data_type();
+                v2_header.format = s.format().cloned();
+                v2_header.items = s.items().cloned();
+                v2_header.enum_ = s.enum_variants().map(<[_]>::to_vec).unwrap_or_default();
+            }
+
+            Some((name, v2_header))
+        })
+        .collect()
+}
+
+/// Returns the v2 collection format for an array parameter in the given
+/// location with the given style (v3 defaults are used if they're not specified).
+fn collection_format(
+    in_: ParameterIn,
+    style: Option<ParameterStyle>,
+    explode: Option<bool>,
+) -> Option<CollectionFormat> {
+    let style = style.unwrap_or(match in_ {
+        ParameterIn::Query | ParameterIn::Cookie => ParameterStyle::Form,
+        ParameterIn::Path | ParameterIn::Header => ParameterStyle::Simple,
+    });
+    let explode = explode.unwrap_or(style == ParameterStyle::Form);
+
+    match style {
+        ParameterStyle::Form if explode => Some(CollectionFormat::Multi),
+        ParameterStyle::Form | ParameterStyle::Simple => Some(CollectionFormat::Csv),
+        ParameterStyle::SpaceDelimited => Some(CollectionFormat::Ssv),
+        ParameterStyle::PipeDelimited => Some(CollectionFormat::Pipes),
+        _ => {
+            warn!("Unsupported style for array parameter: {:?}", style);
+            None
+        }
+    }
+}
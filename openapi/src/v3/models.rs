@@ -0,0 +1,322 @@
+//! Models used by OpenAPI v3.
+
+use crate::source::SourceMap;
+use crate::v2::im::ArcRwLock;
+use crate::v2::models::StatusCode;
+use serde::de::{self, Deserialize, Deserializer};
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+pub use crate::v2::models::Info;
+
+/// OpenAPI version.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Version {
+    /// Any `3.0.x` version.
+    V3_0,
+}
+
+impl<'de> Deserialize<'de> for Version {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let version = String::deserialize(deserializer)?;
+        if version == "3.0" || version.starts_with("3.0.") {
+            Ok(Version::V3_0)
+        } else {
+            Err(de::Error::custom(format!(
+                "unsupported OpenAPI version: {}",
+                version
+            )))
+        }
+    }
+}
+
+/// OpenAPI v3 spec.
+///
+/// https://github.com/OAI/OpenAPI-Specification/blob/master/versions/3.0.2.md#openapi-object
+#[derive(Clone, Debug, Deserialize)]
+pub struct Api<S> {
+    pub openapi: Version,
+    pub info: Option<Info>,
+    #[serde(default)]
+    pub servers: Vec<Server>,
+    pub paths: BTreeMap<String, PathItem<S>>,
+    #[serde(default = "Components::default")]
+    pub components: Components<S>,
+    /// Document from which this spec was loaded (if it was loaded from JSON
+    /// or YAML), used for locating the nodes which cause errors.
+    ///
+    /// **NOTE:** This is not part of the spec.
+    #[serde(skip)]
+    pub source: Option<Arc<SourceMap>>,
+}
+
+/// Server hosting the API.
+///
+/// https://github.com/OAI/OpenAPI-Specification/blob/master/versions/3.0.2.md#serverObject
+#[derive(Clone, Debug, Deserialize)]
+pub struct Server {
+    pub url: String,
+    pub description: Option<String>,
+}
+
+/// Reusable objects referred from other parts of the spec.
+///
+/// https://github.com/OAI/OpenAPI-Specification/blob/master/versions/3.0.2.md#componentsObject
+#[derive(Clone, Debug, Deserialize)]
+pub struct Components<S> {
+    #[serde(default = "BTreeMap::new")]
+    pub schemas: BTreeMap<String, ArcRwLock<S>>,
+    #[serde(default = "BTreeMap::new")]
+    pub parameters: BTreeMap<String, Parameter<S>>,
+    #[serde(default = "BTreeMap::new", rename = "requestBodies")]
+    pub request_bodies: BTreeMap<String, RequestBody<S>>,
+    #[serde(default = "BTreeMap::new")]
+    pub responses: BTreeMap<String, Response<S>>,
+    #[serde(default = "BTreeMap::new")]
+    pub headers: BTreeMap<String, Header<S>>,
+}
+
+/// Either a reference (`$ref` field) to some component or the actual object.
+///
+/// https://github.com/OAI/OpenAPI-Specification/blob/master/versions/3.0.2.md#referenceObject
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum ReferenceOr<T> {
+    Reference {
+        #[serde(rename = "$ref")]
+        reference: String,
+    },
+    Item(T),
+}
+
+/// Path item.
+///
+/// https://github.com/OAI/OpenAPI-Specification/blob/master/versions/3.0.2.md#pathItemObject
+#[derive(Clone, Debug, Deserialize)]
+pub struct PathItem<S> {
+    pub summary: Option<String>,
+    pub description: Option<String>,
+    #[serde(flatten)]
+    pub methods: BTreeMap<HttpMethod, Operation<S>>,
+    pub parameters: Option<Vec<ReferenceOr<Parameter<S>>>>,
+}
+
+/// Request parameter.
+///
+/// https://github.com/OAI/OpenAPI-Specification/blob/master/versions/3.0.2.md#parameterObject
+#[derive(Debug, Deserialize)]
+pub struct Parameter<S> {
+    pub description: Option<String>,
+    #[serde(rename = "in")]
+    pub in_: ParameterIn,
+    pub name: String,
+    #[serde(default)]
+    pub required: bool,
+    pub schema: Option<ArcRwLock<S>>,
+    pub style: Option<ParameterStyle>,
+    pub explode: Option<bool>,
+}
+
+/// How the parameter value is serialized.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ParameterStyle {
+    Matrix,
+    Label,
+    Form,
+    Simple,
+    SpaceDelimited,
+    PipeDelimited,
+    DeepObject,
+}
+
+/// The location of the parameter.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Ord, PartialOrd)]
+#[serde(rename_all = "camelCase")]
+pub enum ParameterIn {
+    Query,
+    Header,
+    Path,
+    Cookie,
+}
+
+/// An operation.
+///
+/// https://github.com/OAI/OpenAPI-Specification/blob/master/versions/3.0.2.md#operationObject
+#[derive(Clone, Debug, Deserialize)]
+pub struct Operation<S> {
+    #[serde(rename = "operationId")]
+    pub operation_id: Option<String>,
+    pub summary: Option<String>,
+    pub description: Option<String>,
+    pub parameters: Option<Vec<ReferenceOr<Parameter<S>>>>,
+    #[serde(rename = "requestBody")]
+    pub request_body: Option<ReferenceOr<RequestBody<S>>>,
+    pub responses: BTreeMap<StatusCode, ReferenceOr<Response<S>>>,
+}
+
+/// Request body.
+///
+/// https://github.com/OAI/OpenAPI-Specification/blob/master/versions/3.0.2.md#requestBodyObject
+#[derive(Debug, Deserialize)]
+pub struct RequestBody<S> {
+    pub description: Option<String>,
+    pub content: BTreeMap<String, MediaType<S>>,
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// Schema for some media type.
+///
+/// https://github.com/OAI/OpenAPI-Specification/blob/master/versions/3.0.2.md#mediaTypeObject
+#[derive(Debug, Deserialize)]
+pub struct MediaType<S> {
+    pub schema: Option<ArcRwLock<S>>,
+}
+
+/// HTTP response.
+///
+/// https://github.com/OAI/OpenAPI-Specification/blob/master/versions/3.0.2.md#responseObject
+#[derive(Debug, Deserialize)]
+pub struct Response<S> {
+    pub description: Option<String>,
+    #[serde(default = "BTreeMap::new")]
+    pub headers: BTreeMap<String, ReferenceOr<Header<S>>>,
+    #[serde(default = "BTreeMap::new")]
+    pub content: BTreeMap<String, MediaType<S>>,
+}
+
+/// Header sent with a response.
+///
+/// https://github.com/OAI/OpenAPI-Specification/blob/master/versions/3.0.2.md#headerObject
+#[derive(Debug, Deserialize)]
+pub struct Header<S> {
+    pub description: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+    pub schema: Option<ArcRwLock<S>>,
+}
+
+/// The HTTP method used for an operation.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[serde(rename_all = "lowercase")]
+pub enum HttpMethod {
+    Get,
+    Put,
+    Post,
+    Delete,
+    Options,
+    Head,
+    Patch,
+    Trace,
+}
+
+impl HttpMethod {
+    /// Returns the (lowercase) name of this method, as used in the spec.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HttpMethod::Get => "get",
+            HttpMethod::Put => "put",
+            HttpMethod::Post => "post",
+            HttpMethod::Delete => "delete",
+            HttpMethod::Options => "options",
+            HttpMethod::Head => "head",
+            HttpMethod::Patch => "patch",
+            HttpMethod::Trace => "trace",
+        }
+    }
+}
+
+impl<S> Default for Components<S> {
+    fn default() -> Self {
+        Components {
+            schemas: BTreeMap::new(),
+            parameters: BTreeMap::new(),
+            request_bodies: BTreeMap::new(),
+            responses: BTreeMap::new(),
+            headers: BTreeMap::new(),
+        }
+    }
+}
+
+// NOTE: These are implemented manually, because deriving `Clone` requires
+// the schema to be `Clone` (and the resolver clones these objects).
+
+impl<S> Clone for Parameter<S> {
+    fn clone(&self) -> Self {
+        Parameter {
+            description: self.description.clone(),
+            in_: self.in_,
+            name: self.name.clone(),
+            required: self.required,
+            schema: self.schema.clone(),
+            style: self.style,
+            explode: self.explode,
+        }
+    }
+}
+
+impl<S> Clone for RequestBody<S> {
+    fn clone(&self) -> Self {
+        RequestBody {
+            description: self.description.clone(),
+            content: self.content.clone(),
+            required: self.required,
+        }
+    }
+}
+
+impl<S> Clone for MediaType<S> {
+    fn clone(&self) -> Self {
+        MediaType {
+            schema: self.schema.clone(),
+        }
+    }
+}
+
+impl<S> Clone for Response<S> {
+    fn clone(&self) -> Self {
+        Response {
+            description: self.description.clone(),
+            headers: self.headers.clone(),
+            content: self.content.clone(),
+        }
+    }
+}
+
+impl<S> Clone for Header<S> {
+    fn clone(&self) -> Self {
+        Header {
+            description: self.description.clone(),
+            required: self.required,
+            schema: self.schema.clone(),
+        }
+    }
+}
+
+impl<T> ReferenceOr<T> {
+    /// Returns the actual object, if this is not a reference.
+    pub fn item(&self) -> Option<&T> {
+        match self {
+            ReferenceOr::Item(t) => Some(t),
+            ReferenceOr::Reference { .. } => None,
+        }
+    }
+}
+
+impl<S> MediaType<S> {
+    /// Given a map of media types and their schema, returns the one we prefer
+    /// for codegen i.e., JSON (if it exists) or the first one.
+    pub fn preferred(content: &BTreeMap<String, MediaType<S>>) -> Option<(&str, &Self)> {
+        content
+            .iter()
+            .find(|(m, _)| m.as_str() == "application/json")
+            .or_else(|| content.iter().find(|(m, _)| m.ends_with("+json")))
+            .or_else(|| content.iter().next())
+            .map(|(m, t)| (m.as_str(), t))
+    }
+}
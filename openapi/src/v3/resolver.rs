@@ -0,0 +1,216 @@
+use super::models::{
+    Components, Header, MediaType, Operation, Parameter, PathItem, ReferenceOr, Response,
+};
+use crate::error::{PaperClipError, PaperClipResult, ResultExt};
+use crate::v2::{
+    im::ArcRwLock,
+    loader::{escape_token, unescape_token},
+    resolver::Resolver as SchemaResolver,
+    Schema,
+};
+
+use std::collections::BTreeMap;
+use std::mem;
+
+const SCHEMA_REF_PREFIX: &str = "#/components/schemas/";
+const PARAMETER_REF_PREFIX: &str = "#/components/parameters/";
+const REQUEST_BODY_REF_PREFIX: &str = "#/components/requestBodies/";
+const RESPONSE_REF_PREFIX: &str = "#/components/responses/";
+const HEADER_REF_PREFIX: &str = "#/components/headers/";
+
+/// API spec resolver for OpenAPI v3. Schemas are resolved using the
+/// resolver for v2 (since they're the same). Other references (parameters,
+/// request bodies, responses and headers) are substituted with (clones of) the
+/// corresponding components.
+pub(crate) struct Resolver<S> {
+    /// Resolver for schemas (`#/components/schemas/...`).
+    schemas: SchemaResolver<S>,
+    /// Components (other than schemas).
+    pub components: Components<S>,
+    /// Paths and the corresponding operations.
+    pub paths: BTreeMap<String, PathItem<S>>,
+}
+
+impl<S> From<(Components<S>, BTreeMap<String, PathItem<S>>)> for Resolver<S> {
+    fn from((mut components, paths): (Components<S>, BTreeMap<String, PathItem<S>>)) -> Self {
+        let schemas = mem::replace(&mut components.schemas, BTreeMap::new());
+        Resolver {
+            schemas: SchemaResolver::from((schemas, BTreeMap::new()))
+                .with_ref_prefix(SCHEMA_REF_PREFIX),
+            components,
+            paths,
+        }
+    }
+}
+
+impl<S> Resolver<S>
+where
+    S: Schema,
+{
+    /// Visit components and paths and resolve them!
+    pub fn resolve(&mut self) -> PaperClipResult<()> {
+        self.schemas.resolve()?;
+
+        let mut components = mem::replace(&mut self.components, Components::default());
+        for (name, header) in &mut components.headers {
+            self.resolve_schema(&mut header.schema)
+                .at(|| format!("/components/headers/{}", escape_token(name)))?;
+        }
+
+        // Responses refer to headers, so we need the resolved ones.
+        self.components.headers = mem::replace(&mut components.headers, BTreeMap::new());
+        for (name, param) in &mut components.parameters {
+            self.resolve_schema(&mut param.schema)
+                .at(|| format!("/components/parameters/{}", escape_token(name)))?;
+        }
+
+        for (name, body) in &mut components.request_bodies {
+            self.resolve_content(&mut body.content)
+                .at(|| format!("/components/requestBodies/{}", escape_token(name)))?;
+        }
+
+        for (name, response) in &mut components.responses {
+            self.resolve_response(response)
+                .at(|| format!("/components/responses/{}", escape_token(name)))?;
+        }
+
+        components.headers = mem::replace(&mut self.components.headers, BTreeMap::new());
+        self.components = components;
+
+        let mut paths = mem::replace(&mut self.paths, BTreeMap::new());
+        paths.iter_mut().try_for_each(|(path, item)| {
+            trace!("Checking path: {}", path);
+            self.resolve_path_item(path, item)
+                .at(|| format!("/paths/{}", escape_token(path)))
+        })?;
+        self.paths = paths;
+
+        self.components.schemas = mem::replace(&mut self.schemas.defs, BTreeMap::new());
+        Ok(())
+    }
+
+    /// Resolve the given path item.
+    fn resolve_path_item(&self, path: &str, item: &mut PathItem<S>) -> PaperClipResult<()> {
+        for (&method, op) in item.methods.iter_mut() {
+            self.resolve_operation(op)
+                .at(|| format!("/{}", method.as_str()))
+                .context(|e| {
+                    PaperClipError::InOperation(method.as_str().to_uppercase(), path.into(), e)
+                })?;
+        }
+
+        self.resolve_parameters(&mut item.parameters)
+            .context(|e| PaperClipError::InPath(path.into(), e))
+    }
+
+    /// Resolve the given operation.
+    fn resolve_operation(&self, op: &mut Operation<S>) -> PaperClipResult<()> {
+        self.resolve_parameters(&mut op.parameters)?;
+
+        if let Some(body) = op.request_body.as_mut() {
+            self.resolve_component(body, REQUEST_BODY_REF_PREFIX, |c| &c.request_bodies)
+                .at(|| "/requestBody".into())?;
+            if let ReferenceOr::Item(b) = body {
+                self.resolve_content(&mut b.content)
+                    .at(|| "/requestBody".into())?;
+            }
+        }
+
+        for (code, response) in op.responses.iter_mut() {
+            let pointer = || format!("/responses/{}", code);
+            self.resolve_component(response, RESPONSE_REF_PREFIX, |c| &c.responses)
+                .at(pointer)?;
+            if let ReferenceOr::Item(r) = response {
+                self.resolve_response(r).at(pointer)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Resolve the given bunch of parameters.
+    fn resolve_parameters(
+        &self,
+        params: &mut Option<Vec<ReferenceOr<Parameter<S>>>>,
+    ) -> PaperClipResult<()> {
+        if let Some(params) = params.as_mut() {
+            for (i, param) in params.iter_mut().enumerate() {
+                let pointer = || format!("/parameters/{}", i);
+                self.resolve_component(param, PARAMETER_REF_PREFIX, |c| &c.parameters)
+                    .at(pointer)?;
+                if let ReferenceOr::Item(p) = param {
+                    self.resolve_schema(&mut p.schema).at(pointer)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Resolve the headers and content of the given response.
+    fn resolve_response(&self, response: &mut Response<S>) -> PaperClipResult<()> {
+        response.headers.iter_mut().try_for_each(|(name, h)| {
+            let pointer = || format!("/headers/{}", escape_token(name));
+            self.resolve_component(h, HEADER_REF_PREFIX, |c| &c.headers)
+                .at(pointer)?;
+            match h {
+                ReferenceOr::Item(Header { schema, .. }) => self.resolve_schema(schema).at(pointer),
+                ReferenceOr::Reference { .. } => Ok(()),
+            }
+        })?;
+
+        self.resolve_content(&mut response.content)
+    }
+
+    /// Resolve the schemas in the given media type map.
+    fn resolve_content(&self, content: &mut BTreeMap<String, MediaType<S>>) -> PaperClipResult<()> {
+        content.iter_mut().try_for_each(|(name, m)| {
+            self.resolve_schema(&mut m.schema)
+                .at(|| format!("/content/{}", escape_token(name)))
+        })
+    }
+
+    /// Resolve the given schema (if any).
+    fn resolve_schema(&self, schema: &mut Option<ArcRwLock<S>>) -> PaperClipResult<()> {
+        match schema.as_mut() {
+            Some(s) => self.schemas.resolve_definitions(s).at(|| "/schema".into()),
+            None => Ok(()),
+        }
+    }
+
+    /// If the given object is a reference, then replace it with the
+    /// corresponding component (obtained through the given function).
+    ///
+    /// **NOTE:** Components (other than schemas) are already resolved
+    /// at this point, so we don't have to resolve them again.
+    fn resolve_component<T, F>(
+        &self,
+        object: &mut ReferenceOr<T>,
+        prefix: &str,
+        map: F,
+    ) -> PaperClipResult<()>
+    where
+        T: Clone,
+        F: FnOnce(&Components<S>) -> &BTreeMap<String, T>,
+    {
+        let item = match object {
+            ReferenceOr::Reference { reference } => {
+                trace!("Resolving {}", reference);
+                if !reference.starts_with(prefix) {
+                    Err(PaperClipError::InvalidRefURI(reference.clone()).at("/$ref"))?;
+                }
+
+                map(&self.components)
+                    .get(&unescape_token(&reference[prefix.len()..]))
+                    .cloned()
+                    .ok_or_else(|| {
+                        PaperClipError::MissingDefinition(reference.clone()).at("/$ref")
+                    })?
+            }
+            ReferenceOr::Item(_) => return Ok(()),
+        };
+
+        *object = ReferenceOr::Item(item);
+        Ok(())
+    }
+}
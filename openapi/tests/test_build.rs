@@ -0,0 +1,44 @@
+use paperclip_openapi::build;
+use paperclip_openapi::v2::codegen::EmitterState;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn test_compile_into_out_dir() {
+    // The generated file is included (and compiled) by the `test_build` crate.
+    let root = env!("CARGO_MANIFEST_DIR");
+    let out_dir = Path::new(root).join("tests/test_build/generated");
+    let _ = fs::remove_dir_all(&out_dir);
+    fs::create_dir_all(&out_dir).expect("creating out dir");
+    env::set_var("OUT_DIR", &out_dir);
+
+    let path = build::compile(format!("{}/tests/pet-v2.yaml", root)).expect("compiling spec");
+    assert_eq!(path, out_dir.join("pet-v2.rs"));
+
+    let contents = fs::read_to_string(&path).expect("reading generated file");
+    assert!(contents.starts_with(
+        "pub mod pet {
+pub mod animal {
+pub type Animal = crate::pet::pet::Pet;
+}
+pub mod bank_payment {
+"
+    ));
+    assert!(!contents.contains("pub mod pet;"));
+    assert!(contents.contains("\npub mod prelude {\n"));
+    assert!(contents.ends_with("}\n}\n"));
+
+    // Definitions from other documents are included. Some definitions don't have
+    // namespaces, so the helpers are at the top level (along with their modules).
+    let mut state = EmitterState::default();
+    state.mod_prefix = "crate::ext_refs::".into();
+    let path = build::compile_with_state(format!("{}/tests/ext-refs/api.yaml", root), state)
+        .expect("compiling spec");
+    let contents = fs::read_to_string(&path).expect("reading generated file");
+    assert!(contents.starts_with("pub mod address {\n"));
+    assert!(contents.contains("\npub mod models_error {\n"));
+    assert!(contents.contains("\npub mod prelude {\n"));
+    assert!(contents.contains("crate::ext_refs::prelude::"));
+}
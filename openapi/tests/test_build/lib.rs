@@ -0,0 +1,10 @@
+#![allow(dead_code)]
+
+#[macro_use] extern crate serde_derive;
+
+// Generated by `build::compile` (with this directory as `OUT_DIR`).
+include!("generated/pet-v2.rs");
+
+pub mod ext_refs {
+    include!("generated/api.rs");
+}
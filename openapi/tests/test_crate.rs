@@ -0,0 +1,111 @@
+#[macro_use]
+extern crate lazy_static;
+
+use paperclip_openapi::v2::{
+    self,
+    codegen::{CrateMeta, DefaultEmitter, Emitter, EmitterState},
+    models::{Api, DefaultSchema},
+};
+
+use std::fs;
+
+lazy_static! {
+    static ref ROOT: String = String::from(env!("CARGO_MANIFEST_DIR"));
+    static ref CODEGEN: () = {
+        let fd = fs::File::open(ROOT.clone() + "/tests/pet-v2.yaml").expect("file?");
+        let raw: Api<DefaultSchema> = v2::from_reader(fd).expect("deserializing spec");
+        let schema = raw.resolve().expect("resolution");
+
+        let mut state = EmitterState::default();
+        state.working_dir = (&*ROOT).into();
+        state.working_dir.push("tests");
+        state.working_dir.push("test_crate");
+        let _ = fs::remove_dir_all(&state.working_dir);
+        fs::create_dir_all(&state.working_dir).expect("creating crate dir");
+        state.crate_meta = Some(CrateMeta {
+            name: Some("pet-api".into()),
+            version: Some("0.2.0".into()),
+            authors: vec!["Me <me@example.com>".into()],
+        });
+
+        let emitter = DefaultEmitter::from(state);
+        emitter.generate(&schema).expect("creating crate");
+    };
+}
+
+fn read_generated(rel_path: &str) -> String {
+    let _ = &*CODEGEN;
+    let path = ROOT.clone() + "/tests/test_crate/" + rel_path;
+    fs::read_to_string(&path).expect("missing file")
+}
+
+#[test]
+fn test_manifest() {
+    assert_eq!(
+        read_generated("Cargo.toml"),
+        "[package]
+name = \"pet-api\"
+version = \"0.2.0\"
+authors = [\"Me <me@example.com>\"]
+edition = \"2018\"
+
+[lib]
+path = \"lib.rs\"
+
+[dependencies]
+reqwest = \"0.9\"
+serde = \"1.0\"
+serde_derive = \"1.0\"
+serde_json = \"1.0\"
+"
+    );
+}
+
+#[test]
+fn test_lib_module() {
+    let contents = read_generated("lib.rs");
+    assert_eq!(
+        contents.find(
+            "#[macro_use]
+extern crate serde_derive;
+
+pub mod pet;
+
+pub mod prelude {
+"
+        ),
+        Some(0)
+    );
+    assert!(contents.contains("\npub mod client {\n"));
+
+    // Helpers are in the crate root.
+    let contents = read_generated("pet/pet.rs");
+    assert!(
+        contents.contains("    pub fn add_pet() -> PetPostBuilder<crate::prelude::MissingName> {")
+    );
+    assert!(!read_generated("pet/mod.rs").contains("pub mod prelude"));
+}
+
+#[test]
+fn test_readme() {
+    assert_eq!(
+        read_generated("README.md"),
+        "# pet-api
+
+API client generated by [paperclip](https://github.com/wafflespeanut/paperclip).
+
+## Pet store (1.0)
+
+Sample API for managing pets.
+
+## Operations
+
+| Method | Path | Builder |
+|---|---|---|
+| `POST` | `/pets` | `pet::pet::Pet::add_pet()` |
+| `GET` | `/pets/{id}` | `pet::pet::Pet::get_pet_by_id()` |
+| `PATCH` | `/pets/{id}` | `pet::pet::Pet::update_pet()` |
+| `POST` | `/pets/{id}/photo` | `pet::pet::Pet::upload_photo()` |
+"
+    );
+}
@@ -0,0 +1,122 @@
+#[macro_use]
+extern crate lazy_static;
+
+use paperclip_openapi::v2::models::{DataType, HttpMethod, StatusCode};
+use paperclip_openapi::v2::{self, Api, DefaultSchema, Schema};
+use paperclip_openapi::{Spec, SpecFormat};
+
+use std::fs;
+use std::io::{self, Cursor, Read};
+use std::path::Path;
+
+lazy_static! {
+    static ref ROOT: String = String::from(env!("CARGO_MANIFEST_DIR"));
+    static ref JSON_SPEC: String = String::from(
+        r##"{
+            "swagger": "2.0",
+            "definitions": {
+                "Pet": {
+                    "type": "object",
+                    "properties": {"name": {"type": "string"}}
+                }
+            },
+            "paths": {}
+        }"##
+    );
+}
+
+/// Reader which can't be seeked (like stdin), returning few bytes at a time.
+struct Trickle<'a>(&'a [u8]);
+
+impl<'a> Read for Trickle<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = buf.len().min(self.0.len()).min(3);
+        buf[..n].copy_from_slice(&self.0[..n]);
+        self.0 = &self.0[n..];
+        Ok(n)
+    }
+}
+
+fn assert_pet(api: &Api<DefaultSchema>) {
+    let pet = api.definitions["Pet"].read();
+    let name = &pet.properties.as_ref().unwrap()["name"];
+    assert_eq!(name.read().data_type, Some(DataType::String));
+}
+
+#[test]
+fn test_format_detection() {
+    assert_eq!(SpecFormat::detect(b"  \n\t{}"), SpecFormat::Json);
+    assert_eq!(SpecFormat::detect(b"\xEF\xBB\xBF\n{}"), SpecFormat::Json);
+    assert_eq!(SpecFormat::detect(b"swagger: '2.0'"), SpecFormat::Yaml);
+    assert_eq!(SpecFormat::detect(b""), SpecFormat::Yaml);
+
+    assert_eq!(
+        SpecFormat::from_path(Path::new("api.JSON")),
+        Some(SpecFormat::Json)
+    );
+    assert_eq!(
+        SpecFormat::from_path(Path::new("a/api.yml")),
+        Some(SpecFormat::Yaml)
+    );
+    assert_eq!(
+        SpecFormat::from_path(Path::new("api.yaml")),
+        Some(SpecFormat::Yaml)
+    );
+    assert_eq!(SpecFormat::from_path(Path::new("api.txt")), None);
+    assert_eq!(SpecFormat::from_path(Path::new("api")), None);
+}
+
+#[test]
+fn test_leading_whitespace_and_bom() {
+    let spec = format!("\u{feff}\n\n   {}", *JSON_SPEC);
+    let api: Api<DefaultSchema> = v2::from_reader(Cursor::new(&spec)).expect("seekable");
+    assert_pet(&api);
+    let api: Api<DefaultSchema> = v2::from_str(&spec).expect("string");
+    assert_pet(&api);
+    let api: Api<DefaultSchema> = v2::from_json_reader(Trickle(spec.as_bytes())).expect("JSON");
+    assert_pet(&api);
+}
+
+#[test]
+fn test_non_seekable_readers() {
+    let api: Api<DefaultSchema> = v2::from_reader(Trickle(JSON_SPEC.as_bytes())).expect("JSON");
+    assert_pet(&api);
+
+    let yaml = fs::read(ROOT.clone() + "/tests/pet-v2.yaml").expect("file?");
+    let api: Api<DefaultSchema> = v2::from_reader(Trickle(&yaml)).expect("YAML");
+    assert_eq!(api.definitions.len(), 12);
+    let api: Api<DefaultSchema> = v2::from_yaml_reader(Trickle(&yaml)).expect("explicit YAML");
+    assert_eq!(api.definitions.len(), 12);
+    let api: Api<DefaultSchema> = v2::from_slice(&yaml).expect("slice");
+    assert_eq!(api.definitions.len(), 12);
+
+    // Explicit formats aren't second-guessed.
+    assert!(v2::from_json_reader::<_, DefaultSchema>(Trickle(&yaml)).is_err());
+}
+
+#[test]
+fn test_path_loading() {
+    let api: Api<DefaultSchema> =
+        v2::from_path(ROOT.clone() + "/tests/ext-refs/api.yaml").expect("spec with refs");
+    let api = api.resolve().expect("resolution");
+    assert!(api.definitions.contains_key("models_Error"));
+    let op = &api.paths["/pets/{id}"].methods[&HttpMethod::Get];
+    let schema = op.responses[&StatusCode::Code(200)]
+        .schema
+        .as_ref()
+        .unwrap()
+        .read();
+    assert_eq!(schema.name(), Some("pet/Pet"));
+
+    match paperclip_openapi::from_path::<_, DefaultSchema>(ROOT.clone() + "/tests/pet-v3.yaml")
+        .expect("v3 spec")
+    {
+        Spec::V3(api) => assert_eq!(api.servers[0].url, "https://pets.example.com/v1"),
+        _ => panic!("expected v3 spec"),
+    }
+
+    match paperclip_openapi::from_slice::<DefaultSchema>(JSON_SPEC.as_bytes()).expect("v2 spec") {
+        Spec::V2(api) => assert_pet(&api),
+        _ => panic!("expected v2 spec"),
+    }
+}
@@ -8,7 +8,7 @@ extern crate serde_derive;
 use paperclip_openapi::v2::{
     self,
     codegen::{DefaultEmitter, Emitter, EmitterState},
-    models::{Api, HttpMethod, Version},
+    models::{Api, HttpMethod, StatusCode, Version},
 };
 
 use std::fs::File;
@@ -68,7 +68,8 @@ fn test_definition_ref_cycles() {
 
 #[test]
 fn test_path_with_schema() {
-    let api_versions = &SCHEMA.paths["/api/"].methods[&HttpMethod::Get].responses["200"].schema;
+    let api_versions =
+        &SCHEMA.paths["/api/"].methods[&HttpMethod::Get].responses[&StatusCode::Code(200)].schema;
     let schema = api_versions.as_ref().expect("bleh?").read();
     assert!(schema.reference.is_none()); // this was a reference
     assert_eq!(
@@ -103,7 +104,7 @@ fn test_complex_object() {
 "#[derive(Debug, Default, Clone, Deserialize, Serialize)]
 pub struct JsonSchemaProps {
     #[serde(rename = \"$ref\")]
-    pub ref_: Option<String>,
+    pub r#ref: Option<String>,
     #[serde(rename = \"$schema\")]
     pub schema: Option<String>,
     #[serde(rename = \"additionalItems\")]
@@ -119,7 +120,7 @@ pub struct JsonSchemaProps {
     pub dependencies: Option<std::collections::BTreeMap<String, String>>,
     pub description: Option<String>,
     #[serde(rename = \"enum\")]
-    pub enum_: Option<Vec<String>>,
+    pub r#enum: Option<Vec<String>>,
     pub example: Option<String>,
     #[serde(rename = \"exclusiveMaximum\")]
     pub exclusive_maximum: Option<bool>,
@@ -157,7 +158,7 @@ pub struct JsonSchemaProps {
     pub required: Option<Vec<String>>,
     pub title: Option<String>,
     #[serde(rename = \"type\")]
-    pub type_: Option<String>,
+    pub r#type: Option<String>,
     #[serde(rename = \"uniqueItems\")]
     pub unique_items: Option<bool>,
     #[serde(rename = \"x-kubernetes-embedded-resource\")]
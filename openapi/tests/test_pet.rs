@@ -0,0 +1,1242 @@
+#[macro_use]
+extern crate lazy_static;
+
+use paperclip_openapi::v2::{
+    self,
+    codegen::object::{ApiEnum, EnumVariant},
+    codegen::{DefaultEmitter, Emitter, EmitterState, FormatOptions, MemoryTree},
+    models::{Api, DefaultSchema},
+    Schema,
+};
+use paperclip_openapi::PaperClipResult;
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
+
+lazy_static! {
+    static ref ROOT: String = String::from(env!("CARGO_MANIFEST_DIR"));
+    static ref SCHEMA: Api<DefaultSchema> = {
+        let fd = File::open(ROOT.clone() + "/tests/pet-v2.yaml").expect("file?");
+        let raw: Api<DefaultSchema> = v2::from_reader(fd).expect("deserializing spec");
+        raw.resolve().expect("resolution")
+    };
+    static ref CODEGEN: () = {
+        let mut state = EmitterState::default();
+        state.working_dir = (&*ROOT).into();
+        state.working_dir.push("tests");
+        state.working_dir.push("test_pet");
+        // Assertions are made on unformatted code (even with `codegen-fmt`).
+        state.format = None;
+
+        let emitter = DefaultEmitter::from(state);
+        emitter.generate(&SCHEMA).expect("creating definitions");
+    };
+}
+
+fn read_generated(rel_path: &str) -> String {
+    let _ = &*CODEGEN;
+    let mut contents = String::new();
+    let path = ROOT.clone() + "/tests/test_pet/" + rel_path;
+    let mut fd = File::open(&path).expect("missing mod");
+    fd.read_to_string(&mut contents).expect("reading mod");
+    contents
+}
+
+/// Generates code for the given (resolved) spec in memory, after customizing
+/// the emitter state with the given function.
+fn generate_in_memory<F>(api: &Api<DefaultSchema>, configure: F) -> PaperClipResult<Arc<MemoryTree>>
+where
+    F: FnOnce(&mut EmitterState),
+{
+    let tree = Arc::new(MemoryTree::default());
+    let mut state = EmitterState::default();
+    configure(&mut state);
+    state.sink = tree.clone();
+    DefaultEmitter::from(state).generate(api)?;
+    Ok(tree)
+}
+
+#[test]
+fn test_object_with_builder_constructors() {
+    let contents = read_generated("pet/pet.rs");
+    assert_eq!(
+        contents.find(
+            "#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct Pet {
+    pub category: Option<crate::pet::category::Category>,
+    pub id: Option<i64>,
+    pub name: String,
+    pub tags: Option<Vec<String>>,
+}
+
+impl Pet {
+    #[inline]
+    pub fn add_pet() -> PetPostBuilder<crate::pet::prelude::MissingName> {
+        PetPostBuilder {
+            inner: Default::default(),
+            _name: core::marker::PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn get_pet_by_id() -> PetGetBuilder1<crate::pet::prelude::MissingId> {
+        PetGetBuilder1 {
+            inner: Default::default(),
+            _param_id: core::marker::PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn update_pet() -> PetPatchBuilder1<crate::pet::prelude::MissingId, crate::pet::prelude::MissingName> {
+        PetPatchBuilder1 {
+            inner: Default::default(),
+            _param_id: core::marker::PhantomData,
+            _name: core::marker::PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn upload_photo() -> PetPostBuilder2<crate::pet::prelude::MissingId> {
+        PetPostBuilder2 {
+            inner: Default::default(),
+            _param_id: core::marker::PhantomData,
+        }
+    }
+}
+"
+        ),
+        Some(0)
+    );
+}
+
+#[test]
+fn test_builder_with_body_and_header() {
+    let contents = read_generated("pet/pet.rs");
+    assert!(contents.contains(
+        "
+impl<Name> PetPostBuilder<Name> {
+    #[inline]
+    pub fn x_request_id(mut self, value: impl Into<String>) -> Self {
+        self.inner.param_x_request_id = Some(value.into());
+        self
+    }
+
+    #[inline]
+    pub fn category(mut self, value: impl Into<crate::pet::category::Category>) -> Self {
+        self.inner.inner.category = Some(value.into());
+        self
+    }
+
+    #[inline]
+    pub fn id(mut self, value: impl Into<i64>) -> Self {
+        self.inner.inner.id = Some(value.into());
+        self
+    }
+
+    #[inline]
+    pub fn name(mut self, value: impl Into<String>) -> PetPostBuilder<crate::pet::prelude::NameExists> {
+        self.inner.inner.name = value.into();
+        unsafe { core::mem::transmute(self) }
+    }
+
+    #[inline]
+    pub fn tags(mut self, value: impl Into<Vec<String>>) -> Self {
+        self.inner.inner.tags = Some(value.into());
+        self
+    }
+}
+
+impl PetPostBuilder<crate::pet::prelude::NameExists> {
+    pub fn send<C: crate::pet::client::ApiClient>(&self, client: &C) -> Result<PetPostResponse, reqwest::Error> {
+        let mut request = client.request_builder(reqwest::Method::POST, \"/pets\");
+        request = request.header(reqwest::header::ACCEPT, \"application/json\");
+        if let Some(v) = self.inner.param_x_request_id.as_ref() {
+            request = request.header(\"X-Request-Id\", v.to_string());
+        }
+        request = request.json(&self.inner.inner);
+        let mut response = request.send()?;
+        Ok(match response.status().as_u16() {
+            200 => PetPostResponse::Ok {
+                body: response.json()?,
+            },
+            status @ 400..=499 => PetPostResponse::ClientError {
+                status,
+            },
+            _ => PetPostResponse::Other(response),
+        })
+    }
+}
+"
+    ));
+}
+
+#[test]
+fn test_builder_with_path_and_query() {
+    let contents = read_generated("pet/pet.rs");
+    assert!(contents.contains(
+        "
+impl PetGetBuilder1<crate::pet::prelude::IdExists> {
+    pub fn send<C: crate::pet::client::ApiClient>(&self, client: &C) -> Result<PetGetResponse1, reqwest::Error> {
+        let mut request = client.request_builder(reqwest::Method::GET, &format!(\"/pets/{}\", crate::pet::client::encode_path_segment(&self.inner.param_id.as_ref().expect(\"missing parameter id?\").to_string())));
+        request = request.header(reqwest::header::ACCEPT, \"application/json\");
+        if let Some(v) = self.inner.param_verbose.as_ref() {
+            request = request.query(&[(\"verbose\", v)]);
+        }
+        if let Some(v) = self.inner.param_tags.as_ref() {
+            for v in v {
+                request = request.query(&[(\"tags\", v)]);
+            }
+        }
+        if let Some(v) = self.inner.param_fields.as_ref() {
+            let v = v.iter().map(ToString::to_string).collect::<Vec<_>>().join(\",\");
+            request = request.query(&[(\"fields\", v)]);
+        }
+        if let Some(v) = self.inner.param_x_owner_ids.as_ref() {
+            let v = v.iter().map(ToString::to_string).collect::<Vec<_>>().join(\"|\");
+            request = request.header(\"X-Owner-Ids\", v.to_string());
+        }
+        let mut response = request.send()?;
+        Ok(match response.status().as_u16() {
+            200 => PetGetResponse1::Ok {
+                e_tag: response.headers().get(\"ETag\").and_then(|v| v.to_str().ok()).and_then(|v| v.parse().ok()),
+                x_rate_limit_remaining: response.headers().get(\"X-RateLimit-Remaining\").and_then(|v| v.to_str().ok()).and_then(|v| v.parse().ok()),
+                body: response.json()?,
+            },
+            404 => PetGetResponse1::NotFound,
+            status => PetGetResponse1::Default {
+                status,
+            },
+        })
+    }
+}
+"
+    ));
+    assert!(contents.contains("    param_x_owner_ids: Option<Vec<i64>>,\n"));
+    assert!(
+        contents.contains("    pub fn x_owner_ids(mut self, value: impl Into<Vec<i64>>) -> Self {")
+    );
+}
+
+#[test]
+fn test_media_types() {
+    use paperclip_openapi::v2::models::{HttpMethod, MediaType};
+    use paperclip_openapi::PaperClipError;
+
+    let media = MediaType::parse("Text/Plain; charset=\"utf-8\"").unwrap();
+    assert_eq!(
+        (media.type_.as_str(), media.subtype.as_str()),
+        ("text", "plain")
+    );
+    assert_eq!(media.params, &[("charset".into(), "utf-8".into())]);
+    assert_eq!(media.to_string(), "text/plain; charset=utf-8");
+    assert!(MediaType::parse("application/merge-patch+json")
+        .unwrap()
+        .is_json());
+    assert!(MediaType::parse("application/x-www-form-urlencoded")
+        .unwrap()
+        .is_form_urlencoded());
+    assert!(MediaType::parse("multipart/form-data")
+        .unwrap()
+        .is_multipart_form());
+    for media in &[
+        "json",
+        "application/",
+        "/json",
+        "text/plain; charset",
+        "a b/c",
+    ] {
+        assert_eq!(MediaType::parse(media), None, "{:?}", media);
+    }
+
+    let op = &SCHEMA.paths["/pets/{id}"].methods[&HttpMethod::Patch];
+    assert_eq!(op.consumes.as_ref().unwrap()[0].suffix(), Some("json"));
+
+    let spec = r#"{
+  "swagger": "2.0",
+  "definitions": {},
+  "paths": {
+    "/pets": {
+      "post": { "consumes": ["application/json", "json"], "responses": {} }
+    }
+  }
+}"#;
+    match v2::from_str::<DefaultSchema>(spec).map_err(PaperClipError::into_inner) {
+        Err(PaperClipError::InvalidMediaType(media, location)) => {
+            assert_eq!(media, "json");
+            assert_eq!(location, "POST \"/pets\"");
+        }
+        r => panic!("unexpected result: {:?}", r),
+    }
+}
+
+#[test]
+fn test_content_negotiation() {
+    let contents = read_generated("pet/pet.rs");
+    // Content type is set for JSON media types other than `application/json`.
+    assert!(contents.contains(
+        "
+        request = request.header(reqwest::header::ACCEPT, \"application/json\");
+        request = request.json(&self.inner.inner);
+        request = request.header(reqwest::header::CONTENT_TYPE, \"application/merge-patch+json\");
+        let mut response = request.send()?;
+"
+    ));
+    // Multipart forms.
+    assert!(contents.contains(
+        "
+        request = request.header(reqwest::header::ACCEPT, \"text/plain; charset=utf-8\");
+        let mut form = reqwest::multipart::Form::new();
+        if let Some(v) = self.inner.param_caption.as_ref() {
+            form = form.text(\"caption\", v.to_string());
+        }
+        if let Some(v) = self.inner.param_tags.as_ref() {
+            for v in v {
+                form = form.text(\"tags\", v.to_string());
+            }
+        }
+        request = request.multipart(form);
+"
+    ));
+    // Non-JSON responses are read as text.
+    assert!(contents.contains(
+        "
+            200 => PetPostResponse2::Ok {
+                body: response.text()?,
+            },
+"
+    ));
+    assert!(contents.contains(
+        "
+pub enum PetPostResponse2 {
+    /// Uploaded photo
+    Ok {
+        body: String,
+    },
+"
+    ));
+
+    // Other responses are read as bytes.
+    let spec = r##"
+swagger: "2.0"
+definitions:
+  pet.Photo:
+    type: object
+    properties:
+      caption:
+        type: string
+paths:
+  /photos/{id}:
+    get:
+      produces: [image/png]
+      parameters:
+        - in: path
+          name: id
+          type: string
+          required: true
+      responses:
+        "200":
+          schema:
+            $ref: "#/definitions/pet.Photo"
+"##;
+    let api: Api<DefaultSchema> = v2::from_str(spec).expect("deserializing spec");
+    let tree = generate_in_memory(&api.resolve().expect("resolution"), |s| s.format = None)
+        .expect("generating code");
+    let contents = tree.files().values().cloned().collect::<String>();
+    assert!(contents.contains("        body: Vec<u8>,\n"));
+    assert!(contents.contains(
+        "
+                body: {
+                    let mut body = vec![];
+                    response.copy_to(&mut body)?;
+                    body
+                },
+"
+    ));
+}
+
+#[test]
+fn test_response_enum() {
+    let contents = read_generated("pet/pet.rs");
+    assert!(contents.contains(
+        "
+/// Responses for `GET /pets/{id}`.
+#[derive(Debug)]
+pub enum PetGetResponse1 {
+    /// Found pet
+    Ok {
+        e_tag: Option<String>,
+        x_rate_limit_remaining: Option<i32>,
+        body: crate::pet::pet::Pet,
+    },
+    /// Pet not found
+    NotFound,
+    /// Unexpected error
+    Default {
+        status: u16,
+    },
+}
+"
+    ));
+    // Operations without a `default` response get a catch-all variant.
+    assert!(contents.contains(
+        "
+    /// Response with an undeclared status code.
+    Other(reqwest::Response),
+}
+"
+    ));
+
+    // JSON bodies of error responses are decoded on a best-effort basis.
+    let spec = r##"
+swagger: "2.0"
+definitions:
+  pet.Error:
+    type: object
+    properties:
+      message:
+        type: string
+  pet.Pet:
+    type: object
+    properties:
+      name:
+        type: string
+paths:
+  /pets:
+    get:
+      responses:
+        "200":
+          schema:
+            $ref: "#/definitions/pet.Pet"
+        "404":
+          schema:
+            $ref: "#/definitions/pet.Error"
+        default:
+          schema:
+            $ref: "#/definitions/pet.Error"
+"##;
+    let api: Api<DefaultSchema> = v2::from_str(spec).expect("deserializing spec");
+    let tree = generate_in_memory(&api.resolve().expect("resolution"), |s| s.format = None)
+        .expect("generating code");
+    let contents = tree.files().values().cloned().collect::<String>();
+    assert!(contents.contains(
+        "
+            200 => PetGetResponse::Ok {
+                body: response.json()?,
+            },
+            404 => PetGetResponse::NotFound {
+                body: crate::pet::client::decode_json_lossy(&mut response)?,
+            },
+            status => PetGetResponse::Default {
+                status,
+                body: crate::pet::client::decode_json_lossy(&mut response)?,
+            },
+"
+    ));
+    assert!(contents.contains(
+        "
+    Ok {
+        body: crate::pet::pet::Pet,
+    },
+    NotFound {
+        /// Decoded body (or the raw body, if it couldn't be decoded).
+        body: Result<crate::pet::error::Error, Vec<u8>>,
+    },
+"
+    ));
+}
+
+#[test]
+fn test_response_headers_and_examples() {
+    use paperclip_openapi::v2::models::{DataType, DataTypeFormat, HttpMethod, StatusCode};
+
+    let op = &SCHEMA.paths["/pets/{id}"].methods[&HttpMethod::Get];
+    let ok = &op.responses[&StatusCode::Code(200)];
+    assert_eq!(ok.headers["ETag"].data_type, Some(DataType::String));
+    let remaining = &ok.headers["X-RateLimit-Remaining"];
+    assert_eq!(remaining.data_type, Some(DataType::Integer));
+    assert_eq!(remaining.format, Some(DataTypeFormat::Int32));
+    assert_eq!(ok.examples["application/json"]["name"], "Fido");
+
+    assert!(op.responses[&StatusCode::Code(404)].schema.is_none());
+    assert_eq!(
+        op.responses[&StatusCode::Default]
+            .description
+            .as_ref()
+            .map(String::as_str),
+        Some("Unexpected error")
+    );
+}
+
+#[test]
+fn test_status_codes() {
+    use paperclip_openapi::v2::models::{HttpMethod, StatusCode};
+    use paperclip_openapi::PaperClipError;
+
+    assert_eq!(StatusCode::parse("204"), Some(StatusCode::Code(204)));
+    assert_eq!(StatusCode::parse("2XX"), Some(StatusCode::Range(2)));
+    assert_eq!(StatusCode::parse("5xx"), Some(StatusCode::Range(5)));
+    assert_eq!(StatusCode::parse("default"), Some(StatusCode::Default));
+    for key in &["20O", "2X0", "600", "099", "6XX", "2000", "Default", ""] {
+        assert_eq!(StatusCode::parse(key), None, "{:?}", key);
+    }
+
+    assert!(StatusCode::Range(4).matches(404));
+    assert!(!StatusCode::Range(4).matches(500));
+    assert!(StatusCode::Code(201).is_success());
+    assert!(!StatusCode::Default.is_success());
+    assert_eq!(StatusCode::Range(4).to_string(), "4XX");
+
+    // Status codes come before ranges, which come before the default response.
+    let op = &SCHEMA.paths["/pets"].methods[&HttpMethod::Post];
+    let keys: Vec<_> = op.responses.keys().cloned().collect();
+    assert_eq!(keys, vec![StatusCode::Code(200), StatusCode::Range(4)]);
+
+    let spec = r#"{
+  "swagger": "2.0",
+  "definitions": {},
+  "paths": {
+    "/pets": {
+      "get": { "responses": { "200": {} } },
+      "post": { "responses": { "20O": {} } }
+    }
+  }
+}"#;
+    match v2::from_str::<DefaultSchema>(spec).map_err(PaperClipError::into_inner) {
+        Err(PaperClipError::InvalidStatusCode(code, method, path)) => {
+            assert_eq!(
+                (code.as_str(), method.as_str(), path.as_str()),
+                ("20O", "POST", "/pets")
+            );
+        }
+        r => panic!("unexpected result: {:?}", r),
+    }
+}
+
+#[test]
+fn test_array_parameters() {
+    use paperclip_openapi::v2::models::{CollectionFormat, HttpMethod};
+    use paperclip_openapi::PaperClipError;
+
+    let op = &SCHEMA.paths["/pets/{id}"].methods[&HttpMethod::Get];
+    let params = op.parameters.as_ref().unwrap();
+    assert_eq!(params[1].default, Some(serde_json::Value::Bool(false)));
+    assert_eq!(params[2].collection_format, Some(CollectionFormat::Multi));
+    assert_eq!(params[3].collection_format, None); // CSV by default
+    assert_eq!(params[3].min_items, Some(1));
+    assert_eq!(params[4].collection_format, Some(CollectionFormat::Pipes));
+    assert_eq!(params[4].max_items, Some(10));
+    assert!(params[4].unique_items);
+
+    // `multi` is only for query and form parameters.
+    let mut param = params[4].clone();
+    param.collection_format = Some(CollectionFormat::Multi);
+    match param.check("/pets/{id}") {
+        Err(PaperClipError::InvalidCollectionFormat(name, _)) => assert_eq!(name, "X-Owner-Ids"),
+        e => panic!("unexpected result: {:?}", e),
+    }
+
+    // Arrays need items.
+    param.items = None;
+    match param.check("/pets/{id}") {
+        Err(PaperClipError::MissingArrayItem(Some(name))) => assert_eq!(name, "X-Owner-Ids"),
+        e => panic!("unexpected result: {:?}", e),
+    }
+}
+
+#[test]
+fn test_root_module_helpers() {
+    let contents = read_generated("pet/mod.rs");
+    assert!(contents.contains(
+        "
+    #[derive(Debug, Clone)]
+    pub struct MissingId;
+    #[derive(Debug, Clone)]
+    pub struct IdExists;
+"
+    ));
+    assert!(contents.contains("\npub mod client {\n"));
+}
+
+#[test]
+fn test_root_module_choice() {
+    use paperclip_openapi::PaperClipError;
+
+    let spec = r#"{
+  "swagger": "2.0",
+  "definitions": {
+    "pet.Pet": { "type": "object", "properties": { "name": { "type": "string" } } },
+    "store.Order": { "type": "object", "properties": { "id": { "type": "integer" } } }
+  },
+  "paths": {}
+}"#;
+    let api = v2::from_str::<DefaultSchema>(spec)
+        .expect("spec")
+        .resolve()
+        .expect("resolving");
+    let generate = |root: Option<&str>| {
+        generate_in_memory(&api, |s| {
+            s.working_dir = "roots".into();
+            s.root_module = root.map(String::from);
+        })
+        .map(|tree| tree.files())
+    };
+
+    // There are two top-level modules, so we don't pick one.
+    match generate(None).map_err(PaperClipError::into_inner) {
+        Err(PaperClipError::MultipleRootModules(ref m)) if m == &["pet", "store"] => (),
+        r => panic!("unexpected result: {:?}", r),
+    }
+
+    match generate(Some("order")).map_err(PaperClipError::into_inner) {
+        Err(PaperClipError::MissingRootModule(ref m)) if m == "order" => (),
+        r => panic!("unexpected result: {:?}", r),
+    }
+
+    let files = generate(Some("store")).expect("creating definitions");
+    assert!(files[Path::new("roots/store/mod.rs")].contains("\npub mod client {\n"));
+    assert!(!files[Path::new("roots/pet/mod.rs")].contains("pub mod client"));
+}
+
+#[test]
+fn test_definitions_without_namespaces() {
+    let spec = r##"{
+  "swagger": "2.0",
+  "definitions": {
+    "Pet": { "type": "object", "properties": { "name": { "type": "string" } } },
+    "Error": { "type": "object", "properties": { "code": { "type": "integer" } } },
+    "store.Order": { "type": "object", "properties": { "id": { "type": "integer" } } }
+  },
+  "paths": {
+    "/pets/{id}": {
+      "get": {
+        "operationId": "getPet",
+        "parameters": [{ "in": "path", "name": "id", "type": "string", "required": true }],
+        "responses": { "200": { "schema": { "$ref": "#/definitions/Pet" } } }
+      }
+    }
+  }
+}"##;
+    let api = v2::from_str::<DefaultSchema>(spec)
+        .expect("spec")
+        .resolve()
+        .expect("resolving");
+    let tree =
+        generate_in_memory(&api, |s| s.working_dir = "plain".into()).expect("creating definitions");
+
+    // The working directory is the root module, which declares the other modules.
+    let contents = tree.file("plain/mod.rs").expect("missing root module");
+    assert!(contents.starts_with("pub mod error;\npub mod pet;\npub mod store;\n"));
+    assert!(contents.contains("\npub mod prelude {\n"));
+    assert!(contents.contains("    pub struct MissingId;\n"));
+    assert!(contents.contains("\npub mod client {\n"));
+    let contents = tree.file("plain/store/mod.rs").expect("missing mod");
+    assert!(!contents.contains("pub mod client"));
+
+    let contents = tree.file("plain/pet.rs").expect("missing mod");
+    assert!(contents.contains("crate::prelude::MissingId"));
+    assert!(contents.contains("<C: crate::client::ApiClient>"));
+}
+
+#[test]
+fn test_path_segments_encoded() {
+    let contents = read_generated("pet/mod.rs");
+    assert!(contents.contains("    pub fn encode_path_segment(value: &str) -> String {\n"));
+
+    let contents = read_generated("pet/pet.rs");
+    assert!(contents.contains(
+        "&format!(\"/pets/{}\", crate::pet::client::encode_path_segment(&self.inner.param_id"
+    ));
+}
+
+#[test]
+fn test_keywords() {
+    let spec = r##"{
+  "swagger": "2.0",
+  "definitions": {
+    "pet.Pet": {
+      "type": "object",
+      "required": ["type"],
+      "properties": {
+        "type": { "type": "string" },
+        "self": { "type": "string" },
+        "match": { "type": "integer" }
+      }
+    }
+  },
+  "paths": {
+    "/pets/{fn}": {
+      "get": {
+        "operationId": "match",
+        "parameters": [
+          { "in": "path", "name": "fn", "type": "string", "required": true },
+          { "in": "query", "name": "self", "type": "string" }
+        ],
+        "responses": {
+          "200": {
+            "schema": { "$ref": "#/definitions/pet.Pet" },
+            "headers": { "type": { "type": "string" } }
+          }
+        }
+      }
+    }
+  }
+}"##;
+    let api = v2::from_str::<DefaultSchema>(spec)
+        .expect("spec")
+        .resolve()
+        .expect("resolving");
+    let tree = generate_in_memory(&api, |s| s.working_dir = "keywords".into())
+        .expect("creating definitions");
+
+    // Keywords are raw identifiers, except for those which can't be.
+    let contents = tree.file("keywords/pet/pet.rs").expect("missing mod");
+    assert!(contents.contains("    #[serde(rename = \"type\")]\n    pub r#type: String,\n"));
+    assert!(contents.contains("    #[serde(rename = \"self\")]\n    pub self_: Option<String>,\n"));
+    assert!(contents.contains("    pub fn r#match() -> "));
+    assert!(contents.contains("    pub fn r#fn(mut self, "));
+    assert!(contents.contains("    pub fn self_(mut self, "));
+    assert!(contents.contains("        r#type: Option<String>,"));
+    for (path, contents) in tree.files() {
+        if let Err(e) = syn::parse_file(&contents) {
+            panic!("invalid code in {:?}: {}", path, e);
+        }
+    }
+}
+
+#[test]
+fn test_type_aliases() {
+    let contents = read_generated("pet/category.rs");
+    assert!(contents.contains("    pub owner_id: Option<String>,\n"));
+
+    let contents = read_generated("pet/owner_id.rs");
+    assert_eq!(contents, "pub type OwnerId = String;\n");
+    let contents = read_generated("pet/animal.rs");
+    assert_eq!(contents, "pub type Animal = crate::pet::pet::Pet;\n");
+    let contents = read_generated("pet/pets.rs");
+    assert_eq!(contents, "pub type Pets = Vec<crate::pet::pet::Pet>;\n");
+    let contents = read_generated("pet/labels.rs");
+    assert_eq!(
+        contents,
+        "pub type Labels = std::collections::BTreeMap<String, String>;\n"
+    );
+
+    let mod_contents = read_generated("pet/mod.rs");
+    assert!(mod_contents.contains("pub mod owner_id;\n"));
+    assert!(!mod_contents.contains("pub mod uuid;\n"));
+}
+
+#[test]
+fn test_enums() {
+    let contents = read_generated("pet/status.rs");
+    assert_eq!(
+        contents,
+        "#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum Status {
+    #[serde(rename = \"available\")]
+    Available,
+    Pending,
+    #[serde(rename = \"SOLD_OUT\")]
+    SoldOut,
+}
+
+impl Default for Status {
+    fn default() -> Self {
+        Status::Available
+    }
+}
+"
+    );
+
+    let contents = read_generated("pet/category.rs");
+    assert!(contents.contains("    pub status: Option<crate::pet::status::Status>,\n"));
+    assert!(contents.contains("    pub kind: Option<crate::pet::category::CategoryKind>,\n"));
+    assert!(contents.contains(
+        "
+pub enum CategoryKind {
+    #[serde(rename = \"dog\")]
+    Dog,
+    #[serde(rename = \"cat\")]
+    Cat,
+    #[serde(rename = \"in-store\")]
+    InStore,
+    #[serde(rename = \"Self\")]
+    Self_,
+}
+"
+    ));
+    assert!(contents.contains(
+        "#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(try_from = \"i64\", into = \"i64\")]
+pub enum CategoryPriority {
+    V1,
+    V2,
+    VNeg1,
+}
+"
+    ));
+    assert!(contents.contains(
+        "
+            -1 => Ok(CategoryPriority::VNeg1),
+            _ => Err(format!(\"unknown value for CategoryPriority: {}\", value)),
+"
+    ));
+}
+
+#[test]
+fn test_enums_with_other_variant() {
+    let mut enum_ = ApiEnum {
+        name: "Phase".into(),
+        is_integer: false,
+        variants: vec![EnumVariant {
+            name: "Running".into(),
+            value: "Running".into(),
+            ty_path: None,
+        }],
+        has_other: true,
+        tag: None,
+        base: None,
+    };
+    assert!(enum_.to_string().contains(
+        "pub enum Phase {
+    Running,
+    #[serde(untagged)]
+    Other(String),
+}
+"
+    ));
+
+    enum_.is_integer = true;
+    enum_.variants[0].value = "1".into();
+    let contents = enum_.to_string();
+    assert!(contents.contains("#[serde(from = \"i64\", into = \"i64\")]"));
+    assert!(contents.contains("            value => Phase::Other(value),\n"));
+    assert!(contents.contains("            Phase::Other(value) => value,\n"));
+}
+
+#[test]
+fn test_composed_object() {
+    let dog = SCHEMA.definitions["pet.Dog"].read();
+    assert_eq!(dog.all_of.len(), 2);
+    assert_eq!(dog.all_of[0].read().name(), Some("pet.Pet")); // resolved
+
+    let contents = read_generated("pet/dog.rs");
+    assert_eq!(
+        contents.find(
+            "#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct Dog {
+    pub bark: Option<bool>,
+    pub breed: String,
+    pub category: Option<crate::pet::category::Category>,
+    pub id: Option<i64>,
+    pub name: String,
+    pub tags: Option<Vec<String>>,
+}
+"
+        ),
+        Some(0)
+    );
+}
+
+#[test]
+fn test_discriminator_enum() {
+    let payment = SCHEMA.definitions["pet.Payment"].read();
+    assert_eq!(payment.subtypes(), &["pet.BankPayment", "pet.CardPayment"]);
+
+    let contents = read_generated("pet/payment.rs");
+    assert_eq!(
+        contents,
+        "#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = \"kind\")]
+pub enum Payment {
+    #[serde(rename = \"pet.Payment\")]
+    Payment(crate::pet::payment::PaymentBase),
+    #[serde(rename = \"pet.BankPayment\")]
+    BankPayment(crate::pet::bank_payment::BankPayment),
+    #[serde(rename = \"pet.CardPayment\")]
+    CardPayment(crate::pet::card_payment::CardPayment),
+}
+
+impl Default for Payment {
+    fn default() -> Self {
+        Payment::Payment(Default::default())
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct PaymentBase {
+    pub amount: f64,
+}
+"
+    );
+
+    // Discriminator is handled by the enum.
+    let contents = read_generated("pet/card_payment.rs");
+    assert_eq!(
+        contents.find(
+            "#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct CardPayment {
+    pub amount: f64,
+    #[serde(rename = \"cardNumber\")]
+    pub card_number: String,
+}
+"
+        ),
+        Some(0)
+    );
+
+    // Subtypes with the same name (in different namespaces) get unique variants.
+    let spec = r##"{
+  "swagger": "2.0",
+  "definitions": {
+    "pet.Pet": {
+      "type": "object",
+      "discriminator": "kind",
+      "required": ["kind"],
+      "properties": { "kind": { "type": "string" } }
+    },
+    "cat.Pet": { "allOf": [{ "$ref": "#/definitions/pet.Pet" }] },
+    "dog.Dog": { "allOf": [{ "$ref": "#/definitions/pet.Pet" }] }
+  },
+  "paths": {}
+}"##;
+    let api = v2::from_str::<DefaultSchema>(spec)
+        .expect("spec")
+        .resolve()
+        .expect("resolving");
+    let tree = generate_in_memory(&api, |s| {
+        s.working_dir = "tagged".into();
+        s.root_module = Some("pet".into());
+    })
+    .expect("creating definitions");
+
+    let contents = tree.file("tagged/pet/pet.rs").expect("missing mod");
+    assert!(contents.contains(
+        "pub enum Pet {
+    #[serde(rename = \"pet.Pet\")]
+    PetPet(crate::pet::pet::PetBase),
+    #[serde(rename = \"cat.Pet\")]
+    CatPet(crate::cat::pet::Pet),
+    #[serde(rename = \"dog.Dog\")]
+    Dog(crate::dog::dog::Dog),
+}
+"
+    ));
+    assert!(contents.contains("pub struct PetBase {}\n"));
+}
+
+#[test]
+fn test_root_object() {
+    use paperclip_openapi::v2::models::{
+        ApiKeyIn, HttpMethod, MediaType, OperationProtocol, SecuritySchemeType,
+    };
+
+    let info = SCHEMA.info.as_ref().unwrap();
+    assert_eq!(info.title, "Pet store");
+    assert_eq!(info.version, "1.0");
+    assert_eq!(info.license.as_ref().unwrap().name, "MIT");
+    assert_eq!(SCHEMA.tags[0].name, "pets");
+    let scheme = &SCHEMA.security_definitions["api_key"];
+    assert_eq!(scheme.type_, SecuritySchemeType::ApiKey);
+    assert_eq!(scheme.in_, Some(ApiKeyIn::Header));
+    assert!(SCHEMA.security[0]["api_key"].is_empty());
+    assert_eq!(SCHEMA.base_url().unwrap(), "https://pets.example.com/v1");
+
+    // Top-level defaults are used only when the operation doesn't have its own.
+    let get = &SCHEMA.paths["/pets/{id}"].methods[&HttpMethod::Get];
+    assert_eq!(
+        get.schemes,
+        &[OperationProtocol::Http, OperationProtocol::Https]
+    );
+    assert_eq!(
+        get.produces,
+        Some(vec![MediaType::new("application", "json")])
+    );
+    assert_eq!(get.consumes, Some(vec![]));
+    let post = &SCHEMA.paths["/pets"].methods[&HttpMethod::Post];
+    assert_eq!(post.schemes, &[OperationProtocol::Https]);
+    assert_eq!(
+        post.consumes,
+        Some(vec![MediaType::new("application", "json")])
+    );
+
+    // Empty lists clear the top-level defaults.
+    let spec = r#"
+swagger: "2.0"
+produces: [application/json]
+paths:
+  /pets:
+    get:
+      responses: {}
+    delete:
+      produces: []
+      responses: {}
+"#;
+    let api: Api<DefaultSchema> = v2::from_str(spec).expect("deserializing spec");
+    let api = api.resolve().expect("resolution");
+    let map = &api.paths["/pets"];
+    assert_eq!(
+        map.methods[&HttpMethod::Get].produces,
+        Some(vec![MediaType::new("application", "json")])
+    );
+    assert_eq!(map.methods[&HttpMethod::Delete].produces, Some(vec![]));
+}
+
+#[test]
+fn test_default_client() {
+    let contents = read_generated("pet/mod.rs");
+    assert!(contents.contains(
+        "
+    pub const DEFAULT_BASE_URL: &str = \"https://pets.example.com/v1\";
+
+    impl Default for Client {
+        fn default() -> Self {
+            Client::new(DEFAULT_BASE_URL)
+        }
+    }
+}
+"
+    ));
+}
+
+#[test]
+fn test_generate_in_another_thread() {
+    let mut state = EmitterState::default();
+    state.working_dir = std::env::temp_dir().join("paperclip-pet-thread");
+    state.format = None;
+    let _ = std::fs::remove_dir_all(&state.working_dir);
+    std::fs::create_dir_all(&state.working_dir).expect("creating working dir");
+    let working_dir = state.working_dir.clone();
+
+    let emitter = DefaultEmitter::from(state);
+    std::thread::spawn(move || emitter.generate(&SCHEMA))
+        .join()
+        .expect("emitter thread")
+        .expect("creating definitions");
+
+    let mut contents = String::new();
+    File::open(working_dir.join("pet/pet.rs"))
+        .expect("missing mod")
+        .read_to_string(&mut contents)
+        .expect("reading mod");
+    assert_eq!(contents, read_generated("pet/pet.rs"));
+}
+
+#[test]
+fn test_generate_in_memory() {
+    let working_dir = std::env::temp_dir().join("paperclip-pet-memory");
+    let _ = std::fs::remove_dir_all(&working_dir);
+    let tree = generate_in_memory(&SCHEMA, |s| {
+        s.working_dir = working_dir.clone();
+        s.format = None;
+    })
+    .expect("creating definitions");
+    assert!(!working_dir.exists());
+
+    let files = tree.files();
+    assert!(files.contains_key(&working_dir.join("pet/mod.rs")));
+    assert_eq!(
+        tree.file(working_dir.join("pet/pet.rs"))
+            .expect("missing mod"),
+        read_generated("pet/pet.rs")
+    );
+
+    tree.write_to_disk().expect("writing files");
+    let mut contents = String::new();
+    File::open(working_dir.join("pet/pet.rs"))
+        .expect("missing mod")
+        .read_to_string(&mut contents)
+        .expect("reading mod");
+    assert_eq!(contents, read_generated("pet/pet.rs"));
+}
+
+#[test]
+fn test_deterministic_output() {
+    let generate = || {
+        let tree = generate_in_memory(&SCHEMA, |s| {
+            s.working_dir = "pet-deterministic".into();
+            s.format = None;
+        });
+        tree.expect("creating definitions").files()
+    };
+
+    let files = generate();
+    assert_eq!(files, generate());
+    let root_mod = &files[Path::new("pet-deterministic/pet/mod.rs")];
+    assert!(root_mod.starts_with(
+        "pub mod animal;
+pub mod bank_payment;
+pub mod card_payment;
+pub mod category;
+"
+    ));
+    assert!(read_generated("pet/mod.rs").contains(
+        "
+pub mod prelude {
+    #[derive(Debug, Clone)]
+    pub struct MissingAmount;
+    #[derive(Debug, Clone)]
+    pub struct AmountExists;
+    #[derive(Debug, Clone)]
+    pub struct MissingBreed;
+"
+    ));
+}
+
+#[test]
+fn test_formatted_output() {
+    let generate = |options| {
+        let tree = generate_in_memory(&SCHEMA, |s| {
+            s.working_dir = "pet-formatted".into();
+            s.format = Some(options);
+        });
+        let tree = tree.expect("creating definitions");
+        tree.file("pet-formatted/pet/pet.rs").expect("missing mod")
+    };
+
+    let long_line = "    pub fn update_pet() -> PetPatchBuilder1<crate::pet::prelude::MissingId, crate::pet::prelude::MissingName> {\n";
+    assert!(read_generated("pet/pet.rs").contains(long_line));
+
+    let contents = generate(FormatOptions::default());
+    assert!(!contents.contains(long_line));
+    assert!(contents.contains("    pub fn update_pet("));
+
+    let config_path = std::env::temp_dir().join("paperclip-rustfmt.toml");
+    std::fs::write(&config_path, "max_width = 120\n").expect("writing config");
+    let options = FormatOptions {
+        config_path: Some(config_path),
+        ..Default::default()
+    };
+    assert!(generate(options).contains(long_line));
+
+    // Missing binary leaves the code unformatted.
+    if !cfg!(feature = "codegen-fmt") {
+        let options = FormatOptions {
+            binary: "paperclip-missing-rustfmt".into(),
+            ..Default::default()
+        };
+        assert!(generate(options).contains(long_line));
+    }
+}
+
+#[test]
+fn test_invalid_path_parameters() {
+    use paperclip_openapi::PaperClipError;
+
+    let generate = |required: bool| {
+        let spec = format!(
+            r##"
+swagger: "2.0"
+definitions:
+  Pet:
+    type: object
+    properties:
+      name:
+        type: string
+paths:
+  /pets/{{id}}/{{kind}}:
+    get:
+      parameters:
+        - in: path
+          name: id
+          type: string
+          required: {}
+      responses:
+        "200":
+          schema:
+            $ref: "#/definitions/Pet"
+"##,
+            required
+        );
+        let api: Api<DefaultSchema> = v2::from_str(&spec).expect("deserializing spec");
+        generate_in_memory(&api.resolve().expect("resolution"), |_| ())
+            .expect_err("invalid path parameters")
+    };
+
+    let err = generate(true);
+    assert_eq!(
+        err.location().map(|l| &*l.pointer),
+        Some("/paths/~1pets~1{id}~1{kind}/get")
+    );
+    match err.root() {
+        PaperClipError::MissingPathParameter(name, path) => {
+            assert_eq!(name, "kind");
+            assert_eq!(path, "/pets/{id}/{kind}");
+        }
+        e => panic!("unexpected error: {}", e),
+    }
+
+    let err = generate(false);
+    assert_eq!(
+        err.location().map(|l| &*l.pointer),
+        Some("/paths/~1pets~1{id}~1{kind}/get/parameters/0")
+    );
+    match err.root() {
+        PaperClipError::OptionalPathParameter(name, path) => {
+            assert_eq!(name, "id");
+            assert_eq!(path, "/pets/{id}/{kind}");
+        }
+        e => panic!("unexpected error: {}", e),
+    }
+}
+
+#[test]
+fn test_enums_with_unsupported_values() {
+    let spec = r#"
+swagger: "2.0"
+definitions:
+  pet.Pet:
+    type: object
+    properties:
+      kind:
+        type: string
+        enum: [dog, null]
+      color:
+        type: string
+        enum: [{name: red}]
+paths: {}
+"#;
+    let api: Api<DefaultSchema> = v2::from_str(spec).expect("deserializing spec");
+    let tree =
+        generate_in_memory(&api.resolve().expect("resolution"), |_| ()).expect("generating code");
+
+    // Such enums are skipped.
+    let contents = tree.file("./pet/pet.rs").expect("generated definition");
+    assert!(contents.contains("    pub kind: Option<String>,\n"));
+    assert!(!contents.contains("pub enum"));
+}
+
+#[test]
+fn test_cyclic_all_of_in_emitter() {
+    use paperclip_openapi::PaperClipError;
+
+    let spec = r#"
+swagger: "2.0"
+definitions:
+  pet.Cat:
+    type: object
+    properties:
+      name:
+        type: string
+  pet.Dog:
+    type: object
+    properties:
+      age:
+        type: integer
+paths: {}
+"#;
+    // The resolver rejects these, so we wire up the cycle ourselves.
+    let api: Api<DefaultSchema> = v2::from_str(spec).expect("deserializing spec");
+    let (cat, dog) = (&api.definitions["pet.Cat"], &api.definitions["pet.Dog"]);
+    cat.write().set_name("pet.Cat");
+    dog.write().set_name("pet.Dog");
+    cat.write().all_of.push(dog.clone());
+    dog.write().all_of.push(cat.clone());
+
+    let err = generate_in_memory(&api, |_| ()).expect_err("cyclic allOf");
+    match err.root() {
+        PaperClipError::CyclicAllOf(name) => assert_eq!(name, "pet.Cat"),
+        e => panic!("unexpected error: {}", e),
+    }
+}
@@ -0,0 +1,72 @@
+#[macro_use]
+extern crate lazy_static;
+
+use paperclip_openapi::v2::{
+    self,
+    codegen::{DefaultEmitter, Emitter, EmitterState},
+    models::{Api, DefaultSchema},
+};
+
+use std::fs;
+
+lazy_static! {
+    static ref ROOT: String = String::from(env!("CARGO_MANIFEST_DIR"));
+    static ref CODEGEN: () = {
+        let fd = fs::File::open(ROOT.clone() + "/tests/pet-v2.yaml").expect("file?");
+        let raw: Api<DefaultSchema> = v2::from_reader(fd).expect("deserializing spec");
+        let schema = raw.resolve().expect("resolution");
+
+        let mut state = EmitterState::default();
+        state.working_dir = (&*ROOT).into();
+        state.working_dir.push("tests");
+        state.working_dir.push("test_prefix");
+        state.working_dir.push("generated");
+        state.mod_prefix = "crate::generated::".into();
+
+        let emitter = DefaultEmitter::from(state);
+        emitter.generate(&schema).expect("creating definitions");
+    };
+}
+
+fn read_generated(rel_path: &str) -> String {
+    let _ = &*CODEGEN;
+    let path = ROOT.clone() + "/tests/test_prefix/generated/" + rel_path;
+    fs::read_to_string(&path).expect("missing mod")
+}
+
+#[test]
+fn test_prefixed_type_paths() {
+    let contents = read_generated("pet/pet.rs");
+    assert!(
+        contents.contains("    pub category: Option<crate::generated::pet::category::Category>,\n")
+    );
+    assert!(contents.contains(
+        "    pub fn add_pet() -> PetPostBuilder<crate::generated::pet::prelude::MissingName> {"
+    ));
+    assert!(contents.contains(
+        "    pub fn category(mut self, value: impl Into<crate::generated::pet::category::Category>) -> Self {"
+    ));
+
+    let contents = read_generated("pet/category.rs");
+    assert!(
+        contents.contains("    pub kind: Option<crate::generated::pet::category::CategoryKind>,\n")
+    );
+
+    let contents = read_generated("pet/payment.rs");
+    assert!(
+        contents.contains("    BankPayment(crate::generated::pet::bank_payment::BankPayment),\n")
+    );
+
+    let contents = read_generated("pet/animal.rs");
+    assert_eq!(
+        contents,
+        "pub type Animal = crate::generated::pet::pet::Pet;\n"
+    );
+}
+
+#[test]
+fn test_prefixed_helper_modules() {
+    let contents = read_generated("pet/pet.rs");
+    assert!(contents.contains("crate::generated::pet::client::ApiClient"));
+    assert!(!contents.contains("crate::pet::"));
+}
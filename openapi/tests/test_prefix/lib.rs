@@ -0,0 +1,4 @@
+#[macro_use] extern crate serde_derive;
+
+#[allow(dead_code)]
+mod generated;
@@ -0,0 +1,349 @@
+#[macro_use]
+extern crate lazy_static;
+#[macro_use]
+extern crate serde_json;
+
+use paperclip_openapi::source::{Position, SourceMap};
+use paperclip_openapi::v2::codegen::{DefaultEmitter, Emitter, EmitterState, MemoryTree};
+use paperclip_openapi::v2::loader::DirLoader;
+use paperclip_openapi::v2::models::{DataType, HttpMethod, ParameterIn, StatusCode};
+use paperclip_openapi::v2::{self, Api, DefaultSchema, Schema};
+use paperclip_openapi::PaperClipError;
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::Cursor;
+use std::sync::Arc;
+
+lazy_static! {
+    static ref ROOT: String = String::from(env!("CARGO_MANIFEST_DIR"));
+    static ref SCHEMA: Api<DefaultSchema> = {
+        let fd = File::open(ROOT.clone() + "/tests/ext-refs/api.yaml").expect("file?");
+        let loader = DirLoader::new(ROOT.clone() + "/tests/ext-refs");
+        let raw: Api<DefaultSchema> =
+            v2::from_reader_with_loader(fd, &loader).expect("deserializing spec");
+        raw.resolve().expect("resolution")
+    };
+}
+
+#[test]
+fn test_external_definitions() {
+    let names = SCHEMA
+        .definitions
+        .keys()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    // `Error` from the other document conflicts with the one in root.
+    assert_eq!(
+        names,
+        &["Address", "Error", "Owner", "models_Error", "pet/Pet"]
+    );
+
+    let owner = SCHEMA.definitions["Owner"].read();
+    let props = owner.properties.as_ref().unwrap();
+    let address = props["address"].read();
+    assert_eq!(address.name(), Some("Address"));
+    let error = props["error"].read();
+    assert_eq!(error.name(), Some("models_Error"));
+    let code = &error.properties.as_ref().unwrap()["code"];
+    assert_eq!(code.read().data_type, Some(DataType::Integer));
+}
+
+#[test]
+fn test_colliding_definitions_in_namespaces() {
+    let mut docs = BTreeMap::new();
+    docs.insert(
+        "common/models.json".to_owned(),
+        json!({ "definitions": { "pet.Error": { "type": "object" } } }),
+    );
+
+    let spec = json!({
+        "swagger": "2.0",
+        "definitions": {
+            "pet.Error": { "type": "object" },
+            "pet.Pet": {
+                "type": "object",
+                "properties": {
+                    "error": { "$ref": "common/models.json#/definitions/pet.Error" },
+                },
+            },
+        },
+        "paths": {},
+    });
+    let api: Api<DefaultSchema> =
+        v2::from_reader_with_loader(Cursor::new(spec.to_string()), &docs).expect("spec");
+    let names = api
+        .definitions
+        .keys()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    // Renamed definition stays in the namespace of the original.
+    assert_eq!(names, &["pet.Error", "pet.Pet", "pet.models_Error"]);
+
+    // ... so that there's still only one root module.
+    let tree = Arc::new(MemoryTree::default());
+    let mut state = EmitterState::default();
+    state.working_dir = "collisions".into();
+    state.sink = tree.clone();
+    DefaultEmitter::from(state)
+        .generate(&api.resolve().expect("resolving"))
+        .expect("generating code");
+    let contents = tree.file("collisions/pet/mod.rs").expect("missing mod");
+    assert!(contents.contains("pub mod models_error;\n"));
+    assert!(contents.contains("\npub mod client {\n"));
+}
+
+#[test]
+fn test_nested_pointers_and_escaping() {
+    let pet = SCHEMA.definitions["pet/Pet"].read();
+    let props = pet.properties.as_ref().unwrap();
+    let tags = props["tags"].read();
+    assert!(tags.name().is_none()); // inlined
+    assert_eq!(tags.data_type, Some(DataType::Array));
+    let owner = props["owner"].read();
+    assert_eq!(owner.name(), Some("Owner"));
+
+    let op = &SCHEMA.paths["/pets/{id}"].methods[&HttpMethod::Get];
+    let schema = op.responses[&StatusCode::Code(200)]
+        .schema
+        .as_ref()
+        .unwrap()
+        .read();
+    assert_eq!(schema.name(), Some("pet/Pet"));
+    assert!(schema.properties.as_ref().unwrap().contains_key("owner"));
+}
+
+#[test]
+fn test_parameter_and_response_references() {
+    let op = &SCHEMA.paths["/pets/{id}"].methods[&HttpMethod::Get];
+    let params = op.parameters.as_ref().unwrap();
+    assert_eq!(params[0].name, "id");
+    assert_eq!(params[0].in_, ParameterIn::Path);
+    assert_eq!(params[1].name, "verbose");
+    assert_eq!(params[1].data_type, Some(DataType::Boolean));
+
+    let response = &op.responses[&StatusCode::Code(404)];
+    assert_eq!(response.description.as_ref().unwrap(), "Pet not found");
+    let schema = response.schema.as_ref().unwrap().read();
+    assert_eq!(schema.name(), Some("models_Error"));
+
+    // Top-level parameters and responses are resolved too.
+    assert_eq!(SCHEMA.parameters["PetId"].name, "id");
+    let schema = SCHEMA.responses["NotFound"].schema.as_ref().unwrap().read();
+    assert_eq!(schema.name(), Some("models_Error"));
+}
+
+#[test]
+fn test_in_memory_loader_and_errors() {
+    let mut docs = BTreeMap::new();
+    docs.insert(
+        "other.json".to_owned(),
+        json!({ "definitions": { "Foo": { "type": "string" } } }),
+    );
+
+    let spec = json!({
+        "swagger": "2.0",
+        "definitions": {
+            "Bar": { "$ref": "other.json#/definitions/Foo" },
+        },
+        "paths": {},
+    });
+    let api: Api<DefaultSchema> =
+        v2::from_reader_with_loader(Cursor::new(spec.to_string()), &docs).expect("spec");
+    assert!(api.definitions.contains_key("Foo"));
+
+    let spec = json!({
+        "swagger": "2.0",
+        "definitions": {},
+        "paths": {},
+        "parameters": {
+            "A": { "$ref": "#/parameters/B" },
+            "B": { "$ref": "#/parameters/A" },
+        },
+    });
+    let err =
+        v2::from_reader_with_loader::<_, DefaultSchema, _>(Cursor::new(spec.to_string()), &docs)
+            .unwrap_err();
+    match err {
+        PaperClipError::ReferenceCycle(_) => (),
+        e => panic!("unexpected result: {:?}", e),
+    }
+
+    let spec = json!({
+        "swagger": "2.0",
+        "definitions": { "Bar": { "$ref": "missing.json#/definitions/Foo" } },
+        "paths": {},
+    });
+    let err = v2::from_reader::<_, DefaultSchema>(Cursor::new(spec.to_string())).unwrap_err();
+    match err {
+        PaperClipError::MissingDocument(ref p) if p == "missing.json" => (),
+        e => panic!("unexpected result: {:?}", e),
+    }
+}
+
+#[test]
+fn test_alias_cycles() {
+    let spec = json!({
+        "swagger": "2.0",
+        "definitions": {
+            "Foo": { "$ref": "#/definitions/Bar" },
+            "Bar": { "$ref": "#/definitions/Baz" },
+            "Baz": { "$ref": "#/definitions/Foo" },
+        },
+        "paths": {},
+    });
+    let api: Api<DefaultSchema> = v2::from_reader(Cursor::new(spec.to_string())).expect("spec");
+    let err = api.resolve().unwrap_err();
+    assert_eq!(err.location().unwrap().pointer, "/definitions/Bar");
+    match err {
+        PaperClipError::Located(_, ref e) => match **e {
+            PaperClipError::InDefinition(ref n, _) if n == "Bar" => (),
+            ref e => panic!("unexpected context: {:?}", e),
+        },
+        ref e => panic!("unexpected error: {:?}", e),
+    }
+
+    // Wrappers expose the inner errors as sources, and the alternate format
+    // includes the whole chain.
+    assert!(err.to_string().starts_with("At "));
+    let context = err.source().expect("context");
+    assert_eq!(context.to_string(), "In definition Bar");
+    let actual = context.source().expect("actual error");
+    assert_eq!(actual.to_string(), "Definition Bar is an alias of itself");
+    assert!(actual.source().is_none());
+    assert!(
+        format!("{:#}", err).starts_with("In definition Bar: Definition Bar is an alias of itself")
+    );
+
+    match err.into_inner() {
+        PaperClipError::CyclicDefinitionAlias(ref n) if n == "Bar" => (),
+        e => panic!("unexpected result: {:?}", e),
+    }
+}
+
+#[test]
+fn test_all_of_cycles() {
+    let spec = json!({
+        "swagger": "2.0",
+        "definitions": {
+            "Animal": { "type": "object", "discriminator": "kind" },
+            "Cat": { "allOf": [{ "$ref": "#/definitions/Animal" }, { "$ref": "#/definitions/Pet" }] },
+            "Pet": { "allOf": [{ "$ref": "#/definitions/Cat" }] },
+        },
+        "paths": {},
+    });
+    let api: Api<DefaultSchema> = v2::from_reader(Cursor::new(spec.to_string())).expect("spec");
+    let err = api.resolve().unwrap_err();
+    assert_eq!(err.location().unwrap().pointer, "/definitions/Cat");
+    match err.into_inner() {
+        PaperClipError::CyclicAllOf(ref n) if n == "Cat" => (),
+        e => panic!("unexpected result: {:?}", e),
+    }
+}
+
+#[test]
+fn test_error_locations() {
+    let spec = "\
+swagger: \"2.0\"
+definitions:
+  Pet:
+    type: object
+    properties:
+      category:
+        $ref: \"#/definitions/Category\"
+paths: {}
+";
+    let api: Api<DefaultSchema> = v2::from_str(spec).expect("spec");
+    let source = api.source.clone().expect("source map");
+    let err = api.resolve().unwrap_err();
+    let location = err.location().expect("location");
+    assert_eq!(
+        location.pointer,
+        "/definitions/Pet/properties/category/$ref"
+    );
+    assert_eq!(location.position, Some(Position { line: 7, column: 9 }));
+    // Missing nodes are located through their ancestors.
+    assert_eq!(
+        source.position("/definitions/Pet/properties/name"),
+        Some(Position { line: 5, column: 5 })
+    );
+    assert_eq!(
+        source.render(&err),
+        "\
+In definition Pet: Definition missing: Category
+ --> line 7, column 9 (/definitions/Pet/properties/category/$ref)
+  |
+7 |         $ref: \"#/definitions/Category\"
+  |         ^"
+    );
+
+    // JSON documents are scanned too.
+    let api: Api<DefaultSchema> = v2::from_slice(
+        br##"{"swagger": "2.0", "paths": {"/pets": {"get": {"responses": {
+  "200": {"schema": {"$ref": "#/definitions/Pet"}}}}}}}"##,
+    )
+    .expect("spec");
+    let err = api.resolve().unwrap_err();
+    match err.root() {
+        PaperClipError::MissingDefinition(ref n) if n == "Pet" => (),
+        e => panic!("unexpected result: {:?}", e),
+    }
+
+    assert_eq!(
+        format!("{:#}", err),
+        "In operation GET \"/pets\": Definition missing: Pet (at line 2, column 22 (/paths/~1pets/get/responses/200/schema/$ref))"
+    );
+}
+
+#[test]
+fn test_load_error_locations() {
+    // Errors from checking the spec.
+    let spec = b"swagger: \"2.0\"\nconsumes: [json]\npaths: {}\n";
+    let err = v2::from_slice::<DefaultSchema>(spec).unwrap_err();
+    let location = err.location().expect("location");
+    assert_eq!(location.pointer, "/consumes/0");
+    assert_eq!(
+        location.position,
+        Some(Position {
+            line: 2,
+            column: 12
+        })
+    );
+
+    // Syntax errors.
+    let spec = b"{\n  \"swagger\": \"2.0\",\n  \"paths\": {,}\n}";
+    let err = paperclip_openapi::from_slice::<DefaultSchema>(spec).unwrap_err();
+    let location = err.location().expect("location");
+    assert_eq!(location.pointer, "");
+    assert_eq!(
+        location.position,
+        Some(Position {
+            line: 3,
+            column: 13
+        })
+    );
+    let source = SourceMap::from_slice(spec).expect("source map");
+    assert_eq!(
+        source.render(&err),
+        "\
+JSON error: key must be a string at line 3 column 13
+ --> line 3, column 13
+  |
+3 |   \"paths\": {,}
+  |             ^"
+    );
+
+    // Errors from deserializing the spec.
+    let spec = b"{\"swagger\": \"2.0\", \"paths\": {\"/pets\": {\"get\": {\"responses\": {},\n  \"operationId\": 42}}}}";
+    let err = v2::from_slice::<DefaultSchema>(spec).unwrap_err();
+    match err.root() {
+        PaperClipError::Json(_) => (),
+        e => panic!("unexpected result: {:?}", e),
+    }
+
+    assert_eq!(
+        err.location().and_then(|l| l.position).map(|p| p.line),
+        Some(2)
+    );
+}
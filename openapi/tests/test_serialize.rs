@@ -0,0 +1,174 @@
+#[macro_use]
+extern crate lazy_static;
+#[macro_use]
+extern crate paperclip_macros;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
+
+use paperclip_openapi::v2::loader::DirLoader;
+use paperclip_openapi::v2::models::HttpMethod;
+use paperclip_openapi::v2::{self, Api, DefaultSchema, Schema};
+use paperclip_openapi::{v3, SpecFormat};
+use serde_json::Value;
+
+use std::fs::File;
+use std::io::Cursor;
+
+lazy_static! {
+    static ref ROOT: String = String::from(env!("CARGO_MANIFEST_DIR"));
+}
+
+fn resolved_pet_spec() -> Api<DefaultSchema> {
+    let fd = File::open(ROOT.clone() + "/tests/pet-v2.yaml").expect("file?");
+    let raw: Api<DefaultSchema> = v2::from_reader(fd).expect("deserializing spec");
+    raw.resolve().expect("resolution")
+}
+
+fn to_value<S>(api: &Api<S>, format: SpecFormat) -> Value
+where
+    S: Schema + serde::Serialize,
+{
+    let mut buf = vec![];
+    v2::to_writer(&mut buf, api, format).expect("serializing spec");
+    match format {
+        SpecFormat::Json => serde_json::from_slice(&buf).expect("reading JSON"),
+        SpecFormat::Yaml => {
+            let value: serde_yaml::Value = serde_yaml::from_slice(&buf).expect("reading YAML");
+            serde_json::to_value(value).expect("converting YAML")
+        }
+    }
+}
+
+#[test]
+fn test_resolved_refs_are_preserved() {
+    let value = to_value(&resolved_pet_spec(), SpecFormat::Json);
+    let pet = &value["definitions"]["pet.Pet"];
+    assert_eq!(
+        pet["properties"]["category"],
+        json!({"$ref": "#/definitions/pet.Category"})
+    );
+    assert!(pet.get("$ref").is_none());
+    assert_eq!(
+        value["definitions"]["pet.Dog"]["allOf"][0],
+        json!({"$ref": "#/definitions/pet.Pet"})
+    );
+
+    let op = &value["paths"]["/pets"]["post"];
+    assert!(op["parameters"][0]["schema"].get("$ref").is_some());
+}
+
+#[test]
+fn test_round_trip() {
+    let api = resolved_pet_spec();
+    let mut buf = vec![];
+    v2::to_writer(&mut buf, &api, SpecFormat::Yaml).expect("serializing spec");
+
+    let raw: Api<DefaultSchema> = v2::from_reader(Cursor::new(buf)).expect("reading back");
+    let other = raw.resolve().expect("resolution");
+    assert_eq!(
+        api.definitions.keys().collect::<Vec<_>>(),
+        other.definitions.keys().collect::<Vec<_>>()
+    );
+    assert_eq!(
+        api.paths.keys().collect::<Vec<_>>(),
+        other.paths.keys().collect::<Vec<_>>()
+    );
+
+    let op = &other.paths["/pets"].methods[&HttpMethod::Post];
+    let schema = op.parameters.as_ref().unwrap()[0].schema.as_ref().unwrap();
+    assert_eq!(schema.read().name(), Some("pet.Pet"));
+    assert_eq!(
+        to_value(&api, SpecFormat::Json),
+        to_value(&other, SpecFormat::Json)
+    );
+}
+
+#[test]
+fn test_external_refs_are_normalized() {
+    let fd = File::open(ROOT.clone() + "/tests/ext-refs/api.yaml").expect("file?");
+    let loader = DirLoader::new(ROOT.clone() + "/tests/ext-refs");
+    let raw: Api<DefaultSchema> =
+        v2::from_reader_with_loader(fd, &loader).expect("deserializing spec");
+    let value = to_value(&raw.resolve().expect("resolution"), SpecFormat::Json);
+
+    let get = &value["paths"]["/pets/{id}"]["get"];
+    assert_eq!(
+        get["responses"]["200"]["schema"],
+        json!({"$ref": "#/definitions/pet~1Pet"})
+    );
+    assert_eq!(
+        value["definitions"]["pet/Pet"]["properties"]["owner"],
+        json!({"$ref": "#/definitions/Owner"})
+    );
+    assert!(value["definitions"]["models_Error"].is_object());
+}
+
+#[test]
+fn test_v3_refs_point_to_definitions() {
+    let fd = File::open(ROOT.clone() + "/tests/pet-v3.yaml").expect("file?");
+    let raw: v3::Api<DefaultSchema> = v3::from_reader(fd).expect("deserializing spec");
+    let api = raw.resolve().expect("resolution");
+
+    // Serializing v3 schemas by themselves leaves dangling references...
+    let pet = serde_json::to_value(&api.components.schemas["pet.Pet"]).expect("serializing");
+    assert_eq!(pet, json!({"$ref": "#/definitions/pet.Pet"}));
+
+    // ... which are valid once the spec has been converted into v2.
+    let value = to_value(&Api::from(api), SpecFormat::Json);
+    assert_eq!(
+        value["definitions"]["pet.Pet"]["properties"]["category"],
+        json!({"$ref": "#/definitions/pet.Category"})
+    );
+    assert!(value["definitions"]["pet.Category"].is_object());
+}
+
+#[api_v2_schema]
+#[derive(Debug, Deserialize, Serialize)]
+struct CustomSchema {
+    #[serde(rename = "x-rust-type", skip_serializing_if = "Option::is_none")]
+    rust_type: Option<String>,
+}
+
+#[test]
+fn test_custom_fields() {
+    let spec = r##"{
+        "swagger": "2.0",
+        "definitions": {
+            "Id": {
+                "type": "string",
+                "x-rust-type": "uuid::Uuid"
+            },
+            "Thing": {
+                "type": "object",
+                "properties": {
+                    "id": {"$ref": "#/definitions/Id"},
+                    "next": {"$ref": "#/definitions/Thing"}
+                }
+            }
+        },
+        "paths": {}
+    }"##;
+
+    let raw: Api<CustomSchema> = v2::from_reader(Cursor::new(spec)).expect("deserializing spec");
+    let value = to_value(&raw, SpecFormat::Json);
+    assert_eq!(
+        value["definitions"]["Id"],
+        json!({"type": "string", "x-rust-type": "uuid::Uuid"})
+    );
+
+    // Cyclic definitions don't get inlined forever.
+    let value = to_value(&raw.resolve().expect("resolution"), SpecFormat::Yaml);
+    assert_eq!(
+        value["definitions"]["Thing"],
+        json!({
+            "type": "object",
+            "properties": {
+                "id": {"$ref": "#/definitions/Id"},
+                "next": {"$ref": "#/definitions/Thing"}
+            }
+        })
+    );
+    assert_eq!(value["definitions"]["Id"]["x-rust-type"], "uuid::Uuid");
+}
@@ -0,0 +1,250 @@
+#[macro_use]
+extern crate lazy_static;
+
+use paperclip_openapi::v2::codegen::{DefaultEmitter, Emitter, EmitterState, MemoryTree};
+use paperclip_openapi::v2::models::{
+    CollectionFormat, DataType, DataTypeFormat, HttpMethod, ParameterIn, StatusCode,
+};
+use paperclip_openapi::v2::Schema;
+use paperclip_openapi::v3::{self, models::ReferenceOr, Api, DefaultSchema};
+use paperclip_openapi::{v2, PaperClipError, Spec};
+
+use std::fs::File;
+use std::sync::Arc;
+
+lazy_static! {
+    static ref ROOT: String = String::from(env!("CARGO_MANIFEST_DIR"));
+    static ref SCHEMA: Api<DefaultSchema> = {
+        let fd = File::open(ROOT.clone() + "/tests/pet-v3.yaml").expect("file?");
+        let raw: Api<DefaultSchema> = v3::from_reader(fd).expect("deserializing spec");
+        raw.resolve().expect("resolution")
+    };
+}
+
+#[test]
+fn test_version_detection() {
+    let fd = File::open(ROOT.clone() + "/tests/pet-v2.yaml").expect("file?");
+    match paperclip_openapi::from_reader::<_, DefaultSchema>(fd).expect("v2 spec") {
+        Spec::V2(api) => assert_eq!(api.definitions.len(), 12),
+        _ => panic!("expected v2 spec"),
+    }
+
+    let fd = File::open(ROOT.clone() + "/tests/pet-v3.yaml").expect("file?");
+    match paperclip_openapi::from_reader::<_, DefaultSchema>(fd).expect("v3 spec") {
+        Spec::V3(api) => assert_eq!(api.servers[0].url, "https://pets.example.com/v1"),
+        _ => panic!("expected v3 spec"),
+    }
+}
+
+#[test]
+fn test_status_codes() {
+    let op = &SCHEMA.paths["/pets/{id}"].methods[&v3::models::HttpMethod::Get];
+    assert!(op.responses.contains_key(&StatusCode::Range(5)));
+
+    let spec = b"openapi: 3.0.0\npaths:\n  /pets:\n    get:\n      responses:\n        2XY: {}\n";
+    match paperclip_openapi::from_slice::<DefaultSchema>(spec).map_err(|e| e.into_inner()) {
+        Err(paperclip_openapi::PaperClipError::InvalidStatusCode(code, method, path)) => {
+            assert_eq!(
+                (code.as_str(), method.as_str(), path.as_str()),
+                ("2XY", "GET", "/pets")
+            );
+        }
+        r => panic!("unexpected result: {:?}", r),
+    }
+}
+
+#[test]
+fn test_component_references() {
+    let op = &SCHEMA.paths["/pets"].methods[&v3::models::HttpMethod::Post];
+    let param = op.parameters.as_ref().unwrap()[0]
+        .item()
+        .expect("resolved param");
+    assert_eq!(param.name, "X-Request-Id");
+
+    let body = op
+        .request_body
+        .as_ref()
+        .unwrap()
+        .item()
+        .expect("resolved body");
+    assert!(body.required);
+
+    let response = op.responses[&StatusCode::Code(200)]
+        .item()
+        .expect("resolved response");
+    let schema = response.content["application/json"]
+        .schema
+        .as_ref()
+        .unwrap()
+        .read();
+    assert!(schema.reference.is_none()); // this was a reference
+    assert_eq!(schema.description.as_ref().unwrap(), "A pet");
+
+    match SCHEMA.paths["/pets/{id}"].parameters.as_ref().unwrap()[0] {
+        ReferenceOr::Item(ref p) => assert_eq!(p.name, "id"),
+        _ => panic!("expected parameter"),
+    }
+
+    let header = response.headers["X-Rate-Limit"]
+        .item()
+        .expect("resolved header");
+    assert!(header.schema.is_some());
+
+    // Names in references are unescaped.
+    let spec = br##"
+openapi: 3.0.0
+paths:
+  /pets:
+    get:
+      parameters:
+        - $ref: "#/components/parameters/pet~1Id"
+      responses: {}
+components:
+  parameters:
+    pet/Id:
+      in: query
+      name: id
+"##;
+    let api: Api<DefaultSchema> = v3::from_reader(std::io::Cursor::new(&spec[..])).unwrap();
+    let api = api.resolve().expect("resolving escaped names");
+    let op = &api.paths["/pets"].methods[&v3::models::HttpMethod::Get];
+    assert_eq!(
+        op.parameters.as_ref().unwrap()[0].item().unwrap().name,
+        "id"
+    );
+}
+
+/// Generates code for the given (resolved) v3 spec in memory.
+fn generate(api: &Api<DefaultSchema>) -> Result<Arc<MemoryTree>, PaperClipError> {
+    let tree = Arc::new(MemoryTree::default());
+    let mut state = EmitterState::default();
+    state.working_dir = "v3".into();
+    state.sink = tree.clone();
+    let api: v2::Api<_> = api.clone().into();
+    DefaultEmitter::from(state).generate(&api)?;
+    Ok(tree)
+}
+
+#[test]
+fn test_schema_keywords() {
+    // v3 keywords are parsed along with the schema.
+    let pet = SCHEMA.components.schemas["pet.Pet"].read();
+    assert!(pet.required.contains("nickname"));
+    assert!(pet.properties.as_ref().unwrap()["nickname"]
+        .read()
+        .is_nullable());
+
+    // Nullable properties are optional.
+    let tree = generate(&SCHEMA).expect("generating code");
+    let contents = tree.file("v3/pet/pet.rs").expect("missing pet");
+    assert!(contents.contains("    pub name: String,\n"));
+    assert!(contents.contains("    pub nickname: Option<String>,\n"));
+
+    let spec = |schema: &str| {
+        format!(
+            "openapi: 3.0.0\npaths: {{}}\ncomponents:\n  schemas:\n    Pet:\n      type: object\n      properties:\n        not: {{}}\n        tag: {}\n",
+            schema
+        )
+    };
+
+    let load = |schema: &str| -> Api<DefaultSchema> {
+        let api = v3::from_reader(std::io::Cursor::new(spec(schema))).expect("parsing");
+        api.resolve().expect("resolution")
+    };
+
+    // `nullable` is ignored outside properties.
+    let schema = "{type: array, items: {type: string, nullable: true}}";
+    assert!(generate(&load(schema)).is_ok());
+
+    // Other keywords are rejected during codegen.
+    for (schema, keyword) in &[
+        ("{oneOf: [{type: string}, {type: integer}]}", "oneOf"),
+        ("{anyOf: [{type: string}]}", "anyOf"),
+        ("{type: array, items: {not: {type: string}}}", "not"),
+    ] {
+        let err = generate(&load(schema)).expect_err("unsupported keyword");
+        match err.root() {
+            PaperClipError::UnsupportedSchemaKeyword(k) => assert_eq!(k, keyword),
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        let pointer = &err.location().expect("location").pointer;
+        assert_eq!(pointer, "/definitions/Pet/properties/tag");
+    }
+}
+
+#[test]
+fn test_external_references() {
+    let spec = paperclip_openapi::from_path::<_, DefaultSchema>(
+        ROOT.clone() + "/tests/ext-refs/api-v3.yaml",
+    )
+    .expect("spec with refs");
+    let api = match spec.resolve().expect("resolution") {
+        Spec::V3(api) => api,
+        _ => panic!("expected v3 spec"),
+    };
+
+    let names = api
+        .components
+        .schemas
+        .keys()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    assert_eq!(names, &["Pet"]);
+    let pet = api.components.schemas["Pet"].read();
+    let owner = pet.properties.as_ref().unwrap()["owner"].read();
+    assert!(owner.properties.as_ref().unwrap().contains_key("address"));
+
+    let op = &api.paths["/pets/{id}"].methods[&v3::models::HttpMethod::Get];
+    assert_eq!(
+        op.parameters.as_ref().unwrap()[0].item().unwrap().name,
+        "id"
+    );
+}
+
+#[test]
+fn test_conversion_to_v2() {
+    let api: v2::Api<DefaultSchema> = SCHEMA.clone().into();
+    assert_eq!(api.info.as_ref().unwrap().title, "Pets");
+    assert_eq!(api.definitions.len(), 2);
+    assert_eq!(api.host.as_ref().unwrap(), "pets.example.com");
+    assert_eq!(api.base_path.as_ref().unwrap(), "/v1");
+    assert_eq!(api.base_url().unwrap(), "https://pets.example.com/v1");
+
+    let op = &api.paths["/pets"].methods[&HttpMethod::Post];
+    assert_eq!(op.operation_id.as_ref().unwrap(), "addPet");
+    let media_types: Vec<_> = op
+        .consumes
+        .iter()
+        .flatten()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(media_types, &["application/json", "application/xml"]);
+    assert!(op.produces.as_ref().unwrap()[0].is_json());
+    let header = &op.responses[&StatusCode::Code(200)].headers["X-Rate-Limit"];
+    assert_eq!(header.data_type, Some(DataType::Integer));
+    assert_eq!(header.format, Some(DataTypeFormat::Int32));
+
+    let params = op.parameters.as_ref().unwrap();
+    assert_eq!(params.len(), 2);
+    assert_eq!(params[0].in_, ParameterIn::Header);
+    assert_eq!(params[0].data_type, Some(DataType::String));
+    assert_eq!(params[1].in_, ParameterIn::Body);
+    let body = params[1].schema.as_ref().unwrap().read();
+    assert_eq!(body.description.as_ref().unwrap(), "A pet"); // JSON is preferred
+
+    let map = &api.paths["/pets/{id}"];
+    let id = &map.parameters.as_ref().unwrap()[0];
+    assert_eq!(id.in_, ParameterIn::Path);
+    assert_eq!(id.data_type, Some(DataType::Integer));
+    assert_eq!(id.format, Some(DataTypeFormat::Int64));
+
+    // Cookie parameters are skipped.
+    let op = &map.methods[&HttpMethod::Get];
+    let params = op.parameters.as_ref().unwrap();
+    assert_eq!(params.len(), 3);
+    // Arrays are exploded by default for query parameters.
+    assert_eq!(params[1].collection_format, Some(CollectionFormat::Multi));
+    assert_eq!(params[2].collection_format, Some(CollectionFormat::Pipes));
+    assert!(op.responses[&StatusCode::Code(200)].schema.is_some());
+}
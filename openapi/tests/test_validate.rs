@@ -0,0 +1,148 @@
+use paperclip_openapi::v2::validator::{Diagnostic, DiagnosticKind, Severity};
+use paperclip_openapi::v2::{self, Api, DefaultSchema};
+
+const SPEC: &str = r##"
+swagger: "2.0"
+definitions:
+  Pet:
+    type: object
+    properties:
+      id:
+        type: string
+        format: snowflake
+  Unused:
+    type: object
+  Alias:
+    $ref: "#/definitions/Pet"
+parameters:
+  Limit:
+    in: query
+    name: limit
+    type: integer
+paths:
+  /pets/{id}/{kind}:
+    parameters:
+      - in: path
+        name: id
+        type: string
+    get:
+      operationId: getPet
+      responses:
+        "200":
+          description: Found pet
+          schema:
+            $ref: "#/definitions/Alias"
+          headers:
+            X-Id:
+              type: string
+              format: snowflake
+    put:
+      operationId: getPet
+      consumes:
+        - application/json
+      parameters:
+        - in: body
+          name: pet
+          schema:
+            $ref: "#/definitions/Pet"
+        - in: body
+          name: other
+          schema:
+            $ref: "#/definitions/Pet"
+        - in: formData
+          name: name
+          type: string
+        - in: query
+          name: fields
+          type: array
+        - in: path
+          name: kind
+          type: string
+          required: true
+      responses:
+        "204":
+          description: Updated
+"##;
+
+fn diagnostic(location: &str, kind: DiagnosticKind) -> Diagnostic {
+    Diagnostic {
+        location: location.into(),
+        kind,
+    }
+}
+
+#[test]
+fn test_all_problems() {
+    let api: Api<DefaultSchema> = v2::from_str(SPEC).expect("deserializing spec");
+    let diagnostics = v2::validate(&api);
+    assert_eq!(
+        diagnostics,
+        vec![
+            diagnostic(
+                "/definitions/Pet/properties/id/format",
+                DiagnosticKind::UnknownFormat("snowflake".into())
+            ),
+            diagnostic(
+                "/paths/~1pets~1{id}~1{kind}/parameters/0",
+                DiagnosticKind::OptionalPathParameter("id".into())
+            ),
+            diagnostic(
+                "/paths/~1pets~1{id}~1{kind}/get",
+                DiagnosticKind::UndeclaredPathParameter("kind".into())
+            ),
+            diagnostic(
+                "/paths/~1pets~1{id}~1{kind}/get/responses/200/headers/X-Id/format",
+                DiagnosticKind::UnknownFormat("snowflake".into())
+            ),
+            diagnostic(
+                "/paths/~1pets~1{id}~1{kind}/put/operationId",
+                DiagnosticKind::DuplicateOperationId("getPet".into())
+            ),
+            diagnostic(
+                "/paths/~1pets~1{id}~1{kind}/put/parameters/3",
+                DiagnosticKind::InvalidParameter(
+                    "Mising item schema for array: Some(\"fields\")".into()
+                )
+            ),
+            diagnostic(
+                "/paths/~1pets~1{id}~1{kind}/put/parameters/1",
+                DiagnosticKind::MultipleBodyParameters
+            ),
+            diagnostic(
+                "/paths/~1pets~1{id}~1{kind}/put",
+                DiagnosticKind::BodyWithFormData
+            ),
+            diagnostic(
+                "/definitions/Unused",
+                DiagnosticKind::UnreferencedDefinition("Unused".into())
+            ),
+        ]
+    );
+
+    assert_eq!(diagnostics[0].kind.severity(), Severity::Warning);
+    assert_eq!(diagnostics[1].kind.severity(), Severity::Error);
+    assert_eq!(
+        diagnostics[4].to_string(),
+        "/paths/~1pets~1{id}~1{kind}/put/operationId: Operation ID \"getPet\" has already been used"
+    );
+}
+
+#[test]
+fn test_valid_spec() {
+    let api: Api<DefaultSchema> = v2::from_str(
+        r##"{
+  "swagger": "2.0",
+  "definitions": { "Pet": { "type": "object" } },
+  "paths": {
+    "/pets/{id}": {
+      "get": {
+        "parameters": [{ "in": "path", "name": "id", "type": "string", "required": true }],
+        "responses": { "200": { "schema": { "$ref": "#/definitions/Pet" } } }
+      }
+    }
+  }
+}"##,
+    )
+    .expect("deserializing spec");
+    assert!(v2::validate(&api).is_empty());
+}